@@ -5,6 +5,7 @@ stdenv.mkDerivation {
     buildInputs = [clang
     llvmPackages.libclang.lib
     cfitsio
+    hdf5
     pkg-config
     necpp
 
@@ -0,0 +1,90 @@
+#ifndef DBF_BEAM_SIMULATOR_H
+#define DBF_BEAM_SIMULATOR_H
+
+/* Generated by cbindgen from src/capi.rs, do not edit. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+typedef enum DbfStatus {
+  DBF_STATUS_OK = 0,
+  DBF_STATUS_NULL_POINTER = 1,
+  DBF_STATUS_INVALID_UTF8 = 2,
+  DBF_STATUS_PARSE_ERROR = 3,
+  DBF_STATUS_IO_ERROR = 4,
+  DBF_STATUS_LENGTH_MISMATCH = 5,
+  DBF_STATUS_INVALID_VALUE = 6,
+  DBF_STATUS_PANIC = 7,
+} DbfStatus;
+
+/**
+ * Element positions of an array, created from an array cfg and released
+ * with `dbf_array_free`.
+ */
+typedef struct DbfArray DbfArray;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Static description of a status code. Takes an int rather than a
+ * `DbfStatus`, as a caller may pass any value.
+ */
+const char *dbf_status_str(int status);
+
+/**
+ * Creates an array from the text of an array cfg YAML.
+ *
+ * # Safety
+ * `yaml` must be a NUL-terminated string and `out` a valid pointer.
+ */
+enum DbfStatus dbf_array_from_yaml(const char *yaml, struct DbfArray **out);
+
+/**
+ * Creates an array from an array cfg YAML file.
+ *
+ * # Safety
+ * `path` must be a NUL-terminated string and `out` a valid pointer.
+ */
+enum DbfStatus dbf_array_from_yaml_file(const char *path, struct DbfArray **out);
+
+/**
+ * # Safety
+ * `array` must come from one of the constructors and not be used afterwards.
+ */
+void dbf_array_free(struct DbfArray *array);
+
+/**
+ * # Safety
+ * `array` and `n` must be valid pointers.
+ */
+enum DbfStatus dbf_array_num_ants(const struct DbfArray *array, size_t *n);
+
+/**
+ * Writes the steering phase of every element for a pointing into `phi`,
+ * which must hold `n` values, `n` being the num of elements.
+ *
+ * # Safety
+ * `array` must be valid and `phi` must point to `n` doubles.
+ */
+enum DbfStatus dbf_steering_phases(const struct DbfArray *array, double az_from_north, double zenith, double freq_hz, double *phi, size_t n);
+
+/**
+ * Array factor towards one direction for element weights `w` and phases
+ * `phi`, both holding `n` values.
+ *
+ * # Safety
+ * `array` must be valid, `w` and `phi` must point to `n` doubles and `gain`
+ * to one double.
+ */
+enum DbfStatus dbf_gain(const struct DbfArray *array, const double *w, const double *phi, size_t n, double az_from_north, double zenith, double freq_hz, double *gain);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* DBF_BEAM_SIMULATOR_H */
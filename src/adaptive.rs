@@ -0,0 +1,208 @@
+//! Multi-resolution beams refined around the main lobe, stored as leaves of
+//! the HEALPix NESTED hierarchy as in multi-order coverage maps.
+
+use std::f64::consts::PI;
+
+use scorus::{
+    coordinates::{SphCoord, Vec3d},
+    healpix::{interp::get_interpol_ring, pix::pix2vec_ring, utils::nside2npix},
+};
+
+use crate::{
+    arbitrary_array::array_factor,
+    constants::LIGHT_SPEED,
+    error::{check_array_lists, DbfError, DbfResult},
+    grid::{nest2ring, HealpixNested},
+    utils::checked_npix2nside,
+};
+
+#[derive(Clone, Copy, Debug)]
+pub struct AdaptiveCfg {
+    /// nside evaluated everywhere
+    pub base_nside: usize,
+    /// finest nside of the refinement
+    pub max_nside: usize,
+    /// pixels with a gain above this fraction of the peak are refined
+    pub gain_threshold: f64,
+    /// pixels whose 4 siblings spread by more than this fraction of the peak
+    /// are refined
+    pub spread_threshold: f64,
+}
+
+impl AdaptiveCfg {
+    fn check(&self) -> DbfResult<()> {
+        for (what, nside) in [
+            ("base_nside", self.base_nside),
+            ("max_nside", self.max_nside),
+        ] {
+            if nside == 0 || !nside.is_power_of_two() {
+                return Err(DbfError::InvalidValue {
+                    what,
+                    reason: format!("must be a power of 2, found {}", nside),
+                });
+            }
+        }
+        if self.max_nside < self.base_nside {
+            return Err(DbfError::InvalidValue {
+                what: "max_nside",
+                reason: format!(
+                    "must not be smaller than base_nside {}, found {}",
+                    self.base_nside, self.max_nside
+                ),
+            });
+        }
+        Ok(())
+    }
+}
+
+/// A leaf of the hierarchy: NESTED pixel `ipix` at `nside`.
+#[derive(Clone, Copy, Debug)]
+pub struct BeamCell {
+    pub nside: usize,
+    pub ipix: usize,
+    pub value: f64,
+}
+
+impl BeamCell {
+    pub fn area(&self) -> f64 {
+        4.0 * PI / nside2npix(self.nside) as f64
+    }
+
+    pub fn centre(&self) -> Vec3d<f64> {
+        pix2vec_ring(self.nside, nest2ring(self.nside, self.ipix))
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct MultiResBeam {
+    pub cells: Vec<BeamCell>,
+}
+
+impl MultiResBeam {
+    /// Integral of the beam over the sphere.
+    pub fn integrate(&self) -> f64 {
+        self.cells.iter().map(|c| c.value * c.area()).sum()
+    }
+
+    pub fn peak(&self) -> f64 {
+        self.cells.iter().map(|c| c.value).fold(0.0, f64::max)
+    }
+
+    /// RING map at `nside`, cells finer than it being averaged and coarser
+    /// ones being repeated.
+    pub fn flatten(&self, nside: usize) -> DbfResult<Vec<f64>> {
+        let nested = HealpixNested::new(nside)?;
+        let npix = nside2npix(nside);
+        let mut sum = vec![0.0; npix];
+        let mut area = vec![0.0; npix];
+        for c in &self.cells {
+            if c.nside >= nside {
+                let shift = 2 * (c.nside / nside).trailing_zeros();
+                let p = c.ipix >> shift;
+                sum[p] += c.value * c.area();
+                area[p] += c.area();
+            } else {
+                let shift = 2 * (nside / c.nside).trailing_zeros();
+                let first = c.ipix << shift;
+                for p in first..first + (1 << shift) {
+                    sum[p] += c.value;
+                    area[p] += 1.0;
+                }
+            }
+        }
+        let map: Vec<f64> = sum
+            .iter()
+            .zip(area.iter())
+            .map(|(&s, &a)| if a > 0.0 { s / a } else { 0.0 })
+            .collect();
+        nested.map_nest2ring(&map)
+    }
+
+    /// Beam-weighted mean of a RING sky map of any nside, sampled at the
+    /// cell centres.
+    pub fn calc_ant_output(&self, sky: &[f64]) -> DbfResult<f64> {
+        let sky_nside = checked_npix2nside(sky.len())?;
+        let (mut num, mut norm) = (0.0, 0.0);
+        for c in &self.cells {
+            let (pix, wgt) = get_interpol_ring(sky_nside, SphCoord::from_vec3d(c.centre()));
+            let s: f64 = pix.iter().zip(wgt.iter()).map(|(&p, &w)| sky[p] * w).sum();
+            num += c.value * c.area() * s;
+            norm += c.value * c.area();
+        }
+        Ok(num / norm)
+    }
+}
+
+/// Evaluates `gain` at `cfg.base_nside` everywhere, then repeatedly splits
+/// groups of 4 sibling pixels that are bright or change steeply until
+/// `cfg.max_nside`.
+pub fn eval_adaptive<F>(cfg: &AdaptiveCfg, gain: F) -> DbfResult<MultiResBeam>
+where
+    F: Fn(&Vec3d<f64>) -> f64,
+{
+    cfg.check()?;
+    let eval = |nside: usize, ipix: usize| BeamCell {
+        nside,
+        ipix,
+        value: gain(&pix2vec_ring(nside, nest2ring(nside, ipix))),
+    };
+
+    let mut nside = cfg.base_nside;
+    let mut done = Vec::new();
+    // leaves at the current nside, in complete groups of 4 siblings
+    let mut active: Vec<BeamCell> = (0..nside2npix(nside)).map(|i| eval(nside, i)).collect();
+    let mut peak = active.iter().map(|c| c.value).fold(0.0, f64::max);
+
+    while nside < cfg.max_nside {
+        let mut next = Vec::new();
+        for group in active.chunks(4) {
+            let max = group
+                .iter()
+                .map(|c| c.value)
+                .fold(f64::NEG_INFINITY, f64::max);
+            let min = group.iter().map(|c| c.value).fold(f64::INFINITY, f64::min);
+            let steep = max - min > cfg.spread_threshold * peak;
+            for c in group {
+                if steep || c.value > cfg.gain_threshold * peak {
+                    next.extend((0..4).map(|k| eval(2 * nside, 4 * c.ipix + k)));
+                } else {
+                    done.push(*c);
+                }
+            }
+        }
+        nside *= 2;
+        peak = next.iter().map(|c| c.value).fold(peak, f64::max);
+        active = next;
+    }
+    done.extend(active);
+    Ok(MultiResBeam { cells: done })
+}
+
+/// Adaptive version of `calc_array_beam`.
+#[allow(clippy::too_many_arguments)]
+pub fn calc_array_beam_adaptive(
+    cfg: &AdaptiveCfg,
+    x_list: &[f64],
+    y_list: &[f64],
+    z_list: &[f64],
+    w_list: &[f64],
+    phi_list: &[f64],
+    freq_hz: f64,
+    ground_cut: bool,
+) -> DbfResult<MultiResBeam> {
+    check_array_lists(&[
+        ("x_list", x_list),
+        ("y_list", y_list),
+        ("z_list", z_list),
+        ("w_list", w_list),
+        ("phi_list", phi_list),
+    ])?;
+    let lambda = LIGHT_SPEED / freq_hz;
+    eval_adaptive(cfg, |pointing| {
+        if ground_cut && pointing[2] < 0.0 {
+            0.0
+        } else {
+            array_factor(pointing, x_list, y_list, z_list, w_list, phi_list, lambda)
+        }
+    })
+}
@@ -4,16 +4,16 @@ use num::complex::Complex;
 
 use crate::{
     constants::LIGHT_SPEED,
-    utils::{calc_averaged_ant_output, integrate_az},
+    error::{check_array_lists, DbfResult},
+    utils::{
+        calc_averaged_ant_output, check_nside, checked_beam_npix2nside, checked_npix2nside,
+        hemi2full, hemi_npix, integrate_az,
+    },
 };
 
 use scorus::{
     coordinates::{rotation3d::RotMatrix, SphCoord, Vec3d},
-    healpix::{
-        pix::pix2vec_ring,
-        rotation::rotate_ring,
-        utils::{npix2nside, nside2npix},
-    },
+    healpix::{pix::pix2vec_ring, rotation::rotate_ring, utils::nside2npix},
 };
 
 #[allow(clippy::too_many_arguments)]
@@ -26,10 +26,18 @@ pub fn calc_array_beam(
     phi_list: &[f64],
     freq_Hz: f64,
     ground_cut: bool,
-) -> Vec<f64> {
+) -> DbfResult<Vec<f64>> {
+    check_nside(nside)?;
+    check_array_lists(&[
+        ("x_list", x_list),
+        ("y_list", y_list),
+        ("z_list", z_list),
+        ("w_list", w_list),
+        ("phi_list", phi_list),
+    ])?;
     let npix = nside2npix(nside);
     let lambda = LIGHT_SPEED / (freq_Hz);
-    (0..npix)
+    Ok((0..npix)
         .map(|i| {
             if i < npix / 2 || !ground_cut {
                 let pointing = pix2vec_ring::<f64>(nside, i);
@@ -50,12 +58,12 @@ pub fn calc_array_beam(
                     .sum::<Complex<f64>>()
                     .norm_sqr()
                     */
-                calc_array_beam1(&pointing, x_list, y_list, z_list, w_list, phi_list, lambda)
+                array_factor(&pointing, x_list, y_list, z_list, w_list, phi_list, lambda)
             } else {
                 0.0
             }
         })
-        .collect()
+        .collect())
 }
 
 #[allow(clippy::too_many_arguments)]
@@ -67,6 +75,29 @@ pub fn calc_array_beam1(
     w_list: &[f64],
     phi_list: &[f64],
     lambda: f64,
+) -> DbfResult<f64> {
+    check_array_lists(&[
+        ("x_list", x_list),
+        ("y_list", y_list),
+        ("z_list", z_list),
+        ("w_list", w_list),
+        ("phi_list", phi_list),
+    ])?;
+    Ok(array_factor(
+        pointing, x_list, y_list, z_list, w_list, phi_list, lambda,
+    ))
+}
+
+/// `calc_array_beam1` for lists already checked to have equal lengths.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn array_factor(
+    pointing: &Vec3d<f64>,
+    x_list: &[f64],
+    y_list: &[f64],
+    z_list: &[f64],
+    w_list: &[f64],
+    phi_list: &[f64],
+    lambda: f64,
 ) -> f64 {
     x_list
         .iter()
@@ -92,20 +123,51 @@ pub fn calc_phase_from_pointing(
     az_from_north: f64,
     zenith: f64,
     freq_Hz: f64,
-) -> Vec<f64> {
+) -> DbfResult<Vec<f64>> {
+    check_array_lists(&[("x_list", x_list), ("y_list", y_list), ("z_list", z_list)])?;
     let az_from_x = PI / 2.0 - az_from_north;
     let lambda = LIGHT_SPEED / freq_Hz;
     let dir = Vec3d::from_sph_coord(SphCoord::new(zenith, az_from_x));
-    x_list
+    Ok(x_list
         .iter()
         .zip(y_list.iter().zip(z_list.iter()))
         .map(|(&x, (&y, &z))| {
             let dl = dir[0] * x + dir[1] * y + dir[2] * z;
             dl / lambda * 2.0 * PI
         })
-        .collect()
+        .collect())
 }
 
+/// `calc_array_beam` with the ground cut, only the upper hemisphere being
+/// computed and returned (see `utils::hemi_npix`).
+pub fn calc_array_beam_hemi(
+    nside: usize,
+    x_list: &[f64],
+    y_list: &[f64],
+    z_list: &[f64],
+    w_list: &[f64],
+    phi_list: &[f64],
+    freq_Hz: f64,
+) -> DbfResult<Vec<f64>> {
+    check_nside(nside)?;
+    check_array_lists(&[
+        ("x_list", x_list),
+        ("y_list", y_list),
+        ("z_list", z_list),
+        ("w_list", w_list),
+        ("phi_list", phi_list),
+    ])?;
+    let lambda = LIGHT_SPEED / (freq_Hz);
+    Ok((0..hemi_npix(nside))
+        .map(|i| {
+            let pointing = pix2vec_ring::<f64>(nside, i);
+            array_factor(&pointing, x_list, y_list, z_list, w_list, phi_list, lambda)
+        })
+        .collect())
+}
+
+/// Beam averaged over LST, the array beam being computed above the horizon
+/// only; `ant_beam` may be full sky or its upper hemisphere.
 #[allow(clippy::too_many_arguments)]
 pub fn calc_averaged_array_beam(
     lat_deg: f64,
@@ -116,22 +178,28 @@ pub fn calc_averaged_array_beam(
     w_list: &[f64],
     phi_list: &[f64],
     freq_hz: f64,
-) -> (Vec<f64>, Vec<usize>, Vec<f64>) {
-    let nside = npix2nside(ant_beam.len());
-    let array_beam = calc_array_beam(
-        nside, x_list, y_list, z_list, w_list, phi_list, freq_hz, true,
-    );
+) -> DbfResult<(Vec<f64>, Vec<usize>, Vec<f64>)> {
+    let nside = checked_beam_npix2nside(ant_beam.len())?;
+    let array_beam =
+        calc_array_beam_hemi(nside, x_list, y_list, z_list, w_list, phi_list, freq_hz)?;
     let total_beam: Vec<f64> = array_beam
         .iter()
         .zip(ant_beam.iter())
         .map(|(&a, &b)| a * b)
         .collect();
+    average_beam_over_lst(lat_deg, &hemi2full(&total_beam)?)
+}
+
+pub fn average_beam_over_lst(
+    lat_deg: f64,
+    total_beam: &[f64],
+) -> DbfResult<(Vec<f64>, Vec<usize>, Vec<f64>)> {
+    checked_npix2nside(total_beam.len())?;
     let rot =
         RotMatrix::about_axis_by_angle(&Vec3d::new(0.0, 1.0, 0.0), (90.0 - lat_deg).to_radians())
             * RotMatrix::about_axis_by_angle(&Vec3d::new(0.0, 0.0, 1.0), -90_f64.to_radians());
-    let rotated_beam = rotate_ring(&total_beam, &rot);
-    let (mean_beam, weight, theta) = integrate_az(&rotated_beam);
-    (mean_beam, weight, theta)
+    let rotated_beam = rotate_ring(total_beam, &rot);
+    integrate_az(&rotated_beam)
 }
 
 #[allow(clippy::too_many_arguments)]
@@ -145,9 +213,9 @@ pub fn calc_averaged_ant_output2(
     w_list: &[f64],
     phi_list: &[f64],
     freq_hz: f64,
-) -> f64 {
+) -> DbfResult<f64> {
     let (mean_beam, _weight, _theta) = calc_averaged_array_beam(
         lat_deg, ant_beam, x_list, y_list, z_list, w_list, phi_list, freq_hz,
-    );
+    )?;
     calc_averaged_ant_output(&mean_beam, sky)
 }
@@ -1,5 +1,9 @@
+use std::f64::consts::PI;
+
 use serde::{Deserialize, Serialize};
 
+use crate::error::{check_len, DbfResult};
+
 #[derive(Clone, Serialize, Deserialize)]
 pub struct ArrayCfg {
     pub ants: Vec<AntCfg>,
@@ -8,4 +12,44 @@ pub struct ArrayCfg {
 #[derive(Clone, Serialize, Deserialize)]
 pub struct AntCfg {
     pub pos: (f64, f64, f64),
+    /// amplitude weight of this element, 1 if not given
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wgt: Option<f64>,
+    /// true time delay applied to this element, in seconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub delay: Option<f64>,
+}
+
+impl ArrayCfg {
+    pub fn xyz_lists(&self) -> (Vec<f64>, Vec<f64>, Vec<f64>) {
+        let x = self.ants.iter().map(|a| a.pos.0).collect();
+        let y = self.ants.iter().map(|a| a.pos.1).collect();
+        let z = self.ants.iter().map(|a| a.pos.2).collect();
+        (x, y, z)
+    }
+
+    pub fn wgt_list(&self) -> Vec<f64> {
+        self.ants.iter().map(|a| a.wgt.unwrap_or(1.0)).collect()
+    }
+
+    /// steering phases at `freq_hz` from the stored delays, zero for elements without delay
+    pub fn phase_list(&self, freq_hz: f64) -> Vec<f64> {
+        self.ants
+            .iter()
+            .map(|a| 2.0 * PI * freq_hz * a.delay.unwrap_or(0.0))
+            .collect()
+    }
+
+    pub fn delay_list(&self) -> Option<Vec<f64>> {
+        self.ants.iter().map(|a| a.delay).collect()
+    }
+
+    pub fn set_delays(&mut self, delay: &[f64]) -> DbfResult<()> {
+        check_len("delay", self.ants.len(), delay.len())?;
+        self.ants
+            .iter_mut()
+            .zip(delay.iter())
+            .for_each(|(a, &d)| a.delay = Some(d));
+        Ok(())
+    }
 }
@@ -0,0 +1,6 @@
+#![cfg(not(target_family = "wasm"))]
+extern crate dbf_beam_simulator;
+
+fn main() {
+    dbf_beam_simulator::cli::main_multi();
+}
@@ -26,18 +26,19 @@ fn main() {
         })
         .collect();
     write_map("input_pattern.fits", &[&hpmap], false, true);
-    let mut wgt = pattern2wgt(&hpmap, d, freq_mhz, array_size);
+    let mut wgt = pattern2wgt(&hpmap, d, freq_mhz, array_size).unwrap();
     wgt.slice_mut(s![0, ..]).fill(0.0);
     wgt.slice_mut(s![.., 0]).fill(0.0);
 
-    let pattern1 = wgt2pattern(wgt.view(), d, freq_mhz, nside);
+    let pattern1 = wgt2pattern(wgt.view(), d, freq_mhz, nside).unwrap();
     let pattern2 = quarter_wgt2pattern(
         wgt.slice(s![array_size / 2..array_size, array_size / 2..array_size])
             .view(),
         d,
         freq_mhz,
         nside,
-    );
+    )
+    .unwrap();
     write_img("wgt.fits".to_string(), &wgt.into_dyn()).unwrap();
     write_map("pattern1.fits", &[&pattern1], false, true);
     write_map("pattern2.fits", &[&pattern2], false, true);
@@ -9,7 +9,7 @@ fn main() {
     let npix = nside2npix(nside);
     let map: Vec<_> = (0..npix).map(|_ipix| 1.0).collect();
 
-    let (m, w, theta) = integrate_az(&map);
+    let (m, w, theta) = integrate_az(&map).unwrap();
 
     for (&m1, (&w1, &theta1)) in m.iter().zip(w.iter().zip(theta.iter())) {
         println!("{} {} {}", m1, w1, theta1);
@@ -0,0 +1,244 @@
+//! C ABI for embedding the simulator, declared in
+//! `include/dbf_beam_simulator.h`. Building with `--features header` makes
+//! cbindgen write a fresh header to `$OUT_DIR`, the build leaving the source
+//! tree untouched; copy it over the one in `include/` after changing this
+//! file.
+//!
+//! All functions return a `DbfStatus`; results are written through out pointers.
+//! Angles are in radians, frequencies in Hz, azimuth is measured from north
+//! towards east as in `calc_phase_from_pointing`.
+
+use std::{
+    ffi::CStr,
+    fs::read_to_string,
+    os::raw::{c_char, c_double, c_int},
+    panic::{catch_unwind, UnwindSafe},
+    slice,
+};
+
+use crate::{
+    arbitrary_array::{calc_array_beam1, calc_phase_from_pointing},
+    array_cfg::ArrayCfg,
+    constants::LIGHT_SPEED,
+    error::DbfError,
+    steering::pointing_vec,
+};
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DbfStatus {
+    Ok = 0,
+    NullPointer = 1,
+    InvalidUtf8 = 2,
+    ParseError = 3,
+    IoError = 4,
+    LengthMismatch = 5,
+    InvalidValue = 6,
+    Panic = 7,
+}
+
+impl From<DbfError> for DbfStatus {
+    fn from(e: DbfError) -> Self {
+        match e {
+            DbfError::LengthMismatch { .. } => DbfStatus::LengthMismatch,
+            _ => DbfStatus::InvalidValue,
+        }
+    }
+}
+
+/// Element positions of an array, created from an array cfg and released
+/// with `dbf_array_free`.
+pub struct DbfArray {
+    x_list: Vec<f64>,
+    y_list: Vec<f64>,
+    z_list: Vec<f64>,
+}
+
+fn guard<F: FnOnce() -> Result<(), DbfStatus> + UnwindSafe>(f: F) -> DbfStatus {
+    match catch_unwind(f) {
+        Ok(Ok(())) => DbfStatus::Ok,
+        Ok(Err(status)) => status,
+        Err(_) => DbfStatus::Panic,
+    }
+}
+
+unsafe fn c_str<'a>(s: *const c_char) -> Result<&'a str, DbfStatus> {
+    if s.is_null() {
+        return Err(DbfStatus::NullPointer);
+    }
+    CStr::from_ptr(s)
+        .to_str()
+        .map_err(|_| DbfStatus::InvalidUtf8)
+}
+
+unsafe fn array_ref<'a>(array: *const DbfArray) -> Result<&'a DbfArray, DbfStatus> {
+    array.as_ref().ok_or(DbfStatus::NullPointer)
+}
+
+unsafe fn in_slice<'a>(p: *const c_double, n: usize) -> Result<&'a [f64], DbfStatus> {
+    if p.is_null() {
+        return Err(DbfStatus::NullPointer);
+    }
+    Ok(slice::from_raw_parts(p, n))
+}
+
+unsafe fn out_slice<'a>(p: *mut c_double, n: usize) -> Result<&'a mut [f64], DbfStatus> {
+    if p.is_null() {
+        return Err(DbfStatus::NullPointer);
+    }
+    Ok(slice::from_raw_parts_mut(p, n))
+}
+
+fn array_from_yaml(yaml: &str) -> Result<*mut DbfArray, DbfStatus> {
+    let cfg: ArrayCfg = serde_yaml::from_str(yaml).map_err(|_| DbfStatus::ParseError)?;
+    let (x_list, y_list, z_list) = cfg.xyz_lists();
+    Ok(Box::into_raw(Box::new(DbfArray {
+        x_list,
+        y_list,
+        z_list,
+    })))
+}
+
+/// Static description of a status code. Takes an int rather than a
+/// `DbfStatus`, as a caller may pass any value.
+#[no_mangle]
+pub extern "C" fn dbf_status_str(status: c_int) -> *const c_char {
+    let s: &'static [u8] = match status {
+        s if s == DbfStatus::Ok as c_int => b"ok\0",
+        s if s == DbfStatus::NullPointer as c_int => b"null pointer\0",
+        s if s == DbfStatus::InvalidUtf8 as c_int => b"invalid utf-8 string\0",
+        s if s == DbfStatus::ParseError as c_int => b"cannot parse array cfg\0",
+        s if s == DbfStatus::IoError as c_int => b"cannot read file\0",
+        s if s == DbfStatus::LengthMismatch as c_int => b"length mismatch\0",
+        s if s == DbfStatus::InvalidValue as c_int => b"invalid value\0",
+        s if s == DbfStatus::Panic as c_int => b"internal error\0",
+        _ => b"unknown status\0",
+    };
+    s.as_ptr() as *const c_char
+}
+
+/// Creates an array from the text of an array cfg YAML.
+///
+/// # Safety
+/// `yaml` must be a NUL-terminated string and `out` a valid pointer.
+#[no_mangle]
+pub unsafe extern "C" fn dbf_array_from_yaml(
+    yaml: *const c_char,
+    out: *mut *mut DbfArray,
+) -> DbfStatus {
+    guard(|| {
+        if out.is_null() {
+            return Err(DbfStatus::NullPointer);
+        }
+        *out = array_from_yaml(c_str(yaml)?)?;
+        Ok(())
+    })
+}
+
+/// Creates an array from an array cfg YAML file.
+///
+/// # Safety
+/// `path` must be a NUL-terminated string and `out` a valid pointer.
+#[no_mangle]
+pub unsafe extern "C" fn dbf_array_from_yaml_file(
+    path: *const c_char,
+    out: *mut *mut DbfArray,
+) -> DbfStatus {
+    guard(|| {
+        if out.is_null() {
+            return Err(DbfStatus::NullPointer);
+        }
+        let yaml = read_to_string(c_str(path)?).map_err(|_| DbfStatus::IoError)?;
+        *out = array_from_yaml(&yaml)?;
+        Ok(())
+    })
+}
+
+/// # Safety
+/// `array` must come from one of the constructors and not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn dbf_array_free(array: *mut DbfArray) {
+    if !array.is_null() {
+        drop(Box::from_raw(array));
+    }
+}
+
+/// # Safety
+/// `array` and `n` must be valid pointers.
+#[no_mangle]
+pub unsafe extern "C" fn dbf_array_num_ants(array: *const DbfArray, n: *mut usize) -> DbfStatus {
+    guard(|| {
+        let array = array_ref(array)?;
+        let n = n.as_mut().ok_or(DbfStatus::NullPointer)?;
+        *n = array.x_list.len();
+        Ok(())
+    })
+}
+
+/// Writes the steering phase of every element for a pointing into `phi`,
+/// which must hold `n` values, `n` being the num of elements.
+///
+/// # Safety
+/// `array` must be valid and `phi` must point to `n` doubles.
+#[no_mangle]
+pub unsafe extern "C" fn dbf_steering_phases(
+    array: *const DbfArray,
+    az_from_north: c_double,
+    zenith: c_double,
+    freq_hz: c_double,
+    phi: *mut c_double,
+    n: usize,
+) -> DbfStatus {
+    guard(|| {
+        let array = array_ref(array)?;
+        let phi = out_slice(phi, n)?;
+        let result = calc_phase_from_pointing(
+            &array.x_list,
+            &array.y_list,
+            &array.z_list,
+            az_from_north,
+            zenith,
+            freq_hz,
+        )?;
+        if result.len() != n {
+            return Err(DbfStatus::LengthMismatch);
+        }
+        phi.copy_from_slice(&result);
+        Ok(())
+    })
+}
+
+/// Array factor towards one direction for element weights `w` and phases
+/// `phi`, both holding `n` values.
+///
+/// # Safety
+/// `array` must be valid, `w` and `phi` must point to `n` doubles and `gain`
+/// to one double.
+#[no_mangle]
+pub unsafe extern "C" fn dbf_gain(
+    array: *const DbfArray,
+    w: *const c_double,
+    phi: *const c_double,
+    n: usize,
+    az_from_north: c_double,
+    zenith: c_double,
+    freq_hz: c_double,
+    gain: *mut c_double,
+) -> DbfStatus {
+    guard(|| {
+        let array = array_ref(array)?;
+        let w = in_slice(w, n)?;
+        let phi = in_slice(phi, n)?;
+        let gain = gain.as_mut().ok_or(DbfStatus::NullPointer)?;
+        *gain = calc_array_beam1(
+            &pointing_vec(az_from_north, zenith),
+            &array.x_list,
+            &array.y_list,
+            &array.z_list,
+            w,
+            phi,
+            LIGHT_SPEED / freq_hz,
+        )?;
+        Ok(())
+    })
+}
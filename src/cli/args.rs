@@ -0,0 +1,219 @@
+use clap::{Arg, ArgGroup};
+
+use crate::regular_array::solver::Solver;
+
+pub fn ant_beam() -> Arg<'static> {
+    Arg::new("ant_beam")
+        .short('a')
+        .long("ant")
+        .takes_value(true)
+        .value_name("single antenna beam")
+        .required(false)
+        .help("ant beam healpix file, isotropic if not given")
+}
+
+pub fn nside() -> Arg<'static> {
+    Arg::new("nside")
+        .short('n')
+        .long("nside")
+        .takes_value(true)
+        .value_name("nside")
+        .required(false)
+        .help("nside")
+}
+
+/// Requires either the element beam or an nside for an isotropic element.
+pub fn ant_beam_or_nside() -> ArgGroup<'static> {
+    ArgGroup::new("ant_beam_or_nside")
+        .args(&["ant_beam", "nside"])
+        .required(true)
+}
+
+pub fn freq() -> Arg<'static> {
+    Arg::new("freq")
+        .short('f')
+        .long("freq")
+        .takes_value(true)
+        .value_name("freq")
+        .required(true)
+        .help("freq, in MHz unless a unit (Hz, kHz, MHz, GHz) is given")
+}
+
+pub fn fmin() -> Arg<'static> {
+    Arg::new("fmin")
+        .long("fmin")
+        .takes_value(true)
+        .value_name("min freq")
+        .required(true)
+        .help("min freq, in MHz unless a unit is given")
+}
+
+pub fn fmax() -> Arg<'static> {
+    Arg::new("fmax")
+        .long("fmax")
+        .takes_value(true)
+        .value_name("max freq")
+        .required(true)
+        .help("max freq, in MHz unless a unit is given")
+}
+
+pub fn nfreq(default: &'static str) -> Arg<'static> {
+    Arg::new("nfreq")
+        .long("nfreq")
+        .takes_value(true)
+        .value_name("num of freqs")
+        .required(false)
+        .default_value(default)
+        .help("num of freqs")
+}
+
+pub fn spacing() -> Arg<'static> {
+    Arg::new("spacing")
+        .short('d')
+        .long("spacing")
+        .takes_value(true)
+        .value_name("spacing")
+        .required(true)
+        .help("element spacing, in metre unless given in lambda (e.g. 0.5lambda)")
+}
+
+pub fn sky() -> Arg<'static> {
+    Arg::new("sky")
+        .short('s')
+        .long("sky")
+        .takes_value(true)
+        .value_name("sky file")
+        .required(true)
+        .help("sky healpix file")
+}
+
+pub fn lat() -> Arg<'static> {
+    Arg::new("lat")
+        .short('l')
+        .long("lat")
+        .takes_value(true)
+        .value_name("lat in deg")
+        .required(true)
+        .allow_hyphen_values(true)
+        .help("lat")
+}
+
+pub fn array_cfg() -> Arg<'static> {
+    Arg::new("array_cfg")
+        .long("array")
+        .takes_value(true)
+        .value_name("array cfg yaml")
+        .required(true)
+        .help("array cfg")
+}
+
+pub fn seed() -> Arg<'static> {
+    Arg::new("seed")
+        .long("seed")
+        .takes_value(true)
+        .value_name("seed")
+        .required(false)
+        .help("rng seed, random if not given")
+}
+
+pub fn solver() -> Arg<'static> {
+    Arg::new("solver")
+        .long("solver")
+        .takes_value(true)
+        .value_name("solver")
+        .possible_values(Solver::NAMES)
+        .required(false)
+        .default_value("pso")
+        .help("global optimizer")
+}
+
+/// Hyperparameters of the solvers, the defaults of `Solver` if not given.
+pub fn solver_params() -> Vec<Arg<'static>> {
+    [
+        ("omega", "omega", "pso: inertia [0.75]"),
+        ("phi_p", "phi-p", "pso: weight of the personal best [0.5]"),
+        ("phi_g", "phi-g", "pso: weight of the global best [1.0]"),
+        ("de_f", "de-f", "de: differential weight [0.7]"),
+        ("de_cr", "de-cr", "de: crossover probability [0.9]"),
+        ("sigma0", "sigma0", "cma_es: initial step size [0.2]"),
+        ("t0", "t0", "anneal: initial temperature, in fitness [0.1]"),
+        (
+            "cooling",
+            "cooling",
+            "anneal: temperature factor per iteration [0.95]",
+        ),
+        ("step", "step", "anneal: initial proposal step [0.1]"),
+    ]
+    .into_iter()
+    .map(|(name, long, help)| {
+        Arg::new(name)
+            .long(long)
+            .takes_value(true)
+            .value_name("value")
+            .required(false)
+            .help(help)
+    })
+    .collect()
+}
+
+pub fn progress_log() -> Arg<'static> {
+    Arg::new("log")
+        .long("log")
+        .takes_value(true)
+        .value_name("log file")
+        .required(false)
+        .help("per-iteration progress, as CSV or as JSON lines if the name ends with .jsonl")
+}
+
+pub fn max_iter() -> Arg<'static> {
+    Arg::new("max_iter")
+        .long("max-iter")
+        .takes_value(true)
+        .value_name("num of iterations")
+        .required(false)
+        .help("stop after this many iterations")
+}
+
+pub fn max_time() -> Arg<'static> {
+    Arg::new("max_time")
+        .long("max-time")
+        .takes_value(true)
+        .value_name("seconds")
+        .required(false)
+        .help("stop after this wall-clock time")
+}
+
+pub fn target_fitness() -> Arg<'static> {
+    Arg::new("target_fitness")
+        .long("target-fitness")
+        .takes_value(true)
+        .value_name("fitness")
+        .required(false)
+        .help("stop once the best fitness reaches this value")
+}
+
+pub fn lmax() -> Arg<'static> {
+    Arg::new("lmax")
+        .long("lmax")
+        .takes_value(true)
+        .value_name("lmax")
+        .required(false)
+        .help("max multipole, 3 * nside - 1 if not given")
+}
+
+pub fn hemi() -> Arg<'static> {
+    Arg::new("hemi")
+        .long("hemi")
+        .takes_value(false)
+        .help("compute and store only the upper hemisphere, as a cut-sky map, which halves memory, compute and file size")
+}
+
+pub fn outfile(help: &'static str) -> Arg<'static> {
+    Arg::new("outfile")
+        .short('o')
+        .long("out")
+        .takes_value(true)
+        .value_name("outfile")
+        .required(true)
+        .help(help)
+}
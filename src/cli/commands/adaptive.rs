@@ -0,0 +1,101 @@
+use clap::{Arg, ArgMatches, Command};
+
+use crate::{
+    adaptive::{calc_array_beam_adaptive, AdaptiveCfg},
+    cli::{
+        args, freq_hz, load_array_cfg, read_hp_map, required, value, write_hp_map, CliResult,
+        Provenance,
+    },
+};
+
+pub fn command() -> Command<'static> {
+    Command::new("adaptive")
+        .about("array beam refined around the main lobe, flattened to a healpix map")
+        .arg(args::array_cfg().short('a'))
+        .arg(args::freq())
+        .arg(
+            Arg::new("base_nside")
+                .long("base-nside")
+                .takes_value(true)
+                .value_name("nside")
+                .required(false)
+                .default_value("16")
+                .help("nside evaluated over the whole sky, a power of 2"),
+        )
+        .arg(
+            Arg::new("max_nside")
+                .long("max-nside")
+                .takes_value(true)
+                .value_name("nside")
+                .required(false)
+                .default_value("512")
+                .help("finest nside around the main lobe, a power of 2"),
+        )
+        .arg(
+            Arg::new("gain_db")
+                .long("gain-db")
+                .takes_value(true)
+                .value_name("dB")
+                .required(false)
+                .default_value("-20")
+                .allow_hyphen_values(true)
+                .help("pixels brighter than this relative to the peak are refined"),
+        )
+        .arg(
+            Arg::new("spread")
+                .long("spread")
+                .takes_value(true)
+                .value_name("fraction of peak")
+                .required(false)
+                .default_value("0.01")
+                .help("pixels whose siblings differ by more than this are refined"),
+        )
+        .arg(
+            args::sky()
+                .required(false)
+                .help("sky, prints the antenna temperature from the multi-resolution beam"),
+        )
+        .arg(args::nside().required(true).help("nside of the output map"))
+        .arg(args::hemi())
+        .arg(args::outfile("output beam file"))
+}
+
+pub fn run(matches: &ArgMatches, prov: &mut Provenance) -> CliResult<()> {
+    let array_cfg = load_array_cfg(prov, required(matches, "array_cfg")?)?;
+    let freq_hz = freq_hz(matches, "freq")?;
+    let cfg = AdaptiveCfg {
+        base_nside: value::<usize>(matches, "base_nside")?,
+        max_nside: value::<usize>(matches, "max_nside")?,
+        gain_threshold: 10_f64.powf(value::<f64>(matches, "gain_db")? / 10.0),
+        spread_threshold: value::<f64>(matches, "spread")?,
+    };
+
+    let (x_list, y_list, z_list) = array_cfg.xyz_lists();
+    let beam = calc_array_beam_adaptive(
+        &cfg,
+        &x_list,
+        &y_list,
+        &z_list,
+        &array_cfg.wgt_list(),
+        &array_cfg.phase_list(freq_hz),
+        freq_hz,
+        true,
+    )?;
+    eprintln!(
+        "{} cells, beam solid angle {} sr",
+        beam.cells.len(),
+        beam.integrate() / beam.peak()
+    );
+
+    if let Some(fname) = matches.value_of("sky") {
+        println!("{}", beam.calc_ant_output(&read_hp_map(prov, fname)?)?);
+    }
+
+    let map = beam.flatten(value::<usize>(matches, "nside")?)?;
+    write_hp_map(
+        prov,
+        required(matches, "outfile")?,
+        &map,
+        matches.is_present("hemi"),
+    )
+}
@@ -0,0 +1,96 @@
+use std::fs::read_to_string;
+
+use clap::{Arg, ArgMatches, Command};
+
+use pest::Parser;
+
+use num::traits::FloatConst;
+
+use scorus::{
+    coordinates::SphCoord,
+    healpix::{interp::get_interpol_ring, utils::nside2npix},
+};
+
+use necrs::nec_parser::{parse_nec_file, NecParser, Rule};
+
+use crate::cli::{
+    args, freq_hz, io_error, required, value, write_hp_map, CliError, CliResult, Provenance,
+};
+
+pub fn command() -> Command<'static> {
+    Command::new("ant-beam")
+        .about("element beam from a NEC model")
+        .arg(
+            Arg::new("nec")
+                .short('n')
+                .long("nec")
+                .takes_value(true)
+                .value_name("nec file")
+                .required(true)
+                .help("nec file"),
+        )
+        .arg(args::nside().short('s').required(true))
+        .arg(args::freq())
+        .arg(args::hemi())
+        .arg(args::outfile("out healpix file"))
+}
+
+/// Gain pattern of the NEC model in `nec_file` above the horizon.
+pub fn calc_ant_beam(
+    prov: &mut Provenance,
+    nec_file: &str,
+    freq_mhz: f64,
+    nside: usize,
+) -> CliResult<Vec<f64>> {
+    let src = read_to_string(nec_file).map_err(|e| io_error(nec_file, e))?;
+    prov.record_input(nec_file);
+    let mut context = parse_nec_file(
+        NecParser::parse(Rule::NecFile, &src)
+            .map_err(|e| io_error(nec_file, e))?
+            .next()
+            .ok_or_else(|| CliError::Invalid(format!("{}: empty nec file", nec_file)))?,
+    );
+
+    context.nec_fr_card(0, 1, freq_mhz, 0.0);
+
+    let npix = nside2npix(nside);
+    let angular_resolution = (4.0 * f64::PI() / npix as f64).sqrt().to_degrees();
+    println!("{}", angular_resolution);
+
+    let (thetas, phis) = context.rp_from_npix(npix * 4, 0, 1, 0, 0, 0, 0.0, 0.0);
+    let mut data = vec![0.0; npix];
+    let mut wgt = vec![0.0; npix];
+
+    for (i, &theta) in thetas.iter().enumerate() {
+        if theta > 90.0 {
+            continue;
+        }
+        for (j, &phi) in phis.iter().enumerate() {
+            let g = (context.nec_gain(0, i as i32, j as i32) / 10.0).exp();
+            let dir = SphCoord::new(theta.to_radians(), phi.to_radians());
+            let (pix, w) = get_interpol_ring(nside, dir);
+            for (&p, &w) in pix.iter().zip(w.iter()) {
+                wgt[p] += w;
+                data[p] += w * g;
+            }
+        }
+    }
+    for (d, &w) in data.iter_mut().zip(wgt.iter()) {
+        if w > 0.0 {
+            *d /= w;
+        }
+    }
+    Ok(data)
+}
+
+pub fn run(matches: &ArgMatches, prov: &mut Provenance) -> CliResult<()> {
+    let freq_mhz = freq_hz(matches, "freq")? / 1e6;
+    let nside = value::<usize>(matches, "nside")?;
+    let data = calc_ant_beam(prov, required(matches, "nec")?, freq_mhz, nside)?;
+    write_hp_map(
+        prov,
+        required(matches, "outfile")?,
+        &data,
+        matches.is_present("hemi"),
+    )
+}
@@ -0,0 +1,41 @@
+use clap::{Arg, ArgMatches, Command};
+
+use crate::{
+    cli::{read_hp_map, required, write_hp_columns, CliResult, Provenance},
+    utils::{averaged_beam_to_healpix, integrate_az},
+};
+
+pub fn command() -> Command<'static> {
+    Command::new("az-mean")
+        .about("azimuthal average of a healpix map per ring")
+        .arg(
+            Arg::new("input_healpix")
+                .short('i')
+                .long("input")
+                .takes_value(true)
+                .value_name("healpix file")
+                .required(true),
+        )
+        .arg(
+            Arg::new("output_healpix")
+                .short('o')
+                .long("output")
+                .takes_value(true)
+                .value_name("healpix file")
+                .required(false),
+        )
+}
+
+pub fn run(matches: &ArgMatches, prov: &mut Provenance) -> CliResult<()> {
+    let hpmap = read_hp_map(prov, required(matches, "input_healpix")?)?;
+    let (mean, wgt, theta) = integrate_az(&hpmap)?;
+    for (&m, (&w, &t)) in mean.iter().zip(wgt.iter().zip(theta.iter())) {
+        println!("{} {} {}", m, w, t);
+    }
+
+    if let Some(fname) = matches.value_of("output_healpix") {
+        let hp_data = averaged_beam_to_healpix(&mean)?;
+        write_hp_columns(prov, fname, &[&hp_data], false)?;
+    }
+    Ok(())
+}
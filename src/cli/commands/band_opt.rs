@@ -0,0 +1,179 @@
+use clap::{Arg, ArgMatches, Command};
+
+use crate::{
+    cli::{
+        args, freq_hz, freq_list_hz, length_m, load_ant_beam, opt_bits,
+        progress::{stop_rule, ProgressLog},
+        read_hp_map_npix, required, seeded_rng, solver, value, write_table, write_wgt_cube,
+        CliResult, Provenance, WgtMeta,
+    },
+    regular_array::{
+        band::{BandFreqReport, BandWgtMode, BandWgtObjective},
+        deflattern_quarter_wgt, flattern_quarter_wgt, full2quarter,
+        opt::optimize_wgt,
+        quarter2full, target_beam2wgt,
+    },
+};
+
+pub fn command() -> Command<'static> {
+    Command::new("band-opt")
+        .about("regular array weights giving the same beam over a band, optimized jointly over all freqs")
+        .arg(args::ant_beam())
+        .arg(args::nside())
+        .arg(
+            Arg::new("target_beam")
+                .short('t')
+                .long("tb")
+                .takes_value(true)
+                .value_name("target beam")
+                .required(true)
+                .help("target beam, common to all freqs"),
+        )
+        .arg(
+            Arg::new("array_size")
+                .short('y')
+                .long("as")
+                .takes_value(true)
+                .value_name("array_size")
+                .required(true)
+                .help("array size"),
+        )
+        .arg(args::spacing().help(
+            "element spacing, in metre unless given in lambda (e.g. 0.5lambda) at the max freq",
+        ))
+        .arg(args::fmin())
+        .arg(args::fmax())
+        .arg(args::nfreq("4"))
+        .arg(
+            Arg::new("shared")
+                .long("shared")
+                .takes_value(false)
+                .help("one set of weights for all freqs instead of one per freq"),
+        )
+        .arg(
+            Arg::new("smoothness")
+                .long("smoothness")
+                .takes_value(true)
+                .value_name("penalty")
+                .required(false)
+                .default_value("1")
+                .conflicts_with("shared")
+                .help("penalty on the squared change of the weights between adjacent freqs"),
+        )
+        .arg(
+            Arg::new("npart")
+                .short('p')
+                .long("npart")
+                .takes_value(true)
+                .value_name("population size")
+                .required(false)
+                .default_value("64")
+                .help("population size, or num of proposals per iteration for anneal"),
+        )
+        .arg(
+            Arg::new("bits")
+                .short('b')
+                .long("bits")
+                .takes_value(true)
+                .value_name("bit depth")
+                .required(false)
+                .help("search only weights representable with this bit depth"),
+        )
+        .arg(args::seed())
+        .arg(args::solver())
+        .args(args::solver_params())
+        .arg(args::max_iter())
+        .arg(args::max_time())
+        .arg(args::target_fitness())
+        .arg(args::progress_log())
+        .arg(
+            Arg::new("report")
+                .short('r')
+                .long("report")
+                .takes_value(true)
+                .value_name("csv file")
+                .required(false)
+                .help("beam variation versus freq, stdout if not given"),
+        )
+        .arg(args::outfile("output wgt cube, freq along the first axis"))
+        .group(args::ant_beam_or_nside())
+}
+
+pub fn run(matches: &ArgMatches, prov: &mut Provenance) -> CliResult<()> {
+    let (ant_beam, nside) = load_ant_beam(prov, matches)?;
+    let npix = ant_beam.len();
+    let target_beam = read_hp_map_npix(prov, required(matches, "target_beam")?, npix)?;
+    let array_size = value::<isize>(matches, "array_size")?;
+    let freqs_mhz: Vec<f64> = freq_list_hz(matches)?.iter().map(|f| f / 1e6).collect();
+    let d = length_m(matches, "spacing", Some(freq_hz(matches, "fmax")?))?;
+    let mode = if matches.is_present("shared") {
+        BandWgtMode::Shared
+    } else {
+        BandWgtMode::PerFreq {
+            smoothness: value::<f64>(matches, "smoothness")?,
+        }
+    };
+    let npart = value::<usize>(matches, "npart")?;
+    let bits = opt_bits(matches)?;
+    let (mut rng, _seed) = seeded_rng(prov, matches)?;
+
+    // single freq designs as the starting point
+    let mut wgt0 = Vec::new();
+    let (mut h, mut w) = (0, 0);
+    for &f in &freqs_mhz {
+        let wgt = target_beam2wgt(&target_beam, &ant_beam, d, f, array_size)?;
+        h = wgt.shape()[0];
+        w = wgt.shape()[1];
+        wgt0.push(flattern_quarter_wgt(full2quarter(wgt.view()).view()));
+    }
+
+    let obj = BandWgtObjective::new(&ant_beam, &target_beam, d, &freqs_mhz, nside, h, w, mode)?;
+    let guess = obj.guess(&wgt0);
+    eprintln!("init misfit: {}", obj.misfit(&guess)?);
+    let mut log = ProgressLog::create(matches.value_of("log"))?;
+    let result = optimize_wgt(
+        &obj,
+        &guess,
+        npart,
+        bits,
+        &solver(matches)?,
+        &stop_rule(matches)?,
+        &mut rng,
+        |x, progress| {
+            let misfit = obj.misfit(x).unwrap_or(f64::NAN);
+            log.log(progress, &[("misfit", misfit)]);
+        },
+    )?;
+    log.finish(result.stop_reason)?;
+    let x = result.x;
+    prov.record_value("MISFIT", &obj.misfit(&x)?.to_string());
+
+    let wgts = obj
+        .wgt_per_freq(&x)
+        .into_iter()
+        .map(|x1| quarter2full(deflattern_quarter_wgt(x1, h, w)?.view()))
+        .collect::<Result<Vec<_>, _>>()?;
+    write_wgt_cube(
+        prov,
+        required(matches, "outfile")?,
+        &wgts,
+        &WgtMeta {
+            spacing_m: d,
+            freqs_hz: freqs_mhz.iter().map(|f| f * 1e6).collect(),
+        },
+    )?;
+
+    let report = obj.report(&x)?;
+    let column = |f: fn(&BandFreqReport) -> f64| -> Vec<f64> { report.iter().map(f).collect() };
+    write_table(
+        prov,
+        matches.value_of("report"),
+        &[
+            ("freq_MHz", &column(|r| r.freq_mhz)),
+            ("misfit", &column(|r| r.misfit)),
+            ("deviation", &column(|r| r.deviation)),
+            ("solid_angle_sr", &column(|r| r.metrics.solid_angle)),
+            ("sidelobe_dB", &column(|r| r.metrics.sidelobe_level_db)),
+        ],
+    )
+}
@@ -0,0 +1,53 @@
+use clap::{Arg, ArgMatches, Command};
+
+use crate::{
+    cli::{
+        args, freq_hz, length_m, load_ant_beam_npix, read_hp_map, required, value, write_wgt_meta,
+        CliResult, Provenance, WgtMeta,
+    },
+    regular_array::target_beam2wgt,
+};
+
+pub fn command() -> Command<'static> {
+    Command::new("beam2wgt")
+        .about("regular array weights synthesising a target beam")
+        .arg(args::ant_beam())
+        .arg(args::nside())
+        .arg(
+            Arg::new("target_beam")
+                .short('t')
+                .long("tb")
+                .takes_value(true)
+                .value_name("target beam")
+                .required(true)
+                .help("target_beam"),
+        )
+        .arg(
+            Arg::new("array_size")
+                .short('y')
+                .long("as")
+                .takes_value(true)
+                .value_name("array_size")
+                .required(true)
+                .help("array size"),
+        )
+        .arg(args::spacing())
+        .arg(args::freq())
+        .arg(args::outfile("output wgt file name"))
+}
+
+pub fn run(matches: &ArgMatches, prov: &mut Provenance) -> CliResult<()> {
+    let target_beam = read_hp_map(prov, required(matches, "target_beam")?)?;
+    let ant_beam = load_ant_beam_npix(prov, matches, target_beam.len())?;
+    let array_size = value::<isize>(matches, "array_size")?;
+    let freq_hz = freq_hz(matches, "freq")?;
+    let d = length_m(matches, "spacing", Some(freq_hz))?;
+
+    let wgt = target_beam2wgt(&target_beam, &ant_beam, d, freq_hz / 1e6, array_size)?;
+    write_wgt_meta(
+        prov,
+        required(matches, "outfile")?,
+        wgt,
+        &WgtMeta::new(d, freq_hz),
+    )
+}
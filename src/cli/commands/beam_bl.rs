@@ -0,0 +1,45 @@
+use clap::{Arg, ArgMatches, Command};
+
+use crate::{
+    cli::{args, opt_value, read_hp_map, required, write_table, CliResult, Provenance},
+    sht::{alm2cl, map2alm_iter, DEFAULT_ITER},
+    utils::checked_npix2nside,
+};
+
+pub fn command() -> Command<'static> {
+    Command::new("beam-bl")
+        .about("beam window function B_l and angular power spectrum C_l of a healpix map, as csv")
+        .arg(
+            Arg::new("input_healpix")
+                .short('i')
+                .long("input")
+                .takes_value(true)
+                .value_name("healpix file")
+                .required(true),
+        )
+        .arg(args::lmax())
+        .arg(
+            Arg::new("outfile")
+                .short('o')
+                .long("out")
+                .takes_value(true)
+                .value_name("csv file")
+                .required(false)
+                .help("output csv file, stdout if not given"),
+        )
+}
+
+pub fn run(matches: &ArgMatches, prov: &mut Provenance) -> CliResult<()> {
+    let beam = read_hp_map(prov, required(matches, "input_healpix")?)?;
+    let nside = checked_npix2nside(beam.len())?;
+    let lmax = opt_value::<usize>(matches, "lmax")?.unwrap_or(3 * nside - 1);
+    let cl = alm2cl(&map2alm_iter(&beam, lmax, DEFAULT_ITER)?);
+
+    let l: Vec<f64> = (0..cl.len()).map(|l| l as f64).collect();
+    let bl: Vec<f64> = cl.iter().map(|&c| (c / cl[0]).sqrt()).collect();
+    write_table(
+        prov,
+        matches.value_of("outfile"),
+        &[("l", &l), ("B_l", &bl), ("C_l", &cl)],
+    )
+}
@@ -0,0 +1,147 @@
+use clap::{Arg, ArgGroup, ArgMatches, Command};
+
+use crate::{
+    cli::{read_hp_map, required, value, write_table, CliError, CliResult, Provenance},
+    cut::{azimuth_cut, elevation_cut},
+};
+
+pub fn command() -> Command<'static> {
+    Command::new("beam-cut")
+        .about("cuts of a healpix beam along vertical planes or constant elevations, as csv in dB")
+        .arg(
+            Arg::new("input_healpix")
+                .short('i')
+                .long("input")
+                .takes_value(true)
+                .value_name("healpix file")
+                .required(true),
+        )
+        .arg(
+            Arg::new("az")
+                .long("az")
+                .takes_value(true)
+                .value_name("az in deg")
+                .use_value_delimiter(true)
+                .multiple_values(true)
+                .allow_hyphen_values(true)
+                .help("azimuths (from north towards east) of the vertical cuts, e.g. 0,90 for the N-S and E-W planes"),
+        )
+        .arg(
+            Arg::new("el")
+                .long("el")
+                .takes_value(true)
+                .value_name("el in deg")
+                .use_value_delimiter(true)
+                .multiple_values(true)
+                .allow_hyphen_values(true)
+                .help("elevations of the conical cuts"),
+        )
+        .group(ArgGroup::new("cut").args(&["az", "el"]).required(true))
+        .arg(
+            Arg::new("step")
+                .long("step")
+                .takes_value(true)
+                .value_name("deg")
+                .required(false)
+                .default_value("1")
+                .help("angular step along the cuts"),
+        )
+        .arg(
+            Arg::new("full")
+                .long("full")
+                .takes_value(false)
+                .help("extend vertical cuts below the horizon, to +-180 deg from zenith"),
+        )
+        .arg(
+            Arg::new("absolute")
+                .long("absolute")
+                .takes_value(false)
+                .help("10 log10 of the map values instead of dB relative to the map peak"),
+        )
+        .arg(
+            Arg::new("outfile")
+                .short('o')
+                .long("out")
+                .takes_value(true)
+                .value_name("csv file")
+                .required(false)
+                .help("output csv file, stdout if not given"),
+        )
+}
+
+fn angle_list(matches: &ArgMatches, name: &str) -> CliResult<Vec<f64>> {
+    matches
+        .values_of(name)
+        .into_iter()
+        .flatten()
+        .map(|s| {
+            s.trim().parse::<f64>().map_err(|e| CliError::InvalidValue {
+                arg: name.to_string(),
+                value: s.to_string(),
+                reason: e.to_string(),
+            })
+        })
+        .collect()
+}
+
+/// `n + 1` evenly spaced angles covering [from, to] with a spacing close to `step`.
+fn angle_grid(from: f64, to: f64, step: f64) -> Vec<f64> {
+    let n = ((to - from) / step).round().max(1.0) as usize;
+    (0..=n)
+        .map(|i| from + (to - from) * i as f64 / n as f64)
+        .collect()
+}
+
+pub fn run(matches: &ArgMatches, prov: &mut Provenance) -> CliResult<()> {
+    let map = read_hp_map(prov, required(matches, "input_healpix")?)?;
+    let step = value::<f64>(matches, "step")?;
+    if step.is_nan() || step <= 0.0 {
+        return Err(CliError::InvalidValue {
+            arg: "step".to_string(),
+            value: step.to_string(),
+            reason: "must be positive".to_string(),
+        });
+    }
+
+    let (angle_name, angles, cuts) = if matches.is_present("az") {
+        let theta_max = if matches.is_present("full") {
+            180.0
+        } else {
+            90.0
+        };
+        let theta = angle_grid(-theta_max, theta_max, step);
+        let mut cuts = Vec::new();
+        for az in angle_list(matches, "az")? {
+            cuts.push((format!("az{}_dB", az), azimuth_cut(&map, az, &theta)?));
+        }
+        ("theta_deg", theta, cuts)
+    } else {
+        let az = angle_grid(0.0, 360.0, step);
+        let mut cuts = Vec::new();
+        for el in angle_list(matches, "el")? {
+            cuts.push((format!("el{}_dB", el), elevation_cut(&map, el, &az)?));
+        }
+        ("az_deg", az, cuts)
+    };
+
+    let reference = if matches.is_present("absolute") {
+        1.0
+    } else {
+        map.iter().cloned().fold(f64::NEG_INFINITY, f64::max)
+    };
+
+    let db: Vec<(&str, Vec<f64>)> = cuts
+        .iter()
+        .map(|(name, cut)| {
+            let db = cut
+                .iter()
+                .map(|&c| 10.0 * (c / reference).log10())
+                .collect();
+            (name.as_str(), db)
+        })
+        .collect();
+    let columns: Vec<(&str, &[f64])> = std::iter::once((angle_name, &angles[..]))
+        .chain(db.iter().map(|(name, c)| (*name, &c[..])))
+        .collect();
+    write_table(prov, matches.value_of("outfile"), &columns)
+}
@@ -0,0 +1,166 @@
+use clap::{Arg, ArgMatches, Command};
+
+use crate::{
+    cli::{
+        args, freq_hz, freq_list_hz, load_ant_beam_npix, load_array_cfg, opt_freq_hz, read_hp_map,
+        read_hp_map_npix, required, seeded_rng, value, write_table, CliResult, Provenance,
+    },
+    foreground::{
+        check_log_poly_order, eval_log_poly, fit_log_poly, rms, simulate_ant_temp_spectrum,
+        CalibrationError,
+    },
+};
+
+pub fn command() -> Command<'static> {
+    Command::new("fg-residual")
+        .about("foreground-fit residual of the beamformed spectrum")
+        .arg(
+            args::array_cfg()
+                .short('a')
+                .help("array cfg with the element wgts and delays"),
+        )
+        .arg(args::ant_beam().short('e'))
+        .arg(args::sky().help("sky at the reference freq"))
+        .arg(
+            Arg::new("beta")
+                .short('b')
+                .long("beta")
+                .takes_value(true)
+                .value_name("spectral index")
+                .required(false)
+                .default_value("-2.5")
+                .allow_hyphen_values(true)
+                .help("spectral index of the sky"),
+        )
+        .arg(
+            Arg::new("beta_map")
+                .long("beta-map")
+                .takes_value(true)
+                .value_name("spectral index map")
+                .required(false)
+                .conflicts_with("beta")
+                .help("per-pixel spectral index of the sky"),
+        )
+        .arg(args::lat())
+        .arg(args::fmin())
+        .arg(args::fmax())
+        .arg(args::nfreq("64"))
+        .arg(
+            Arg::new("ref_freq")
+                .short('r')
+                .long("ref")
+                .takes_value(true)
+                .value_name("ref freq")
+                .required(true)
+                .help("freq of the sky map and of the fit"),
+        )
+        .arg(
+            Arg::new("order")
+                .short('k')
+                .long("order")
+                .takes_value(true)
+                .value_name("order")
+                .required(false)
+                .default_value("4")
+                .help("order of the log-polynomial foreground model"),
+        )
+        .arg(
+            Arg::new("ripple_amp")
+                .long("ripple-amp")
+                .takes_value(true)
+                .value_name("fraction")
+                .required(false)
+                .default_value("0")
+                .help("fractional amplitude of the gain ripple"),
+        )
+        .arg(
+            Arg::new("ripple_period")
+                .long("ripple-period")
+                .takes_value(true)
+                .value_name("period")
+                .required(false)
+                .help("period of the gain ripple, in MHz unless a unit is given"),
+        )
+        .arg(
+            Arg::new("gain_sigma")
+                .long("gain-sigma")
+                .takes_value(true)
+                .value_name("fraction")
+                .required(false)
+                .default_value("0")
+                .help("rms random gain error per channel"),
+        )
+        .arg(args::seed())
+        .arg(
+            args::outfile("spectrum, model and residual")
+                .value_name("csv file")
+                .required(false),
+        )
+}
+
+pub fn run(matches: &ArgMatches, prov: &mut Provenance) -> CliResult<()> {
+    let sky = read_hp_map(prov, required(matches, "sky")?)?;
+    let npix = sky.len();
+    let ant_beam = load_ant_beam_npix(prov, matches, npix)?;
+    let beta = if let Some(fname) = matches.value_of("beta_map") {
+        read_hp_map_npix(prov, fname, npix)?
+    } else {
+        vec![value::<f64>(matches, "beta")?; npix]
+    };
+
+    let array_cfg = load_array_cfg(prov, required(matches, "array_cfg")?)?;
+    let (x_list, y_list, z_list) = array_cfg.xyz_lists();
+    let w_list = array_cfg.wgt_list();
+
+    let lat = value::<f64>(matches, "lat")?;
+    let ref_freq_hz = freq_hz(matches, "ref_freq")?;
+    let order = value::<usize>(matches, "order")?;
+    let freqs_hz = freq_list_hz(matches)?;
+    check_log_poly_order(order, freqs_hz.len())?;
+
+    let cal_err = CalibrationError {
+        ripple_amp: value(matches, "ripple_amp")?,
+        ripple_period: opt_freq_hz(matches, "ripple_period")?.unwrap_or(0.0),
+        gain_sigma: value(matches, "gain_sigma")?,
+    };
+    let (mut rng, _seed) = seeded_rng(prov, matches)?;
+
+    let temps = simulate_ant_temp_spectrum(
+        &sky,
+        &beta,
+        ref_freq_hz,
+        lat,
+        &ant_beam,
+        &x_list,
+        &y_list,
+        &z_list,
+        &w_list,
+        |f| array_cfg.phase_list(f),
+        &freqs_hz,
+    )?;
+    let temps = cal_err.apply(&freqs_hz, &temps, &mut rng);
+    let coeffs = fit_log_poly(&freqs_hz, &temps, ref_freq_hz, order)?;
+    let model = eval_log_poly(&coeffs, &freqs_hz, ref_freq_hz);
+    let resid: Vec<f64> = temps
+        .iter()
+        .zip(model.iter())
+        .map(|(&t, &m)| t - m)
+        .collect();
+
+    if let Some(fname) = matches.value_of("outfile") {
+        let freqs_mhz: Vec<f64> = freqs_hz.iter().map(|f| f / 1e6).collect();
+        write_table(
+            prov,
+            Some(fname),
+            &[
+                ("freq_MHz", &freqs_mhz),
+                ("temp_K", &temps),
+                ("model_K", &model),
+                ("residual_K", &resid),
+            ],
+        )?;
+    }
+    println!("# coeffs: {:?}", coeffs);
+    println!("# residual rms: {} K", rms(&resid));
+    Ok(())
+}
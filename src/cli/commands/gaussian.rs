@@ -0,0 +1,40 @@
+use clap::{Arg, ArgMatches, Command};
+
+use crate::{
+    cli::{args, opt_value, required, value, write_hp_map, CliResult, Provenance},
+    sht::band_limit,
+    utils::gaussian_beam,
+};
+
+pub fn command() -> Command<'static> {
+    Command::new("gaussian")
+        .about("circular gaussian target beam")
+        .arg(args::nside().required(true))
+        .arg(
+            Arg::new("sigma_deg")
+                .short('s')
+                .long("sigma")
+                .takes_value(true)
+                .value_name("sigma in deg")
+                .required(true)
+                .help("sigma"),
+        )
+        .arg(args::lmax().help("band-limit the beam to this lmax, not band-limited if not given"))
+        .arg(args::hemi())
+        .arg(args::outfile("output file name"))
+}
+
+pub fn run(matches: &ArgMatches, prov: &mut Provenance) -> CliResult<()> {
+    let nside = value::<usize>(matches, "nside")?;
+    let sigma_deg = value::<f64>(matches, "sigma_deg")?;
+    let mut hpmap = gaussian_beam(nside, sigma_deg)?;
+    if let Some(lmax) = opt_value::<usize>(matches, "lmax")? {
+        hpmap = band_limit(&hpmap, lmax)?;
+    }
+    write_hp_map(
+        prov,
+        required(matches, "outfile")?,
+        &hpmap,
+        matches.is_present("hemi"),
+    )
+}
@@ -0,0 +1,22 @@
+pub mod adaptive;
+pub mod ant_beam;
+pub mod az_mean;
+pub mod band_opt;
+pub mod beam2wgt;
+pub mod beam_bl;
+pub mod beam_cut;
+pub mod fg_residual;
+pub mod gaussian;
+pub mod multibeam;
+pub mod opt;
+pub mod quantize;
+pub mod regrid;
+pub mod render;
+pub mod run_job;
+pub mod smooth;
+pub mod squint;
+pub mod steer;
+pub mod target;
+pub mod tolerance;
+pub mod trim_ants;
+pub mod wgt2beam;
@@ -0,0 +1,101 @@
+use clap::{Arg, ArgMatches, Command};
+
+use crate::{
+    cli::{
+        args, freq_hz, load_ant_beam, load_array_cfg, read_yaml, required, value, write_hp_cube,
+        CliResult, Provenance,
+    },
+    multibeam::{
+        beam_overlap, calc_multi_array_beam, crossover_level, tiling_coverage, MultiBeamCfg,
+    },
+};
+
+pub fn command() -> Command<'static> {
+    Command::new("multibeam")
+        .about("evaluate several beams of one array and report their overlap and tiling")
+        .arg(args::array_cfg().short('a'))
+        .arg(
+            Arg::new("beams")
+                .short('b')
+                .long("beams")
+                .takes_value(true)
+                .value_name("beam cfg yaml")
+                .required(true)
+                .help("pointings and optional weights of all beams"),
+        )
+        .arg(args::ant_beam().short('e'))
+        .arg(args::nside())
+        .arg(args::freq())
+        .arg(
+            Arg::new("coverage_db")
+                .short('c')
+                .long("cov")
+                .takes_value(true)
+                .value_name("threshold in dB")
+                .required(false)
+                .default_value("-3")
+                .allow_hyphen_values(true)
+                .help("level relative to the beam peak used for the tiling coverage"),
+        )
+        .arg(args::outfile("out healpix file, one column per beam"))
+        .group(args::ant_beam_or_nside())
+}
+
+fn print_matrix(m: &ndarray::Array2<f64>) {
+    for row in m.rows() {
+        println!(
+            "{}",
+            row.iter()
+                .map(|x| x.to_string())
+                .collect::<Vec<_>>()
+                .join(" ")
+        );
+    }
+}
+
+pub fn run(matches: &ArgMatches, prov: &mut Provenance) -> CliResult<()> {
+    let (ant_beam, nside) = load_ant_beam(prov, matches)?;
+    let array_cfg = load_array_cfg(prov, required(matches, "array_cfg")?)?;
+    let beam_cfg: MultiBeamCfg = read_yaml(prov, required(matches, "beams")?)?;
+    beam_cfg.check()?;
+    let (x_list, y_list, z_list) = array_cfg.xyz_lists();
+
+    let freq_hz = freq_hz(matches, "freq")?;
+    let coverage_db = value::<f64>(matches, "coverage_db")?;
+
+    let weight_sets =
+        beam_cfg.weight_sets(&x_list, &y_list, &z_list, &array_cfg.wgt_list(), freq_hz)?;
+    let mut beams = calc_multi_array_beam(
+        nside,
+        &x_list,
+        &y_list,
+        &z_list,
+        &weight_sets,
+        freq_hz,
+        true,
+    )?;
+    beams.iter_mut().for_each(|b| {
+        b.iter_mut()
+            .zip(ant_beam.iter())
+            .for_each(|(x, &a)| *x *= a)
+    });
+
+    println!("# overlap");
+    print_matrix(&beam_overlap(&beams));
+    println!("# crossover level in dB");
+    print_matrix(&crossover_level(&beams));
+    println!(
+        "# coverage above {} dB: {}",
+        coverage_db,
+        tiling_coverage(&beams, coverage_db)?
+    );
+
+    let columns: Vec<&[f64]> = beams.iter().map(|b| &b[..]).collect();
+    write_hp_cube(
+        prov,
+        required(matches, "outfile")?,
+        &columns,
+        &vec![freq_hz; columns.len()],
+    )?;
+    Ok(())
+}
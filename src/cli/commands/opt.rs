@@ -0,0 +1,241 @@
+use clap::{Arg, ArgMatches, Command};
+
+use crate::{
+    cli::{
+        args, load_ant_beam, opt_bits,
+        progress::{stop_rule, ProgressLog},
+        read_complex_wgt, read_hp_map_npix, read_wgt_meta, required, seeded_rng, solver, value,
+        wgt_freq_spacing, write_complex_wgt, write_wgt_meta, CliResult, Provenance, WgtMeta,
+    },
+    regular_array::{
+        complex::{steer_wgt, ComplexWgtLayout, ComplexWgtObjective, WgtSymmetry},
+        deflattern_quarter_wgt, full2quarter,
+        opt::{optimize_wgt, QuarterWgtObjective, WgtObjective},
+        quarter2full,
+    },
+    utils::calc_ant_output,
+};
+
+pub fn command() -> Command<'static> {
+    Command::new("opt")
+        .about("optimize regular array weights towards a target beam")
+        .arg(args::ant_beam())
+        .arg(
+            Arg::new("target_beam")
+                .short('t')
+                .long("tb")
+                .takes_value(true)
+                .value_name("target beam")
+                .required(true)
+                .help("target_beam"),
+        )
+        .arg(
+            Arg::new("wgt0")
+                .short('w')
+                .long("wgt0")
+                .takes_value(true)
+                .value_name("wgt file")
+                .required(true)
+                .help("initial wgt"),
+        )
+        .arg(args::nside())
+        .arg(args::spacing().required(false).help(
+            "element spacing, in metre unless given in lambda (e.g. 0.5lambda); read from the initial wgt file if not given",
+        ))
+        .arg(args::freq().required(false).help(
+            "freq, in MHz unless a unit (Hz, kHz, MHz, GHz) is given; read from the initial wgt file if not given",
+        ))
+        .arg(args::sky())
+        .arg(
+            Arg::new("npart")
+                .short('p')
+                .long("npart")
+                .takes_value(true)
+                .value_name("population size")
+                .required(false)
+                .default_value("64")
+                .help("population size, or num of proposals per iteration for anneal"),
+        )
+        .arg(
+            Arg::new("bits")
+                .short('b')
+                .long("bits")
+                .takes_value(true)
+                .value_name("bit depth")
+                .required(false)
+                .conflicts_with("complex")
+                .help("search only weights representable with this bit depth"),
+        )
+        .arg(
+            Arg::new("complex")
+                .long("complex")
+                .takes_value(false)
+                .required(false)
+                .help("optimize amplitude and phase of every element, e.g. for off-zenith or asymmetric target beams"),
+        )
+        .arg(
+            Arg::new("sym")
+                .long("sym")
+                .takes_value(true)
+                .value_name("symmetry")
+                .possible_values(WgtSymmetry::NAMES)
+                .default_value("none")
+                .requires("complex")
+                .help("symmetry imposed on the complex weights"),
+        )
+        .arg(
+            Arg::new("az_deg")
+                .long("az")
+                .takes_value(true)
+                .value_name("az from north in deg")
+                .required(false)
+                .allow_hyphen_values(true)
+                .requires_all(&["complex", "zenith_deg"])
+                .help("azimuth the initial complex weights are steered to"),
+        )
+        .arg(
+            Arg::new("zenith_deg")
+                .long("zenith")
+                .takes_value(true)
+                .value_name("zenith angle in deg")
+                .required(false)
+                .requires_all(&["complex", "az_deg"])
+                .help("zenith angle the initial complex weights are steered to"),
+        )
+        .arg(args::seed())
+        .arg(args::solver())
+        .args(args::solver_params())
+        .arg(args::max_iter())
+        .arg(args::max_time())
+        .arg(args::target_fitness())
+        .arg(args::progress_log())
+        .arg(args::outfile("out_wgt"))
+        .group(args::ant_beam_or_nside())
+}
+
+pub fn run(matches: &ArgMatches, prov: &mut Provenance) -> CliResult<()> {
+    let (ant_beam, nside) = load_ant_beam(prov, matches)?;
+    let npix = ant_beam.len();
+    let sky = read_hp_map_npix(prov, required(matches, "sky")?, npix)?;
+    let target_beam = read_hp_map_npix(prov, required(matches, "target_beam")?, npix)?;
+    let target_ant_out = calc_ant_output(&target_beam, &sky)?;
+    if matches.is_present("complex") {
+        return run_complex(
+            matches,
+            prov,
+            &ant_beam,
+            nside,
+            &sky,
+            &target_beam,
+            target_ant_out,
+        );
+    }
+
+    let (wgt, meta) = read_wgt_meta(prov, required(matches, "wgt0")?)?;
+    let h = wgt.shape()[0];
+    let w = wgt.shape()[1];
+
+    let (freq_hz, d) = wgt_freq_spacing(matches, meta.as_ref())?;
+    let npart = value::<usize>(matches, "npart")?;
+    let bits = opt_bits(matches)?;
+    let (mut rng, _seed) = seeded_rng(prov, matches)?;
+
+    let wgt_eff = full2quarter(wgt.view());
+
+    let obj = QuarterWgtObjective::new(&ant_beam, &target_beam, d, freq_hz / 1e6, nside, h, w)?;
+    let guess: Vec<_> = wgt_eff.iter().skip(1).cloned().collect();
+    eprintln!("init fitness: {}", obj.fitness(&guess)?);
+    eprintln!("target ant temp: {}", target_ant_out);
+
+    let mut log = ProgressLog::create(matches.value_of("log"))?;
+    let result = optimize_wgt(
+        &obj,
+        &guess,
+        npart,
+        bits,
+        &solver(matches)?,
+        &stop_rule(matches)?,
+        &mut rng,
+        |x, progress| {
+            let rel_err = obj
+                .total_beam(x)
+                .and_then(|b| calc_ant_output(&b, &sky))
+                .map_or(f64::NAN, |t| (t - target_ant_out).abs() / target_ant_out);
+            log.log(progress, &[("ant_temp_rel_err", rel_err)]);
+        },
+    )?;
+    log.finish(result.stop_reason)?;
+    prov.record_value("FITNESS", &obj.fitness(&result.x)?.to_string());
+
+    let wgt = quarter2full(deflattern_quarter_wgt(&result.x, h, w)?.view())?;
+    write_wgt_meta(
+        prov,
+        required(matches, "outfile")?,
+        wgt,
+        &WgtMeta::new(d, freq_hz),
+    )
+}
+
+/// Optimizes amplitude and phase of every element of the whole grid, tied
+/// only by the `sym` arg.
+fn run_complex(
+    matches: &ArgMatches,
+    prov: &mut Provenance,
+    ant_beam: &[f64],
+    nside: usize,
+    sky: &[f64],
+    target_beam: &[f64],
+    target_ant_out: f64,
+) -> CliResult<()> {
+    let (wgt, meta) = read_complex_wgt(prov, required(matches, "wgt0")?)?;
+    let (h, w) = (wgt.shape()[0], wgt.shape()[1]);
+
+    let (freq_hz, d) = wgt_freq_spacing(matches, meta.as_ref())?;
+    let npart = value::<usize>(matches, "npart")?;
+    let (mut rng, _seed) = seeded_rng(prov, matches)?;
+
+    let wgt = if matches.is_present("az_deg") {
+        let az = value::<f64>(matches, "az_deg")?.to_radians();
+        let zenith = value::<f64>(matches, "zenith_deg")?.to_radians();
+        steer_wgt(wgt.view(), d, freq_hz / 1e6, az, zenith)
+    } else {
+        wgt
+    };
+
+    let sym =
+        WgtSymmetry::from_name(required(matches, "sym")?).expect("restricted by possible_values");
+    let layout = ComplexWgtLayout::new(h, w, sym)?;
+    let guess = layout.from_wgt(wgt.view())?;
+    let obj = ComplexWgtObjective::new(ant_beam, target_beam, d, freq_hz / 1e6, nside, layout)?;
+    eprintln!("num of params: {}", guess.len());
+    eprintln!("init fitness: {}", obj.fitness(&guess)?);
+    eprintln!("target ant temp: {}", target_ant_out);
+
+    let mut log = ProgressLog::create(matches.value_of("log"))?;
+    let result = optimize_wgt(
+        &obj,
+        &guess,
+        npart,
+        None,
+        &solver(matches)?,
+        &stop_rule(matches)?,
+        &mut rng,
+        |x, progress| {
+            let rel_err = obj
+                .total_beam(x)
+                .and_then(|b| calc_ant_output(&b, sky))
+                .map_or(f64::NAN, |t| (t - target_ant_out).abs() / target_ant_out);
+            log.log(progress, &[("ant_temp_rel_err", rel_err)]);
+        },
+    )?;
+    log.finish(result.stop_reason)?;
+    prov.record_value("FITNESS", &obj.fitness(&result.x)?.to_string());
+    prov.record_value("WGTSYM", required(matches, "sym")?);
+
+    write_complex_wgt(
+        prov,
+        required(matches, "outfile")?,
+        &obj.layout.to_wgt(&result.x)?,
+        &WgtMeta::new(d, freq_hz),
+    )
+}
@@ -0,0 +1,132 @@
+use clap::{Arg, ArgGroup, ArgMatches, Command};
+
+use crate::{
+    arbitrary_array::{calc_array_beam, calc_phase_from_pointing},
+    cli::{
+        args, freq_hz, load_array_cfg, read_wgt_meta, value, wgt_freq_spacing, write_wgt_meta,
+        CliResult, Provenance, WgtMeta,
+    },
+    metrics::{angular_distance, calc_beam_metrics, BeamMetrics},
+    quantize::{check_bits, quantize_complex_wgt, quantize_wgt},
+    regular_array::wgt2pattern,
+};
+
+pub fn command() -> Command<'static> {
+    Command::new("quantize")
+        .about("beam degradation caused by fixed-point beamformer coefficients")
+        .arg(
+            Arg::new("wgt")
+                .short('w')
+                .long("wgt")
+                .takes_value(true)
+                .value_name("wgt file")
+                .required(false)
+                .help(
+                    "regular array wgt, with the spacing and freq read from the file if not given",
+                ),
+        )
+        .arg(
+            args::array_cfg()
+                .short('a')
+                .required(false)
+                .requires_all(&["az_deg", "zenith_deg"])
+                .help("arbitrary array steered with complex coefficients"),
+        )
+        .arg(
+            Arg::new("az_deg")
+                .long("az")
+                .takes_value(true)
+                .value_name("az from north in deg")
+                .required(false)
+                .allow_hyphen_values(true)
+                .help("azimuth of the pointing"),
+        )
+        .arg(
+            Arg::new("zenith_deg")
+                .long("zenith")
+                .takes_value(true)
+                .value_name("zenith angle in deg")
+                .required(false)
+                .help("zenith angle of the pointing"),
+        )
+        .arg(args::spacing().required(false))
+        .arg(args::freq().required(false))
+        .arg(args::nside().required(true))
+        .arg(
+            Arg::new("bits")
+                .short('b')
+                .long("bits")
+                .takes_value(true)
+                .value_name("bit depth")
+                .required(true)
+                .help("bit depth of the real and imaginary parts"),
+        )
+        .arg(args::outfile("quantized wgt").required(false))
+        .group(
+            ArgGroup::new("inputs")
+                .args(&["wgt", "array_cfg"])
+                .required(true),
+        )
+}
+
+fn print_metrics(name: &str, m: &BeamMetrics) {
+    println!(
+        "{} {} {} {} {} {}",
+        name,
+        m.peak_dir.pol.to_degrees(),
+        m.peak_dir.az.to_degrees(),
+        m.main_lobe_radius.to_degrees(),
+        m.sidelobe_level_db,
+        m.solid_angle
+    );
+}
+
+pub fn run(matches: &ArgMatches, prov: &mut Provenance) -> CliResult<()> {
+    let nside = value::<usize>(matches, "nside")?;
+    let bits = value::<u32>(matches, "bits")?;
+    check_bits(bits)?;
+
+    let (beam0, beam1) = if let Some(fname) = matches.value_of("wgt") {
+        let (wgt, meta) = read_wgt_meta(prov, fname)?;
+        let (freq_hz, d) = wgt_freq_spacing(matches, meta.as_ref())?;
+        let freq_mhz = freq_hz / 1e6;
+        let qwgt = quantize_wgt(wgt.view(), bits)?;
+        let beam0 = wgt2pattern(wgt.view(), d, freq_mhz, nside)?;
+        let beam1 = wgt2pattern(qwgt.view(), d, freq_mhz, nside)?;
+        if let Some(out) = matches.value_of("outfile") {
+            write_wgt_meta(prov, out, qwgt, &WgtMeta::new(d, freq_hz))?;
+        }
+        (beam0, beam1)
+    } else {
+        let freq_hz = freq_hz(matches, "freq")?;
+        let array_cfg = load_array_cfg(prov, matches.value_of("array_cfg").unwrap())?;
+        let (x_list, y_list, z_list) = array_cfg.xyz_lists();
+        let az = value::<f64>(matches, "az_deg")?.to_radians();
+        let zenith = value::<f64>(matches, "zenith_deg")?.to_radians();
+        let w_list = array_cfg.wgt_list();
+        let phi_list = calc_phase_from_pointing(&x_list, &y_list, &z_list, az, zenith, freq_hz)?;
+        let (qw_list, qphi_list) = quantize_complex_wgt(&w_list, &phi_list, bits)?;
+        let beam0 = calc_array_beam(
+            nside, &x_list, &y_list, &z_list, &w_list, &phi_list, freq_hz, true,
+        )?;
+        let beam1 = calc_array_beam(
+            nside, &x_list, &y_list, &z_list, &qw_list, &qphi_list, freq_hz, true,
+        )?;
+        (beam0, beam1)
+    };
+
+    let m0 = calc_beam_metrics(&beam0)?;
+    let m1 = calc_beam_metrics(&beam1)?;
+    println!("# name pol_deg az_deg main_lobe_radius_deg sll_dB solid_angle_sr");
+    print_metrics("continuous", &m0);
+    print_metrics("quantized", &m1);
+    println!(
+        "# pointing error: {} deg",
+        angular_distance(&m0.peak_dir, &m1.peak_dir).to_degrees()
+    );
+    println!(
+        "# sidelobe rise: {} dB",
+        m1.sidelobe_level_db - m0.sidelobe_level_db
+    );
+    Ok(())
+}
@@ -0,0 +1,123 @@
+use clap::{Arg, ArgMatches, Command};
+
+use ndarray::Array2;
+
+use crate::{
+    cli::{
+        io_error, read_hp_map, read_wgt, required, value, write_hp_columns, write_wgt, CliError,
+        CliResult, Provenance,
+    },
+    grid::{regrid, Equirect, HealpixNested, HealpixRing, SphericalGrid},
+};
+
+pub fn command() -> Command<'static> {
+    Command::new("regrid")
+        .about("convert a map between healpix ring, healpix nested and equirect theta/phi grids")
+        .arg(
+            Arg::new("input")
+                .short('i')
+                .long("input")
+                .takes_value(true)
+                .value_name("input file")
+                .required(true)
+                .help("healpix map, or for equirect a fits image with theta along rows (0 to 180 deg inclusive) and phi along columns"),
+        )
+        .arg(
+            Arg::new("from")
+                .long("from")
+                .takes_value(true)
+                .value_name("scheme")
+                .possible_values(["healpix", "equirect"])
+                .default_value("healpix")
+                .help("grid of the input, the ordering of a healpix map being read from its header"),
+        )
+        .arg(
+            Arg::new("to")
+                .long("to")
+                .takes_value(true)
+                .value_name("scheme")
+                .possible_values(["ring", "nested", "equirect"])
+                .default_value("ring")
+                .help("grid of the output"),
+        )
+        .arg(
+            Arg::new("nside")
+                .short('n')
+                .long("nside")
+                .takes_value(true)
+                .value_name("nside")
+                .required_if_eq_any(&[("to", "ring"), ("to", "nested")])
+                .help("nside of a healpix output"),
+        )
+        .arg(
+            Arg::new("ntheta")
+                .long("ntheta")
+                .takes_value(true)
+                .value_name("ntheta")
+                .default_value("181")
+                .help("num of theta rows of an equirect output"),
+        )
+        .arg(
+            Arg::new("nphi")
+                .long("nphi")
+                .takes_value(true)
+                .value_name("nphi")
+                .default_value("360")
+                .help("num of phi columns of an equirect output"),
+        )
+        .arg(
+            Arg::new("outfile")
+                .short('o')
+                .long("out")
+                .takes_value(true)
+                .value_name("outfile")
+                .required(true)
+                .help("output file"),
+        )
+}
+
+fn regrid_to<S: SphericalGrid>(
+    src: &S,
+    map: &[f64],
+    matches: &ArgMatches,
+    prov: &Provenance,
+) -> CliResult<()> {
+    let outfile = required(matches, "outfile")?;
+    match required(matches, "to")? {
+        "ring" => {
+            let dst = HealpixRing::new(value::<usize>(matches, "nside")?)?;
+            write_hp_columns(prov, outfile, &[&regrid(src, map, &dst)?], false)?;
+        }
+        "nested" => {
+            let dst = HealpixNested::new(value::<usize>(matches, "nside")?)?;
+            write_hp_columns(prov, outfile, &[&regrid(src, map, &dst)?], true)?;
+        }
+        _ => {
+            let dst = Equirect::new(
+                value::<usize>(matches, "ntheta")?,
+                value::<usize>(matches, "nphi")?,
+            )?;
+            let data = Array2::from_shape_vec((dst.ntheta, dst.nphi), regrid(src, map, &dst)?)
+                .map_err(|e| io_error(outfile, e))?;
+            write_wgt(prov, outfile, data)?;
+        }
+    }
+    Ok(())
+}
+
+pub fn run(matches: &ArgMatches, prov: &mut Provenance) -> CliResult<()> {
+    let input = required(matches, "input")?;
+    match required(matches, "from")? {
+        "healpix" => {
+            let map = read_hp_map(prov, input)?;
+            regrid_to(&HealpixRing::from_npix(map.len())?, &map, matches, prov)
+        }
+        "equirect" => {
+            let img = read_wgt(prov, input)?;
+            let src = Equirect::new(img.nrows(), img.ncols())?;
+            let map: Vec<f64> = img.iter().cloned().collect();
+            regrid_to(&src, &map, matches, prov)
+        }
+        s => Err(CliError::Invalid(format!("unknown grid '{}'", s))),
+    }
+}
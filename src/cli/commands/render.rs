@@ -0,0 +1,124 @@
+use std::{fs::File, io::BufWriter};
+
+use clap::{Arg, ArgMatches, Command};
+
+use crate::{
+    cli::{args, io_error, opt_value, read_hp_map, required, value, CliResult, Provenance},
+    projection::Projection,
+    render::{colorbar, render, write_png, RenderCfg},
+};
+
+pub fn command() -> Command<'static> {
+    Command::new("render")
+        .about("render a healpix map (beam or sky) to a png in dB")
+        .arg(
+            Arg::new("map")
+                .short('m')
+                .long("map")
+                .takes_value(true)
+                .value_name("healpix file")
+                .required(true)
+                .help("input healpix map"),
+        )
+        .arg(
+            Arg::new("projection")
+                .short('p')
+                .long("proj")
+                .takes_value(true)
+                .value_name("projection")
+                .required(false)
+                .possible_values(["mollweide", "orthographic", "azel"])
+                .default_value("mollweide")
+                .help("mollweide (full sky), orthographic (from zenith) or azel grid"),
+        )
+        .arg(
+            Arg::new("width")
+                .long("width")
+                .takes_value(true)
+                .value_name("width")
+                .required(false)
+                .default_value("800")
+                .help("image width in pixels"),
+        )
+        .arg(
+            Arg::new("height")
+                .long("height")
+                .takes_value(true)
+                .value_name("height")
+                .required(false)
+                .help("image height in pixels, half the width for mollweide and azel, equal to it for orthographic if not given"),
+        )
+        .arg(
+            Arg::new("db_range")
+                .long("range")
+                .takes_value(true)
+                .value_name("dB")
+                .required(false)
+                .default_value("40")
+                .help("dynamic range of the color scale below the peak, in dB"),
+        )
+        .arg(
+            Arg::new("graticule")
+                .long("graticule")
+                .takes_value(true)
+                .value_name("deg")
+                .required(false)
+                .default_value("30")
+                .help("spacing of the az/el graticule in deg, 0 to disable"),
+        )
+        .arg(
+            Arg::new("colorbar")
+                .long("colorbar")
+                .takes_value(false)
+                .required(false)
+                .help("append a color bar below the map"),
+        )
+        .arg(args::outfile("output png file"))
+}
+
+pub fn run(matches: &ArgMatches, prov: &mut Provenance) -> CliResult<()> {
+    let map = read_hp_map(prov, required(matches, "map")?)?;
+    let projection = Projection::from_name(required(matches, "projection")?)?;
+    let width = value::<usize>(matches, "width")?;
+    let height = opt_value::<usize>(matches, "height")?.unwrap_or(match projection {
+        Projection::Orthographic => width,
+        Projection::Mollweide | Projection::AzEl => width / 2,
+    });
+    let graticule = value::<f64>(matches, "graticule")?;
+    let cfg = RenderCfg {
+        projection,
+        width,
+        height,
+        db_range: value::<f64>(matches, "db_range")?,
+        graticule_deg: if graticule > 0.0 {
+            Some(graticule)
+        } else {
+            None
+        },
+    };
+
+    let mut rgb = render(&map, &cfg)?;
+    let mut total_height = height;
+    if matches.is_present("colorbar") {
+        let gap = (height / 40).max(2);
+        let bar_height = (height / 20).max(4);
+        rgb.resize(rgb.len() + width * gap * 3, 255);
+        rgb.extend(colorbar(width, bar_height));
+        total_height += gap + bar_height;
+        eprintln!(
+            "color bar: -{} dB (left) to 0 dB (right) relative to the peak",
+            cfg.db_range
+        );
+    }
+
+    let outfile = required(matches, "outfile")?;
+    let file = File::create(outfile).map_err(|e| io_error(outfile, e))?;
+    write_png(
+        BufWriter::new(file),
+        width,
+        total_height,
+        &rgb,
+        &prov.attrs(),
+    )
+    .map_err(|e| io_error(outfile, e))
+}
@@ -0,0 +1,423 @@
+use std::{
+    fs::{copy, create_dir_all, read, read_to_string, remove_dir_all, write, File},
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use clap::{Arg, ArgMatches, Command};
+
+use rand::{rngs::StdRng, thread_rng, Rng, SeedableRng};
+
+use scorus::healpix::utils::nside2npix;
+
+#[cfg(feature = "hdf5")]
+use crate::cli::h5::H5Products;
+use crate::{
+    arbitrary_array::average_beam_over_lst,
+    cli::{
+        commands::ant_beam::calc_ant_beam, io_error, progress::ProgressLog, provenance::sha256_hex,
+        read_cfg, read_hp_map, read_hp_map_npix, read_wgt_meta, required, write_hp_columns,
+        write_wgt_meta, CliError, CliResult, Provenance, WgtMeta,
+    },
+    job::{ElementCfg, JobCfg, Product, TargetCfg},
+    regular_array::{
+        deflattern_quarter_wgt, full2quarter,
+        opt::{optimize_wgt, QuarterWgtObjective, WgtObjective},
+        quarter2full, regular_wgt2beam, target_beam2wgt,
+    },
+    utils::{calc_ant_output, calc_averaged_ant_output, gaussian_beam},
+};
+
+pub fn command() -> Command<'static> {
+    Command::new("run")
+        .about("run all steps of a YAML or TOML job file")
+        .arg(
+            Arg::new("job")
+                .takes_value(true)
+                .value_name("job file")
+                .required(true)
+                .help("job file, TOML if it ends with .toml, YAML otherwise"),
+        )
+        .arg(
+            Arg::new("force")
+                .long("force")
+                .takes_value(false)
+                .help("discard cached intermediate products"),
+        )
+}
+
+fn path_str(p: &Path) -> String {
+    p.to_string_lossy().into_owned()
+}
+
+/// Files read by the job, whose content the cached products depend on.
+fn input_files(job: &JobCfg) -> Vec<&str> {
+    let element = match &job.element {
+        Some(ElementCfg::Nec(fname)) | Some(ElementCfg::Healpix(fname)) => Some(fname),
+        None => None,
+    };
+    let target = match &job.target {
+        TargetCfg::Healpix(fname) => Some(fname),
+        _ => None,
+    };
+    [job.sky.as_ref(), element, target]
+        .into_iter()
+        .flatten()
+        .map(|s| s.as_str())
+        .collect()
+}
+
+/// The cache is kept only if it was produced by an identical job reading
+/// identical input files; the list of requested products and the progress
+/// log do not affect the cached steps.
+fn prepare_cache(cache_dir: &Path, job: &JobCfg, force: bool) -> CliResult<()> {
+    let mut stamp_job = job.clone();
+    stamp_job.output.products.clear();
+    stamp_job.output.hdf5 = None;
+    if let Some(opt) = &mut stamp_job.optimize {
+        opt.log = false;
+    }
+    let mut stamp = serde_yaml::to_string(&stamp_job).map_err(|e| io_error(&job.name, e))?;
+    for fname in input_files(job) {
+        let data = read(fname).map_err(|e| io_error(fname, e))?;
+        stamp += &format!("# {} sha256={}\n", fname, sha256_hex(&data));
+    }
+    let stamp_file = cache_dir.join("job.yaml");
+    let valid = !force
+        && read_to_string(&stamp_file)
+            .map(|s| s == stamp)
+            .unwrap_or(false);
+    if !valid && cache_dir.exists() {
+        remove_dir_all(cache_dir).map_err(|e| io_error(&path_str(cache_dir), e))?;
+    }
+    create_dir_all(cache_dir).map_err(|e| io_error(&path_str(cache_dir), e))?;
+    write(&stamp_file, stamp).map_err(|e| io_error(&path_str(&stamp_file), e))
+}
+
+fn cached_map<F>(prov: &mut Provenance, path: &Path, compute: F) -> CliResult<Vec<f64>>
+where
+    F: FnOnce(&mut Provenance) -> CliResult<Vec<f64>>,
+{
+    let fname = path_str(path);
+    if path.exists() {
+        eprintln!("cached: {}", fname);
+        return read_hp_map(prov, &fname);
+    }
+    eprintln!("computing: {}", fname);
+    let data = compute(prov)?;
+    write_hp_columns(prov, &fname, &[&data], false)?;
+    Ok(data)
+}
+
+fn cached_wgt<F>(
+    prov: &mut Provenance,
+    path: &Path,
+    meta: &WgtMeta,
+    compute: F,
+) -> CliResult<ndarray::Array2<f64>>
+where
+    F: FnOnce(&mut Provenance) -> CliResult<ndarray::Array2<f64>>,
+{
+    let fname = path_str(path);
+    if path.exists() {
+        eprintln!("cached: {}", fname);
+        return Ok(read_wgt_meta(prov, &fname)?.0);
+    }
+    eprintln!("computing: {}", fname);
+    let wgt = compute(prov)?;
+    write_wgt_meta(prov, &fname, wgt.clone(), meta)?;
+    Ok(wgt)
+}
+
+/// Everything computed at one freq, kept for the HDF5 output.
+#[cfg_attr(not(feature = "hdf5"), allow(dead_code))]
+struct FreqProducts {
+    freq_hz: f64,
+    spacing_m: f64,
+    ant_beam: Vec<f64>,
+    target_beam: Vec<f64>,
+    wgt: ndarray::Array2<f64>,
+    beam: Vec<f64>,
+}
+
+#[cfg(feature = "hdf5")]
+fn write_hdf5(
+    prov: &Provenance,
+    path: &str,
+    job: &JobCfg,
+    products: &[FreqProducts],
+    ant_temps: &[(f64, f64)],
+) -> CliResult<()> {
+    let h5 = H5Products::create(path)?;
+    let freqs_hz: Vec<f64> = products.iter().map(|p| p.freq_hz).collect();
+    let yaml = |name: &str, s: Result<String, serde_yaml::Error>| {
+        s.map_err(|e| io_error(path, format!("{}: {}", name, e)))
+    };
+    let job_yaml = yaml("job", serde_yaml::to_string(job))?;
+    let site_yaml = yaml("site", serde_yaml::to_string(&job.site))?;
+    let array_yaml = yaml("array", serde_yaml::to_string(&job.array))?;
+    let provenance = prov.attrs();
+    let mut attrs = vec![
+        ("name", job.name.as_str()),
+        ("job", &job_yaml),
+        ("site", &site_yaml),
+        ("array", &array_yaml),
+    ];
+    attrs.extend(provenance.iter().map(|(k, v)| (k.as_str(), v.as_str())));
+    h5.write_meta(&attrs, &freqs_hz)?;
+
+    let maps = |f: fn(&FreqProducts) -> &Vec<f64>| -> Vec<&[f64]> {
+        products.iter().map(|p| &f(p)[..]).collect()
+    };
+    let freqs = Some(&freqs_hz[..]);
+    for product in &job.output.products {
+        match product {
+            Product::AntBeam => h5.write_beams(product.name(), freqs, &maps(|p| &p.ant_beam))?,
+            Product::TargetBeam => {
+                h5.write_beams(product.name(), freqs, &maps(|p| &p.target_beam))?
+            }
+            Product::Beam => h5.write_beams(product.name(), freqs, &maps(|p| &p.beam))?,
+            Product::Wgt => h5.write_wgts(
+                product.name(),
+                &freqs_hz,
+                &products.iter().map(|p| p.spacing_m).collect::<Vec<_>>(),
+                &products.iter().map(|p| p.wgt.clone()).collect::<Vec<_>>(),
+            )?,
+            Product::AntTemp => {
+                let (f, t): (Vec<f64>, Vec<f64>) = ant_temps.iter().cloned().unzip();
+                h5.write_table(product.name(), &[("freq_mhz", &f), ("ant_temp", &t)])?
+            }
+        }
+    }
+    Ok(())
+}
+
+pub fn run_job(job: &JobCfg, force: bool, prov: &mut Provenance) -> CliResult<()> {
+    if job.output.hdf5.is_some() && !cfg!(feature = "hdf5") {
+        return Err(CliError::Invalid(
+            "hdf5 output needs the hdf5 feature".to_string(),
+        ));
+    }
+    if let Some(opt) = &job.optimize {
+        opt.check()?;
+    }
+    let nside = job.nside;
+    let npix = nside2npix(nside);
+    let out_dir = PathBuf::from(&job.output.dir);
+    let cache_dir = out_dir.join("cache");
+    prepare_cache(&cache_dir, job, force)?;
+    if let Ok(yaml) = serde_yaml::to_string(&job.array) {
+        prov.record_array_cfg(&yaml);
+    }
+
+    let sky = job
+        .sky
+        .as_ref()
+        .map(|fname| read_hp_map_npix(prov, fname, npix))
+        .transpose()?;
+    if sky.is_none() && (job.optimize.is_some() || job.output.products.contains(&Product::AntTemp))
+    {
+        return Err(CliError::Invalid(
+            "a sky is needed by optimize and ant_temp".to_string(),
+        ));
+    }
+    let fixed_ant_beam = match &job.element {
+        Some(ElementCfg::Healpix(fname)) => Some(read_hp_map_npix(prov, fname, npix)?),
+        Some(ElementCfg::Nec(_)) => None,
+        None => Some(vec![1.0; npix]),
+    };
+    // analytic targets with a ref freq are computed per freq in the loop
+    let fixed_target_beam = match &job.target {
+        TargetCfg::Gaussian { sigma_deg } => Some(gaussian_beam(nside, *sigma_deg)?),
+        TargetCfg::Healpix(fname) => Some(read_hp_map_npix(prov, fname, npix)?),
+        TargetCfg::Analytic(target) if target.ref_freq_mhz.is_none() => {
+            Some(target.healpix(nside, 0.0)?)
+        }
+        TargetCfg::Analytic(_) => None,
+    };
+    if let Some(target_beam) = &fixed_target_beam {
+        write_hp_columns(
+            prov,
+            &path_str(&cache_dir.join("target_beam.fits")),
+            &[target_beam],
+            false,
+        )?;
+    }
+
+    if let Some(ant_beam) = &fixed_ant_beam {
+        write_hp_columns(
+            prov,
+            &path_str(&cache_dir.join("ant_beam.fits")),
+            &[ant_beam],
+            false,
+        )?;
+    }
+
+    let mut ant_temps = Vec::new();
+    let mut freq_products = Vec::new();
+    for freq_hz in job.freqs.freqs_hz() {
+        let freq_mhz = freq_hz / 1e6;
+        let tag = format!("{}MHz", freq_mhz);
+        let d = job.array.spacing(freq_hz).ok_or_else(|| {
+            CliError::Invalid("either spacing_m or spacing_lambda is needed".to_string())
+        })?;
+
+        let ant_beam = if let Some(ElementCfg::Nec(nec_file)) = &job.element {
+            cached_map(
+                prov,
+                &cache_dir.join(format!("ant_beam_{}.fits", tag)),
+                |prov| calc_ant_beam(prov, nec_file, freq_mhz, nside),
+            )?
+        } else {
+            fixed_ant_beam.clone().unwrap()
+        };
+
+        let target_beam = match (&fixed_target_beam, &job.target) {
+            (Some(target_beam), _) => target_beam.clone(),
+            (None, TargetCfg::Analytic(target)) => cached_map(
+                prov,
+                &cache_dir.join(format!("target_beam_{}.fits", tag)),
+                |_| Ok(target.healpix(nside, freq_mhz)?),
+            )?,
+            _ => unreachable!(),
+        };
+
+        let wgt_meta = WgtMeta::new(d, freq_hz);
+        let wgt0 = cached_wgt(
+            prov,
+            &cache_dir.join(format!("wgt0_{}.fits", tag)),
+            &wgt_meta,
+            |_| {
+                Ok(target_beam2wgt(
+                    &target_beam,
+                    &ant_beam,
+                    d,
+                    freq_mhz,
+                    job.array.size,
+                )?)
+            },
+        )?;
+
+        let wgt = if let (Some(opt), Some(sky)) = (&job.optimize, &sky) {
+            cached_wgt(
+                prov,
+                &cache_dir.join(format!("wgt_{}.fits", tag)),
+                &wgt_meta,
+                |prov| {
+                    let h = wgt0.shape()[0];
+                    let w = wgt0.shape()[1];
+                    let obj = QuarterWgtObjective::new(
+                        &ant_beam,
+                        &target_beam,
+                        d,
+                        freq_mhz,
+                        nside,
+                        h,
+                        w,
+                    )?;
+                    let guess: Vec<_> = full2quarter(wgt0.view()).iter().skip(1).cloned().collect();
+                    let seed = opt.seed.unwrap_or_else(|| thread_rng().gen());
+                    eprintln!("seed: {}", seed);
+                    prov.record_seed(seed);
+                    let mut rng = StdRng::seed_from_u64(seed);
+                    let target_ant_out = calc_ant_output(&target_beam, sky)?;
+                    let log_file = path_str(&out_dir.join(format!("opt_log_{}.csv", tag)));
+                    let mut log = ProgressLog::create(opt.log.then_some(log_file.as_str()))?;
+                    let result = optimize_wgt(
+                        &obj,
+                        &guess,
+                        opt.npart,
+                        opt.bits,
+                        &opt.solver,
+                        &opt.stop,
+                        &mut rng,
+                        |x, progress| {
+                            let rel_err = obj
+                                .total_beam(x)
+                                .and_then(|b| calc_ant_output(&b, sky))
+                                .map_or(f64::NAN, |t| (t - target_ant_out).abs() / target_ant_out);
+                            log.log(progress, &[("ant_temp_rel_err", rel_err)]);
+                        },
+                    )?;
+                    log.finish(result.stop_reason)?;
+                    prov.record_value("FITNESS", &obj.fitness(&result.x)?.to_string());
+                    Ok(quarter2full(
+                        deflattern_quarter_wgt(&result.x, h, w)?.view(),
+                    )?)
+                },
+            )?
+        } else {
+            write_wgt_meta(
+                prov,
+                &path_str(&cache_dir.join(format!("wgt_{}.fits", tag))),
+                wgt0.clone(),
+                &wgt_meta,
+            )?;
+            wgt0
+        };
+
+        let beam = cached_map(prov, &cache_dir.join(format!("beam_{}.fits", tag)), |_| {
+            Ok(regular_wgt2beam(wgt.view(), &ant_beam, d, freq_mhz, nside)?)
+        })?;
+
+        if let Some(sky) = &sky {
+            let (mean_beam, _, _) = average_beam_over_lst(job.site.lat_deg, &beam)?;
+            ant_temps.push((freq_mhz, calc_averaged_ant_output(&mean_beam, sky)?));
+        }
+        if job.output.hdf5.is_some() {
+            freq_products.push(FreqProducts {
+                freq_hz,
+                spacing_m: d,
+                ant_beam,
+                target_beam,
+                wgt,
+                beam,
+            });
+        }
+    }
+
+    #[cfg(feature = "hdf5")]
+    if let Some(fname) = &job.output.hdf5 {
+        return write_hdf5(
+            prov,
+            &path_str(&out_dir.join(fname)),
+            job,
+            &freq_products,
+            &ant_temps,
+        );
+    }
+
+    for product in &job.output.products {
+        if *product == Product::AntTemp {
+            let fname = path_str(&out_dir.join("ant_temp.txt"));
+            let mut outfile = File::create(&fname).map_err(|e| io_error(&fname, e))?;
+            write!(outfile, "{}", prov.comments()).map_err(|e| io_error(&fname, e))?;
+            for (f, t) in &ant_temps {
+                writeln!(outfile, "{} {}", f, t).map_err(|e| io_error(&fname, e))?;
+            }
+            continue;
+        }
+        for entry in cache_dir
+            .read_dir()
+            .map_err(|e| io_error(&path_str(&cache_dir), e))?
+        {
+            let src = entry
+                .map_err(|e| io_error(&path_str(&cache_dir), e))?
+                .path();
+            let stem = src
+                .file_stem()
+                .map(|s| s.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            if stem == product.name() || stem.starts_with(&format!("{}_", product.name())) {
+                let dst = out_dir.join(src.file_name().unwrap());
+                copy(&src, &dst).map_err(|e| io_error(&path_str(&dst), e))?;
+            }
+        }
+    }
+    Ok(())
+}
+
+pub fn run(matches: &ArgMatches, prov: &mut Provenance) -> CliResult<()> {
+    let job: JobCfg = read_cfg(prov, required(matches, "job")?)?;
+    run_job(&job, matches.is_present("force"), prov)
+}
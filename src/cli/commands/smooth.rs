@@ -0,0 +1,65 @@
+use clap::{Arg, ArgMatches, Command};
+
+use crate::{
+    cli::{
+        args, opt_value, read_hp_map, read_hp_map_npix, required, write_hp_map, CliResult,
+        Provenance,
+    },
+    sht::{beam_bl, gaussian_bl, smooth},
+    utils::checked_npix2nside,
+};
+
+pub fn command() -> Command<'static> {
+    Command::new("smooth")
+        .about("band-limit a healpix map, or convolve it with a beam or a gaussian")
+        .arg(
+            Arg::new("input_healpix")
+                .short('i')
+                .long("input")
+                .takes_value(true)
+                .value_name("healpix file")
+                .required(true)
+                .help("map to smooth, e.g. a sky or a target beam"),
+        )
+        .arg(
+            Arg::new("beam")
+                .short('b')
+                .long("beam")
+                .takes_value(true)
+                .value_name("healpix file")
+                .required(false)
+                .conflicts_with("fwhm")
+                .help("beam to convolve with, treated as symmetric about its axis"),
+        )
+        .arg(
+            Arg::new("fwhm")
+                .long("fwhm")
+                .takes_value(true)
+                .value_name("deg")
+                .required(false)
+                .help("fwhm of a gaussian to convolve with"),
+        )
+        .arg(args::lmax())
+        .arg(args::hemi())
+        .arg(args::outfile("output healpix file"))
+}
+
+pub fn run(matches: &ArgMatches, prov: &mut Provenance) -> CliResult<()> {
+    let map = read_hp_map(prov, required(matches, "input_healpix")?)?;
+    let nside = checked_npix2nside(map.len())?;
+    let lmax = opt_value::<usize>(matches, "lmax")?.unwrap_or(3 * nside - 1);
+
+    let bl = if let Some(fname) = matches.value_of("beam") {
+        beam_bl(&read_hp_map_npix(prov, fname, map.len())?, lmax)?
+    } else if let Some(fwhm) = opt_value::<f64>(matches, "fwhm")? {
+        gaussian_bl(fwhm, lmax)
+    } else {
+        vec![1.0; lmax + 1]
+    };
+    write_hp_map(
+        prov,
+        required(matches, "outfile")?,
+        &smooth(&map, &bl, lmax)?,
+        matches.is_present("hemi"),
+    )
+}
@@ -0,0 +1,128 @@
+use clap::{Arg, ArgMatches, Command};
+
+use crate::{
+    cli::{
+        args, freq_hz, freq_list_hz, load_array_cfg, opt_value, required, value, CliError,
+        CliResult, Provenance,
+    },
+    steering::{calc_delay_from_pointing, calc_squint, find_beam_peak, Steering},
+};
+
+pub fn command() -> Command<'static> {
+    Command::new("squint")
+        .about("beam squint and gain loss of true-time-delay and phase-only steering")
+        .arg(
+            args::array_cfg()
+                .short('a')
+                .help("array cfg, the stored element delays are used if az/zenith are not given"),
+        )
+        .arg(
+            Arg::new("az_deg")
+                .long("az")
+                .takes_value(true)
+                .value_name("az from north in deg")
+                .required(false)
+                .requires("zenith_deg")
+                .allow_hyphen_values(true)
+                .help("azimuth of the pointing"),
+        )
+        .arg(
+            Arg::new("zenith_deg")
+                .long("zenith")
+                .takes_value(true)
+                .value_name("zenith angle in deg")
+                .required(false)
+                .requires("az_deg")
+                .help("zenith angle of the pointing"),
+        )
+        .arg(
+            Arg::new("ref_freq")
+                .short('r')
+                .long("ref")
+                .takes_value(true)
+                .value_name("ref freq")
+                .required(true)
+                .help("freq at which the phase-only steering is computed"),
+        )
+        .arg(args::fmin())
+        .arg(args::fmax())
+        .arg(args::nfreq("16"))
+        .arg(
+            args::nside()
+                .default_value("64")
+                .help("nside of the coarse peak search"),
+        )
+}
+
+pub fn run(matches: &ArgMatches, prov: &mut Provenance) -> CliResult<()> {
+    let mut array_cfg = load_array_cfg(prov, required(matches, "array_cfg")?)?;
+    let (x_list, y_list, z_list) = array_cfg.xyz_lists();
+    let w_list = array_cfg.wgt_list();
+
+    if let (Some(az), Some(zenith)) = (
+        opt_value::<f64>(matches, "az_deg")?,
+        opt_value::<f64>(matches, "zenith_deg")?,
+    ) {
+        array_cfg.set_delays(&calc_delay_from_pointing(
+            &x_list,
+            &y_list,
+            &z_list,
+            az.to_radians(),
+            zenith.to_radians(),
+        ))?;
+    }
+    let delay = array_cfg.delay_list().ok_or_else(|| {
+        CliError::Invalid("every element needs a delay if no pointing is given".to_string())
+    })?;
+
+    let ref_freq_hz = freq_hz(matches, "ref_freq")?;
+    let freqs_hz = freq_list_hz(matches)?;
+    let nside = value::<usize>(matches, "nside")?;
+
+    // the true-time-delay beam does not squint, so its peak defines the target
+    let target = find_beam_peak(
+        &x_list,
+        &y_list,
+        &z_list,
+        &w_list,
+        &Steering::TrueTimeDelay.phase(&delay, ref_freq_hz),
+        ref_freq_hz,
+        nside,
+    )?;
+
+    let ttd = calc_squint(
+        &x_list,
+        &y_list,
+        &z_list,
+        &w_list,
+        &delay,
+        Steering::TrueTimeDelay,
+        &target,
+        &freqs_hz,
+        nside,
+    )?;
+    let phase_only = calc_squint(
+        &x_list,
+        &y_list,
+        &z_list,
+        &w_list,
+        &delay,
+        Steering::PhaseOnly { ref_freq_hz },
+        &target,
+        &freqs_hz,
+        nside,
+    )?;
+
+    println!("# freq_MHz squint_ttd_deg loss_ttd_dB squint_phase_deg loss_phase_dB");
+    for (t, p) in ttd.iter().zip(phase_only.iter()) {
+        println!(
+            "{} {} {} {} {}",
+            t.freq_hz / 1e6,
+            t.squint_deg,
+            t.gain_loss_db,
+            p.squint_deg,
+            p.gain_loss_db
+        );
+    }
+    Ok(())
+}
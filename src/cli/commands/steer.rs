@@ -0,0 +1,72 @@
+use clap::{Arg, ArgGroup, ArgMatches, Command};
+
+use scorus::{
+    coordinates::{rotation3d::RotMatrix, vec3d::Vec3d},
+    healpix::rotation::rotate_ring,
+};
+
+use crate::cli::{
+    args, opt_value, read_hp_map, required, value, write_hp_columns, CliResult, Provenance,
+};
+
+pub fn command() -> Command<'static> {
+    Command::new("steer")
+        .about("rotate a beam or a sky map between horizontal and equatorial frames")
+        .arg(
+            Arg::new("beam")
+                .short('b')
+                .long("beam")
+                .takes_value(true)
+                .value_name("beam healpix")
+                .required(false)
+                .help("healpix"),
+        )
+        .arg(args::sky().required(false).help("sky healpix"))
+        .arg(args::outfile("out healpix file"))
+        .arg(args::lat())
+        .arg(
+            Arg::new("lon")
+                .short('m')
+                .long("lon")
+                .takes_value(true)
+                .value_name("lon in deg")
+                .required(false)
+                .allow_hyphen_values(true)
+                .help("lon"),
+        )
+        .group(
+            ArgGroup::new("inputs")
+                .args(&["beam", "sky"])
+                .required(true),
+        )
+}
+
+pub fn run(matches: &ArgMatches, prov: &mut Provenance) -> CliResult<()> {
+    let lat = value::<f64>(matches, "lat")?;
+    let lon = opt_value::<f64>(matches, "lon")?.unwrap_or(0.0);
+
+    let (hp_data, rot) = if let Some(fname) = matches.value_of("beam") {
+        let data = read_hp_map(prov, fname)?;
+        let rot =
+            RotMatrix::about_axis_by_angle(&Vec3d::new(0.0, 0.0, 1.0), (90.0 + lon).to_radians())
+                * RotMatrix::about_axis_by_angle(
+                    &Vec3d::new(1.0, 0.0, 0.0),
+                    (90.0 - lat).to_radians(),
+                );
+        (data, rot)
+    } else {
+        let data = read_hp_map(prov, required(matches, "sky")?)?;
+        let rot =
+            RotMatrix::about_axis_by_angle(&Vec3d::new(1.0, 0.0, 0.0), -(90.0 - lat).to_radians())
+                * RotMatrix::about_axis_by_angle(
+                    &Vec3d::new(0.0, 0.0, 1.0),
+                    -(90.0 + lon).to_radians(),
+                );
+        (data, rot)
+    };
+
+    println!("{:?}", rot);
+    let rotated = rotate_ring(&hp_data, &rot);
+    write_hp_columns(prov, required(matches, "outfile")?, &[&rotated], false)?;
+    Ok(())
+}
@@ -0,0 +1,123 @@
+use clap::{Arg, ArgMatches, Command};
+
+use crate::{
+    cli::{
+        args, freq_list_hz, opt_freq_hz, opt_value, required, value, write_hp_cube, write_hp_map,
+        CliError, CliResult, Provenance,
+    },
+    target::{TargetBeam, TargetShape},
+};
+
+fn deg_arg(name: &'static str, long: &'static str, help: &'static str) -> Arg<'static> {
+    Arg::new(name)
+        .long(long)
+        .takes_value(true)
+        .value_name("deg")
+        .required(false)
+        .help(help)
+}
+
+pub fn command() -> Command<'static> {
+    Command::new("target")
+        .about("analytic target beam centred on the zenith, at one freq or as a freq cube")
+        .arg(
+            Arg::new("shape")
+                .long("shape")
+                .takes_value(true)
+                .value_name("shape")
+                .possible_values(["gaussian", "cos-n", "airy", "flat-top"])
+                .required(true),
+        )
+        .arg(deg_arg("sigma", "sigma", "gaussian sigma, along the major axis if elliptical"))
+        .arg(deg_arg("sigma_minor", "sigma-minor", "gaussian sigma along the minor axis, circular if not given"))
+        .arg(deg_arg("pa", "pa", "azimuth of the major axis of the gaussian, from north towards east").default_value("0"))
+        .arg(
+            Arg::new("n")
+                .long("n")
+                .takes_value(true)
+                .value_name("n")
+                .required_if_eq("shape", "cos-n")
+                .help("power of cos(theta)"),
+        )
+        .arg(deg_arg("fwhm", "fwhm", "fwhm of the airy beam").required_if_eq("shape", "airy"))
+        .arg(deg_arg("radius", "radius", "radius of the flat top").required_if_eq("shape", "flat-top"))
+        .arg(deg_arg("taper", "taper", "width of the raised cosine taper of the flat top").default_value("0"))
+        .arg(
+            deg_arg("horizon", "horizon", "elevation below which the beam is zero")
+                .default_value("0")
+                .allow_hyphen_values(true),
+        )
+        .arg(
+            Arg::new("ref_freq")
+                .long("ref-freq")
+                .takes_value(true)
+                .value_name("freq")
+                .required(false)
+                .help("freq at which the widths are given, scaling as 1 / freq at other freqs; frequency independent if not given"),
+        )
+        .arg(
+            args::freq()
+                .required(false)
+                .conflicts_with("fmin")
+                .help("freq of the output map, in MHz unless a unit is given"),
+        )
+        .arg(args::fmin().required(false).requires("fmax").help("min freq of a cube, in MHz unless a unit is given"))
+        .arg(args::fmax().required(false).requires("fmin"))
+        .arg(args::nfreq("1"))
+        .arg(args::nside().required(true))
+        .arg(args::hemi().conflicts_with("fmin"))
+        .arg(args::outfile("output healpix file, one column per freq for a cube"))
+}
+
+fn shape(matches: &ArgMatches) -> CliResult<TargetShape> {
+    Ok(match required(matches, "shape")? {
+        "gaussian" => TargetShape::Gaussian {
+            sigma_deg: value::<f64>(matches, "sigma")?,
+            sigma_minor_deg: opt_value::<f64>(matches, "sigma_minor")?,
+            pa_deg: value::<f64>(matches, "pa")?,
+        },
+        "cos-n" => TargetShape::CosN {
+            n: value::<f64>(matches, "n")?,
+        },
+        "airy" => TargetShape::Airy {
+            fwhm_deg: value::<f64>(matches, "fwhm")?,
+        },
+        "flat-top" => TargetShape::FlatTop {
+            radius_deg: value::<f64>(matches, "radius")?,
+            taper_deg: value::<f64>(matches, "taper")?,
+        },
+        s => return Err(CliError::Invalid(format!("unknown shape '{}'", s))),
+    })
+}
+
+pub fn run(matches: &ArgMatches, prov: &mut Provenance) -> CliResult<()> {
+    let nside = value::<usize>(matches, "nside")?;
+    let target = TargetBeam {
+        shape: shape(matches)?,
+        horizon_deg: value::<f64>(matches, "horizon")?,
+        ref_freq_mhz: opt_freq_hz(matches, "ref_freq")?.map(|f| f / 1e6),
+    };
+    let outfile = required(matches, "outfile")?;
+
+    if matches.is_present("fmin") {
+        let freqs_hz = freq_list_hz(matches)?;
+        let freqs_mhz: Vec<f64> = freqs_hz.iter().map(|f| f / 1e6).collect();
+        let cube = target.healpix_cube(nside, &freqs_mhz)?;
+        for (i, f) in freqs_mhz.iter().enumerate() {
+            println!("{} {}", i, f);
+        }
+        let columns: Vec<&[f64]> = cube.iter().map(|m| &m[..]).collect();
+        write_hp_cube(prov, outfile, &columns, &freqs_hz)
+    } else {
+        if target.ref_freq_mhz.is_some() && !matches.is_present("freq") {
+            return Err(CliError::MissingArg("freq".to_string()));
+        }
+        let freq_mhz = opt_freq_hz(matches, "freq")?.map_or(0.0, |f| f / 1e6);
+        let map = if matches.is_present("hemi") {
+            target.healpix_hemi(nside, freq_mhz)?
+        } else {
+            target.healpix(nside, freq_mhz)?
+        };
+        write_hp_map(prov, outfile, &map, false)
+    }
+}
@@ -0,0 +1,194 @@
+use clap::{Arg, ArgMatches, Command};
+
+use crate::{
+    arbitrary_array::{average_beam_over_lst, calc_array_beam},
+    cli::{
+        args, freq_hz, load_ant_beam, load_array_cfg, read_hp_map_npix, required, seeded_rng,
+        value, write_table, CliError, CliResult, Provenance,
+    },
+    metrics::{angular_distance, calc_beam_metrics},
+    tolerance::{calc_perturbed_array_beam, summarize, Stats, Tolerance},
+    utils::calc_averaged_ant_output,
+};
+
+fn tolerance_arg(name: &'static str, long: &'static str, help: &'static str) -> Arg<'static> {
+    Arg::new(name)
+        .long(long)
+        .takes_value(true)
+        .value_name("sigma")
+        .required(false)
+        .default_value("0")
+        .help(help)
+}
+
+pub fn command() -> Command<'static> {
+    Command::new("tolerance")
+        .about("Monte-Carlo of element failures and position, gain and beam errors")
+        .arg(args::array_cfg().short('a'))
+        .arg(args::ant_beam().short('e'))
+        .arg(args::nside())
+        .arg(
+            args::sky()
+                .required(false)
+                .requires("lat")
+                .help("sky, enables the antenna temperature statistics"),
+        )
+        .arg(args::lat().required(false))
+        .arg(args::freq())
+        .arg(
+            Arg::new("niter")
+                .short('N')
+                .long("niter")
+                .takes_value(true)
+                .value_name("num of realisations")
+                .required(false)
+                .default_value("100")
+                .help("num of realisations"),
+        )
+        .arg(args::seed())
+        .arg(tolerance_arg(
+            "dropout",
+            "dropout",
+            "probability of an element being dead",
+        ))
+        .arg(tolerance_arg(
+            "pos_sigma",
+            "pos-sigma",
+            "rms position error along each axis, in metre",
+        ))
+        .arg(tolerance_arg(
+            "amp_sigma",
+            "amp-sigma",
+            "rms fractional amplitude error",
+        ))
+        .arg(tolerance_arg(
+            "phase_sigma_deg",
+            "phase-sigma",
+            "rms phase error, in deg",
+        ))
+        .arg(tolerance_arg(
+            "beam_sigma",
+            "beam-sigma",
+            "rms gain slope of the element beams",
+        ))
+        .arg(
+            Arg::new("outfile")
+                .short('o')
+                .long("out")
+                .takes_value(true)
+                .value_name("csv file")
+                .required(false)
+                .help("metrics of every realisation, not written if not given"),
+        )
+        .group(args::ant_beam_or_nside())
+}
+
+fn print_stats(name: &str, s: &Stats) {
+    println!(
+        "{} {} {} {} {} {}",
+        name, s.mean, s.p05, s.p50, s.p95, s.worst
+    );
+    if s.dropped > 0 {
+        println!("# {}: {} realisations with NaN left out", name, s.dropped);
+    }
+}
+
+pub fn run(matches: &ArgMatches, prov: &mut Provenance) -> CliResult<()> {
+    let (ant_beam, nside) = load_ant_beam(prov, matches)?;
+    let sky_and_lat = if let Some(fname) = matches.value_of("sky") {
+        Some((
+            read_hp_map_npix(prov, fname, ant_beam.len())?,
+            value::<f64>(matches, "lat")?,
+        ))
+    } else {
+        None
+    };
+
+    let array_cfg = load_array_cfg(prov, required(matches, "array_cfg")?)?;
+    let (x_list, y_list, z_list) = array_cfg.xyz_lists();
+    let freq_hz = freq_hz(matches, "freq")?;
+    let w_list = array_cfg.wgt_list();
+    let phi_list = array_cfg.phase_list(freq_hz);
+
+    let tolerance = Tolerance {
+        dropout_prob: value(matches, "dropout")?,
+        pos_sigma: value(matches, "pos_sigma")?,
+        amp_sigma: value(matches, "amp_sigma")?,
+        phase_sigma: value::<f64>(matches, "phase_sigma_deg")?.to_radians(),
+        beam_sigma: value(matches, "beam_sigma")?,
+    };
+    let niter = value::<usize>(matches, "niter")?;
+    if niter == 0 {
+        return Err(CliError::InvalidValue {
+            arg: "niter".to_string(),
+            value: niter.to_string(),
+            reason: "at least one realisation is needed".to_string(),
+        });
+    }
+    let (mut rng, _seed) = seeded_rng(prov, matches)?;
+
+    let apply_ant_beam = |array_beam: Vec<f64>| -> Vec<f64> {
+        array_beam
+            .iter()
+            .zip(ant_beam.iter())
+            .map(|(&a, &b)| a * b)
+            .collect()
+    };
+    let ant_temp = |total_beam: &[f64]| {
+        sky_and_lat
+            .as_ref()
+            .map(|(sky, lat)| {
+                let (mean_beam, _, _) = average_beam_over_lst(*lat, total_beam)?;
+                calc_averaged_ant_output(&mean_beam, sky)
+            })
+            .transpose()
+    };
+
+    let nominal = apply_ant_beam(calc_array_beam(
+        nside, &x_list, &y_list, &z_list, &w_list, &phi_list, freq_hz, true,
+    )?);
+    let nominal_metrics = calc_beam_metrics(&nominal)?;
+    let nominal_temp = ant_temp(&nominal)?;
+
+    let mut pointing_err = Vec::with_capacity(niter);
+    let mut gain_loss = Vec::with_capacity(niter);
+    let mut sll = Vec::with_capacity(niter);
+    let mut solid_angle = Vec::with_capacity(niter);
+    let mut temp_err = Vec::with_capacity(niter);
+    for _ in 0..niter {
+        let array = tolerance.realise(&x_list, &y_list, &z_list, &w_list, &phi_list, &mut rng)?;
+        let beam = apply_ant_beam(calc_perturbed_array_beam(nside, &array, freq_hz, true)?);
+        let m = calc_beam_metrics(&beam)?;
+        pointing_err.push(angular_distance(&m.peak_dir, &nominal_metrics.peak_dir).to_degrees());
+        gain_loss.push(10.0 * (m.peak / nominal_metrics.peak).log10());
+        sll.push(m.sidelobe_level_db);
+        solid_angle.push(m.solid_angle);
+        if let (Some(t), Some(t0)) = (ant_temp(&beam)?, nominal_temp) {
+            temp_err.push(((t - t0) / t0).abs());
+        }
+    }
+
+    println!("# metric mean p05 p50 p95 worst");
+    print_stats("pointing_error_deg", &summarize(&pointing_err, true)?);
+    print_stats("peak_gain_dB", &summarize(&gain_loss, false)?);
+    print_stats("sidelobe_level_dB", &summarize(&sll, true)?);
+    print_stats("solid_angle_sr", &summarize(&solid_angle, true)?);
+    if let Some(t0) = nominal_temp {
+        println!("# nominal antenna temperature: {}", t0);
+        print_stats("ant_temp_abs_rel_err", &summarize(&temp_err, true)?);
+    }
+
+    if let Some(fname) = matches.value_of("outfile") {
+        let mut columns = vec![
+            ("pointing_error_deg", &pointing_err[..]),
+            ("peak_gain_dB", &gain_loss[..]),
+            ("sidelobe_level_dB", &sll[..]),
+            ("solid_angle_sr", &solid_angle[..]),
+        ];
+        if nominal_temp.is_some() {
+            columns.push(("ant_temp_abs_rel_err", &temp_err[..]));
+        }
+        write_table(prov, Some(fname), &columns)?;
+    }
+    Ok(())
+}
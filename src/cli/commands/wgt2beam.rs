@@ -0,0 +1,58 @@
+use clap::{Arg, ArgMatches, Command};
+
+use crate::{
+    cli::{
+        args, load_ant_beam, read_complex_wgt, required, wgt_freq_spacing, write_hp_map, CliResult,
+        Provenance,
+    },
+    regular_array::{complex::complex_wgt2beam, regular_wgt2beam},
+    utils::hemi_npix,
+};
+
+pub fn command() -> Command<'static> {
+    Command::new("wgt2beam")
+        .about("beam of a regular array with four-fold symmetric or complex weights")
+        .arg(args::ant_beam())
+        .arg(args::nside())
+        .arg(
+            Arg::new("wgt")
+                .short('w')
+                .long("wgt")
+                .takes_value(true)
+                .value_name("wgt file")
+                .required(true)
+                .help("wgt"),
+        )
+        .arg(args::spacing().required(false).help(
+            "element spacing, in metre unless given in lambda (e.g. 0.5lambda); read from the wgt file if not given",
+        ))
+        .arg(args::freq().required(false).help(
+            "freq, in MHz unless a unit (Hz, kHz, MHz, GHz) is given; read from the wgt file if not given",
+        ))
+        .arg(args::hemi())
+        .arg(args::outfile("output beam file"))
+        .group(args::ant_beam_or_nside())
+}
+
+pub fn run(matches: &ArgMatches, prov: &mut Provenance) -> CliResult<()> {
+    let (mut ant_beam, nside) = load_ant_beam(prov, matches)?;
+    if matches.is_present("hemi") {
+        ant_beam.truncate(hemi_npix(nside));
+    }
+    let (wgt, meta) = read_complex_wgt(prov, required(matches, "wgt")?)?;
+    let (freq_hz, d) = wgt_freq_spacing(matches, meta.as_ref())?;
+
+    // real weights keep the zenith normalisation of the four-fold symmetric beam
+    let total_beam = if wgt.iter().all(|w| w.im == 0.0) {
+        let wgt = wgt.mapv(|w| w.re);
+        regular_wgt2beam(wgt.view(), &ant_beam, d, freq_hz / 1e6, nside)?
+    } else {
+        complex_wgt2beam(wgt.view(), &ant_beam, d, freq_hz / 1e6, nside)?
+    };
+    write_hp_map(
+        prov,
+        required(matches, "outfile")?,
+        &total_beam,
+        matches.is_present("hemi"),
+    )
+}
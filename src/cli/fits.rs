@@ -0,0 +1,138 @@
+//! Minimal FITS header reading and writing, for the keywords that the
+//! `healpix_fits` and `fitsimg` readers do not expose.
+
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{Read, Seek, SeekFrom},
+};
+
+use crate::cli::{check_exists, io_error, CliResult};
+
+pub(super) const BLOCK: usize = 2880;
+pub(super) const CARD: usize = 80;
+
+pub(super) type Header = HashMap<String, String>;
+
+/// `len` rounded up to whole FITS blocks.
+pub(super) fn padded(len: usize) -> usize {
+    (len + BLOCK - 1) / BLOCK * BLOCK
+}
+
+fn parse_value(s: &str) -> String {
+    let s = s.trim_start();
+    if let Some(quoted) = s.strip_prefix('\'') {
+        let mut value = String::new();
+        let mut chars = quoted.chars().peekable();
+        while let Some(c) = chars.next() {
+            if c == '\'' {
+                if chars.peek() == Some(&'\'') {
+                    chars.next();
+                } else {
+                    break;
+                }
+            }
+            value.push(c);
+        }
+        value.trim_end().to_string()
+    } else {
+        s.split('/').next().unwrap_or("").trim().to_string()
+    }
+}
+
+/// Header cards of every HDU with the byte offset and size of its data.
+pub(super) fn read_hdus(path: &str) -> CliResult<Vec<(Header, u64, usize)>> {
+    check_exists(path)?;
+    let mut f = File::open(path).map_err(|e| io_error(path, e))?;
+    let file_len = f.metadata().map_err(|e| io_error(path, e))?.len();
+
+    let mut hdus = Vec::new();
+    let mut pos = 0_u64;
+    let mut block = [0_u8; BLOCK];
+    while pos + BLOCK as u64 <= file_len {
+        f.seek(SeekFrom::Start(pos))
+            .map_err(|e| io_error(path, e))?;
+        let mut header = Header::new();
+        let mut end = false;
+        while !end {
+            f.read_exact(&mut block)
+                .map_err(|_| io_error(path, "truncated fits header"))?;
+            for card in block.chunks(CARD) {
+                let card = String::from_utf8_lossy(card);
+                let key = card.get(..8).unwrap_or("").trim().to_string();
+                if key == "END" {
+                    end = true;
+                    break;
+                }
+                if card.get(8..10) == Some("= ") {
+                    header.insert(key, parse_value(card.get(10..).unwrap_or("")));
+                }
+            }
+            pos += BLOCK as u64;
+        }
+        let int = |k: &str| {
+            header
+                .get(k)
+                .and_then(|v| v.parse::<i64>().ok())
+                .unwrap_or(0)
+        };
+        let naxis = int("NAXIS");
+        let size = if naxis == 0 {
+            0
+        } else {
+            let n: i64 = (1..=naxis).map(|i| int(&format!("NAXIS{}", i))).product();
+            let gcount = if header.contains_key("GCOUNT") {
+                int("GCOUNT")
+            } else {
+                1
+            };
+            (int("BITPIX").abs() / 8 * gcount * (int("PCOUNT") + n)) as usize
+        };
+        hdus.push((header, pos, size));
+        pos += padded(size) as u64;
+    }
+    Ok(hdus)
+}
+
+pub(super) fn read_data(path: &str, offset: u64, size: usize) -> CliResult<Vec<u8>> {
+    let mut data = vec![0; size];
+    File::open(path)
+        .and_then(|mut f| {
+            f.seek(SeekFrom::Start(offset))?;
+            f.read_exact(&mut data)
+        })
+        .map_err(|e| io_error(path, e))?;
+    Ok(data)
+}
+
+pub(super) fn header_str<'a>(
+    header: &'a Header,
+    primary: &'a Header,
+    key: &str,
+) -> Option<&'a str> {
+    header
+        .get(key)
+        .or_else(|| primary.get(key))
+        .map(|s| s.as_str())
+}
+
+pub(super) fn card(key: &str, value: &str) -> String {
+    format!("{:<8}= {:>20}", key, value)
+}
+
+pub(super) fn str_card(key: &str, value: &str) -> String {
+    format!("{:<8}= '{:<8}'", key, value)
+}
+
+pub(super) fn push_header(buf: &mut Vec<u8>, cards: &[String]) {
+    let start = buf.len();
+    for c in cards
+        .iter()
+        .map(|s| s.as_str())
+        .chain(std::iter::once("END"))
+    {
+        buf.extend(format!("{:<80}", c).bytes());
+    }
+    let len = buf.len() - start;
+    buf.resize(start + padded(len), b' ');
+}
@@ -0,0 +1,446 @@
+//! All products of a run in one HDF5 file, behind the `hdf5` feature.
+//!
+//! ```text
+//! /meta                  attrs describing the run, and freqs_hz
+//! /beams/NAME            (nfreq, npix) RING maps, attrs freqs_hz, nside, ordering
+//! /wgts/NAME             (nfreq, h, w) wgt cube laid out as in `wgtfile`,
+//!                        attrs freqs_hz, spacing_m (one per freq)
+//! /outputs/NAME/COLUMN   1D columns of a table, e.g. the sky averaged output
+//! /images/NAME           2D image, e.g. a map regridded to theta/phi
+//! ```
+//!
+//! Other tools read one map or wgt as `FILE.h5:/beams/NAME:INDEX`, the index
+//! along the freq axis being optional for a single freq. Every tool writes
+//! its products into such a file when the output is given as
+//! `FILE.h5:/GROUP/NAME`, the file being created if needed and other
+//! products in it kept. Complex wgts are stored as a re and an im plane, with
+//! a `complex` attr.
+
+use hdf5::{types::VarLenUnicode, Dataset, File, Group, Location};
+
+use ndarray::{stack, Array2, Array3, ArrayView2, Axis, Ix3};
+
+use num::complex::Complex;
+
+use crate::{
+    cli::{io_error, provenance::Provenance, CliError, CliResult},
+    utils::checked_npix2nside,
+};
+
+pub struct H5Products {
+    path: String,
+    file: File,
+}
+
+/// `FILE.h5:DATASET[:INDEX]`, as accepted in place of a FITS file name.
+pub struct H5Path<'a> {
+    pub file: &'a str,
+    pub dataset: &'a str,
+    pub index: Option<usize>,
+}
+
+impl<'a> H5Path<'a> {
+    pub fn parse(path: &'a str) -> Option<Self> {
+        let lower = path.to_lowercase();
+        let split = [".h5:", ".hdf5:"]
+            .iter()
+            .filter_map(|ext| lower.find(ext).map(|i| i + ext.len()))
+            .min()?;
+        let (file, rest) = (&path[..split - 1], &path[split..]);
+        match rest.rsplit_once(':') {
+            Some((dataset, index)) if index.parse::<usize>().is_ok() => Some(H5Path {
+                file,
+                dataset,
+                index: index.parse().ok(),
+            }),
+            _ => Some(H5Path {
+                file,
+                dataset: rest,
+                index: None,
+            }),
+        }
+    }
+
+    fn plane(&self, n: usize) -> CliResult<usize> {
+        match self.index {
+            Some(i) if i < n => Ok(i),
+            None if n == 1 => Ok(0),
+            Some(i) => Err(CliError::Invalid(format!(
+                "{}:{}: index {} out of {} freqs",
+                self.file, self.dataset, i, n
+            ))),
+            None => Err(CliError::Invalid(format!(
+                "{}:{}: an index is needed to pick one of {} freqs",
+                self.file, self.dataset, n
+            ))),
+        }
+    }
+
+    /// One RING map.
+    pub fn read_map(&self) -> CliResult<Vec<f64>> {
+        let maps = H5Products::open(self.file)?.read_beams(self.dataset)?;
+        let i = self.plane(maps.nrows())?;
+        Ok(maps.row(i).to_vec())
+    }
+
+    /// One wgt with its spacing and freq.
+    pub fn read_wgt(&self) -> CliResult<(Array2<f64>, f64, f64)> {
+        let h5 = H5Products::open(self.file)?;
+        if h5.is_complex(self.dataset)? {
+            return Err(io_error(
+                self.file,
+                format!(
+                    "{}: complex wgt, only tools accepting complex weights can read it",
+                    self.dataset
+                ),
+            ));
+        }
+        let (wgts, spacings_m, freqs_hz) = h5.read_wgts(self.dataset)?;
+        let i = self.plane(wgts.shape()[0])?;
+        Ok((
+            wgts.index_axis(Axis(0), i).to_owned(),
+            spacings_m[i],
+            freqs_hz[i],
+        ))
+    }
+
+    /// One complex wgt with its spacing and freq, or a real one with zero
+    /// phase.
+    pub fn read_complex_wgt(&self) -> CliResult<(Array2<Complex<f64>>, f64, f64)> {
+        let h5 = H5Products::open(self.file)?;
+        if !h5.is_complex(self.dataset)? {
+            let (wgt, spacing_m, freq_hz) = self.read_wgt()?;
+            return Ok((wgt.mapv(|x| Complex::new(x, 0.0)), spacing_m, freq_hz));
+        }
+        let (parts, spacings_m, freqs_hz) = h5.read_wgts(self.dataset)?;
+        if parts.shape()[0] != 2 {
+            return Err(h5.err(self.dataset, "complex wgt must have 2 planes"));
+        }
+        let (re, im) = (parts.index_axis(Axis(0), 0), parts.index_axis(Axis(0), 1));
+        let wgt = Array2::from_shape_fn(re.raw_dim(), |ij| Complex::new(re[ij], im[ij]));
+        Ok((wgt, spacings_m[0], freqs_hz[0]))
+    }
+
+    /// The product file opened for writing and the name of the product in
+    /// `group`, the dataset being given as `NAME` or `/GROUP/NAME`.
+    fn product(&self, group: &str) -> CliResult<(H5Products, &'a str)> {
+        let name = self.dataset.trim_start_matches('/');
+        let name = name
+            .strip_prefix(group)
+            .and_then(|n| n.strip_prefix('/'))
+            .unwrap_or(name);
+        if name.is_empty() || name.contains('/') || self.index.is_some() {
+            return Err(CliError::Invalid(format!(
+                "{}:{}: this product is written as FILE.h5:/{}/NAME",
+                self.file, self.dataset, group
+            )));
+        }
+        Ok((H5Products::append(self.file)?, name))
+    }
+
+    /// Writes maps as `/beams/NAME`, with the freq of every map if known.
+    pub fn write_maps(
+        &self,
+        prov: &Provenance,
+        maps: &[&[f64]],
+        freqs_hz: Option<&[f64]>,
+    ) -> CliResult<()> {
+        let (h5, name) = self.product("beams")?;
+        h5.write_beams(name, freqs_hz, maps)?;
+        let ds = h5.dataset(&format!("beams/{}", name))?;
+        h5.write_provenance(prov, &ds)
+    }
+
+    /// Writes one wgt per freq as `/wgts/NAME`.
+    pub fn write_wgts(
+        &self,
+        prov: &Provenance,
+        wgts: &[Array2<f64>],
+        spacing_m: f64,
+        freqs_hz: &[f64],
+    ) -> CliResult<()> {
+        let (h5, name) = self.product("wgts")?;
+        h5.write_wgts(name, freqs_hz, &vec![spacing_m; freqs_hz.len()], wgts)?;
+        let ds = h5.dataset(&format!("wgts/{}", name))?;
+        h5.write_provenance(prov, &ds)
+    }
+
+    /// Writes a complex wgt as `/wgts/NAME`.
+    pub fn write_complex_wgt(
+        &self,
+        prov: &Provenance,
+        wgt: &Array2<Complex<f64>>,
+        spacing_m: f64,
+        freq_hz: f64,
+    ) -> CliResult<()> {
+        let (h5, name) = self.product("wgts")?;
+        h5.write_wgts(
+            name,
+            &[freq_hz; 2],
+            &[spacing_m; 2],
+            &[wgt.mapv(|w| w.re), wgt.mapv(|w| w.im)],
+        )?;
+        let ds = h5.dataset(&format!("wgts/{}", name))?;
+        h5.write_str_attr(&ds, "complex", "re,im")?;
+        h5.write_provenance(prov, &ds)
+    }
+
+    /// Writes an image as `/images/NAME`.
+    pub fn write_image(&self, prov: &Provenance, image: &Array2<f64>) -> CliResult<()> {
+        let (h5, name) = self.product("images")?;
+        let ds = h5
+            .group_for("images", name)?
+            .new_dataset_builder()
+            .with_data(image)
+            .create(name)
+            .map_err(|e| h5.err(name, e))?;
+        h5.write_provenance(prov, &ds)
+    }
+
+    /// Writes columns of equal length as `/outputs/NAME`.
+    pub fn write_table(&self, prov: &Provenance, columns: &[(&str, &[f64])]) -> CliResult<()> {
+        let (h5, name) = self.product("outputs")?;
+        h5.write_table(name, columns)?;
+        let table = h5
+            .file
+            .group(&format!("outputs/{}", name))
+            .map_err(|e| h5.err(name, e))?;
+        h5.write_provenance(prov, &table)
+    }
+}
+
+impl H5Products {
+    pub fn create(path: &str) -> CliResult<Self> {
+        Ok(H5Products {
+            path: path.to_string(),
+            file: File::create(path).map_err(|e| io_error(path, e))?,
+        })
+    }
+
+    pub fn open(path: &str) -> CliResult<Self> {
+        Ok(H5Products {
+            path: path.to_string(),
+            file: File::open(path).map_err(|e| io_error(path, e))?,
+        })
+    }
+
+    /// Opens a product file for writing, creating it if needed.
+    pub fn append(path: &str) -> CliResult<Self> {
+        Ok(H5Products {
+            path: path.to_string(),
+            file: File::append(path).map_err(|e| io_error(path, e))?,
+        })
+    }
+
+    fn err<E: std::fmt::Debug>(&self, what: &str, e: E) -> CliError {
+        io_error(&self.path, format!("{}: {:?}", what, e))
+    }
+
+    fn group(&self, name: &str) -> CliResult<Group> {
+        if self.file.link_exists(name) {
+            self.file.group(name)
+        } else {
+            self.file.create_group(name)
+        }
+        .map_err(|e| self.err(name, e))
+    }
+
+    fn dataset(&self, name: &str) -> CliResult<Dataset> {
+        self.file.dataset(name).map_err(|e| self.err(name, e))
+    }
+
+    /// `group`, with any earlier member `name` removed so that it can be
+    /// written again.
+    fn group_for(&self, group: &str, name: &str) -> CliResult<Group> {
+        let g = self.group(group)?;
+        if g.link_exists(name) {
+            g.unlink(name).map_err(|e| self.err(name, e))?;
+        }
+        Ok(g)
+    }
+
+    /// Provenance of the run as string attrs of `obj`.
+    fn write_provenance(&self, prov: &Provenance, obj: &Location) -> CliResult<()> {
+        for (key, value) in prov.attrs() {
+            self.write_str_attr(obj, &key, &value)?;
+        }
+        Ok(())
+    }
+
+    fn is_complex(&self, name: &str) -> CliResult<bool> {
+        let path = if name.contains('/') {
+            name.to_string()
+        } else {
+            format!("wgts/{}", name)
+        };
+        Ok(self
+            .dataset(&path)?
+            .attr_names()
+            .map_err(|e| self.err(&path, e))?
+            .iter()
+            .any(|a| a == "complex"))
+    }
+
+    fn write_str_attr(&self, obj: &Location, key: &str, value: &str) -> CliResult<()> {
+        let value: VarLenUnicode = value.parse().map_err(|e| self.err(key, e))?;
+        obj.new_attr::<VarLenUnicode>()
+            .shape(())
+            .create(key)
+            .and_then(|a| a.write_scalar(&value))
+            .map_err(|e| self.err(key, e))
+    }
+
+    fn write_f64_attr(&self, obj: &Location, key: &str, values: &[f64]) -> CliResult<()> {
+        obj.new_attr::<f64>()
+            .shape(values.len())
+            .create(key)
+            .and_then(|a| a.write_raw(values))
+            .map_err(|e| self.err(key, e))
+    }
+
+    fn read_f64_attr(&self, obj: &Dataset, key: &str) -> CliResult<Vec<f64>> {
+        obj.attr(key)
+            .and_then(|a| a.read_raw::<f64>())
+            .map_err(|e| self.err(key, e))
+    }
+
+    /// Describes the run: string attrs such as the site or the array config,
+    /// and the freqs.
+    pub fn write_meta(&self, attrs: &[(&str, &str)], freqs_hz: &[f64]) -> CliResult<()> {
+        let meta = self.group("meta")?;
+        for (key, value) in attrs {
+            self.write_str_attr(&meta, key, value)?;
+        }
+        meta.new_dataset_builder()
+            .with_data(freqs_hz)
+            .create("freqs_hz")
+            .map_err(|e| self.err("freqs_hz", e))?;
+        Ok(())
+    }
+
+    pub fn freqs_hz(&self) -> CliResult<Vec<f64>> {
+        self.dataset("meta/freqs_hz")?
+            .read_raw::<f64>()
+            .map_err(|e| self.err("meta/freqs_hz", e))
+    }
+
+    /// RING maps of the same nside, one per freq if `freqs_hz` is given.
+    pub fn write_beams(
+        &self,
+        name: &str,
+        freqs_hz: Option<&[f64]>,
+        maps: &[&[f64]],
+    ) -> CliResult<()> {
+        let npix = maps.first().map_or(0, |m| m.len());
+        if freqs_hz.is_some_and(|f| f.len() != maps.len()) || maps.iter().any(|m| m.len() != npix) {
+            return Err(self.err(name, "one map of the same size is needed per freq"));
+        }
+        let nside = checked_npix2nside(npix)?;
+        let data = Array2::from_shape_vec((maps.len(), npix), maps.concat())
+            .map_err(|e| self.err(name, e))?;
+        let ds = self
+            .group_for("beams", name)?
+            .new_dataset_builder()
+            .with_data(&data)
+            .create(name)
+            .map_err(|e| self.err(name, e))?;
+        if let Some(freqs_hz) = freqs_hz {
+            self.write_f64_attr(&ds, "freqs_hz", freqs_hz)?;
+        }
+        ds.new_attr::<u64>()
+            .shape(())
+            .create("nside")
+            .and_then(|a| a.write_scalar(&(nside as u64)))
+            .map_err(|e| self.err("nside", e))?;
+        self.write_str_attr(&ds, "ordering", "RING")
+    }
+
+    /// Maps of `/beams/NAME`, one row per freq.
+    pub fn read_beams(&self, name: &str) -> CliResult<Array2<f64>> {
+        let path = if name.contains('/') {
+            name.to_string()
+        } else {
+            format!("beams/{}", name)
+        };
+        let data = self
+            .dataset(&path)?
+            .read_2d::<f64>()
+            .map_err(|e| self.err(&path, e))?;
+        checked_npix2nside(data.ncols())?;
+        Ok(data)
+    }
+
+    /// One wgt per freq, each with its spacing.
+    pub fn write_wgts(
+        &self,
+        name: &str,
+        freqs_hz: &[f64],
+        spacings_m: &[f64],
+        wgts: &[Array2<f64>],
+    ) -> CliResult<()> {
+        if wgts.len() != freqs_hz.len() || spacings_m.len() != freqs_hz.len() {
+            return Err(self.err(name, "one wgt and spacing is needed per freq"));
+        }
+        let views: Vec<ArrayView2<f64>> = wgts.iter().map(|w| w.view()).collect();
+        let cube = stack(Axis(0), &views).map_err(|e| self.err(name, e))?;
+        let ds = self
+            .group_for("wgts", name)?
+            .new_dataset_builder()
+            .with_data(&cube)
+            .create(name)
+            .map_err(|e| self.err(name, e))?;
+        self.write_f64_attr(&ds, "freqs_hz", freqs_hz)?;
+        self.write_f64_attr(&ds, "spacing_m", spacings_m)
+    }
+
+    /// Wgt cube of `/wgts/NAME` with the spacing and freq of every plane.
+    pub fn read_wgts(&self, name: &str) -> CliResult<(Array3<f64>, Vec<f64>, Vec<f64>)> {
+        let path = if name.contains('/') {
+            name.to_string()
+        } else {
+            format!("wgts/{}", name)
+        };
+        let ds = self.dataset(&path)?;
+        let cube = ds.read::<f64, Ix3>().map_err(|e| self.err(&path, e))?;
+        let spacings_m = self.read_f64_attr(&ds, "spacing_m")?;
+        let freqs_hz = self.read_f64_attr(&ds, "freqs_hz")?;
+        if spacings_m.len() != cube.shape()[0] || freqs_hz.len() != cube.shape()[0] {
+            return Err(self.err(
+                &path,
+                "spacing_m and freqs_hz must have one value per plane",
+            ));
+        }
+        Ok((cube, spacings_m, freqs_hz))
+    }
+
+    /// Columns of equal length, e.g. freq and antenna temperature.
+    pub fn write_table(&self, name: &str, columns: &[(&str, &[f64])]) -> CliResult<()> {
+        let table = self.group_for("outputs", name)?;
+        let table = table.create_group(name).map_err(|e| self.err(name, e))?;
+        for (col, values) in columns {
+            table
+                .new_dataset_builder()
+                .with_data(*values)
+                .create(*col)
+                .map_err(|e| self.err(col, e))?;
+        }
+        Ok(())
+    }
+
+    pub fn read_table(&self, name: &str) -> CliResult<Vec<(String, Vec<f64>)>> {
+        let path = format!("outputs/{}", name);
+        let table = self.file.group(&path).map_err(|e| self.err(&path, e))?;
+        table
+            .member_names()
+            .map_err(|e| self.err(&path, e))?
+            .into_iter()
+            .map(|col| {
+                let values = table
+                    .dataset(&col)
+                    .and_then(|d| d.read_raw::<f64>())
+                    .map_err(|e| self.err(&col, e))?;
+                Ok((col, values))
+            })
+            .collect()
+    }
+}
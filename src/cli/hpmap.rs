@@ -0,0 +1,318 @@
+//! HEALPix FITS I/O aware of the `ORDERING` and `INDXSCHM` keywords.
+//!
+//! `healpix_fits` reads the pixel column as stored, so the header and, for
+//! cut-sky maps, the explicit-index binary table are parsed here.
+//!
+//! Maps are converted to RING order and full sky on reading. Beams computed
+//! over the upper hemisphere only (see `utils::hemi_npix`) are written as
+//! they are, as cut-sky maps.
+
+use std::{fs::File, io::Write};
+
+use healpix_fits::{read_map, write_map};
+
+use scorus::healpix::utils::nside2npix;
+
+use crate::{
+    cli::{
+        check_no_h5,
+        fits::{card, header_str, padded, push_header, read_data, read_hdus, str_card, Header},
+        io_error,
+        provenance::Provenance,
+        CliError, CliResult,
+    },
+    grid::HealpixNested,
+    utils::{check_nside, checked_hemi_npix2nside, checked_npix2nside, hemi_npix},
+};
+
+/// Columns `names` of a binary table, converted to f64; only scalar numeric
+/// columns (repeat count 1) are supported.
+fn read_columns(
+    path: &str,
+    header: &Header,
+    data: &[u8],
+    names: &[&str],
+) -> CliResult<Vec<Vec<f64>>> {
+    let nfields = header
+        .get("TFIELDS")
+        .and_then(|v| v.parse::<usize>().ok())
+        .unwrap_or(0);
+    let row_len = header
+        .get("NAXIS1")
+        .and_then(|v| v.parse::<usize>().ok())
+        .unwrap_or(0);
+    let nrows = header
+        .get("NAXIS2")
+        .and_then(|v| v.parse::<usize>().ok())
+        .unwrap_or(0);
+
+    // (name, offset in row, type code)
+    let mut layout = Vec::new();
+    let mut offset = 0;
+    for i in 1..=nfields {
+        let form = header
+            .get(&format!("TFORM{}", i))
+            .ok_or_else(|| io_error(path, format!("no TFORM{}", i)))?;
+        let split = form
+            .find(|c: char| !c.is_ascii_digit())
+            .ok_or_else(|| io_error(path, format!("bad TFORM{} '{}'", i, form)))?;
+        let repeat = form[..split].parse::<usize>().unwrap_or(1);
+        let code = form[split..].chars().next().unwrap_or(' ');
+        let width = match code {
+            'L' | 'B' | 'A' => 1,
+            'I' => 2,
+            'J' | 'E' => 4,
+            'K' | 'D' => 8,
+            'X' => 0,
+            _ => return Err(io_error(path, format!("unsupported TFORM{} '{}'", i, form))),
+        };
+        let name = header
+            .get(&format!("TTYPE{}", i))
+            .cloned()
+            .unwrap_or_default();
+        layout.push((name, offset, code, repeat));
+        offset += if code == 'X' {
+            (repeat + 7) / 8
+        } else {
+            width * repeat
+        };
+    }
+    if data.len() < row_len * nrows {
+        return Err(io_error(path, "truncated binary table"));
+    }
+
+    names
+        .iter()
+        .map(|&name| {
+            let &(_, offset, code, repeat) = layout
+                .iter()
+                .find(|(n, ..)| n.eq_ignore_ascii_case(name))
+                .ok_or_else(|| io_error(path, format!("no {} column", name)))?;
+            if repeat != 1 {
+                return Err(io_error(path, format!("column {} is not scalar", name)));
+            }
+            Ok((0..nrows)
+                .map(|r| {
+                    let b = &data[r * row_len + offset..];
+                    match code {
+                        'B' => b[0] as f64,
+                        'I' => i16::from_be_bytes([b[0], b[1]]) as f64,
+                        'J' => i32::from_be_bytes([b[0], b[1], b[2], b[3]]) as f64,
+                        'K' => i64::from_be_bytes(b[..8].try_into().unwrap()) as f64,
+                        'E' => f32::from_be_bytes([b[0], b[1], b[2], b[3]]) as f64,
+                        'D' => f64::from_be_bytes(b[..8].try_into().unwrap()),
+                        _ => f64::NAN,
+                    }
+                })
+                .collect())
+        })
+        .collect()
+}
+
+/// Reads a HEALPix map in RING order and full sky, whatever its ordering
+/// and index scheme on disk. Pixels missing from a cut-sky map are 0, which
+/// for a beam stored as the upper hemisphere is the ground cut. With the
+/// `hdf5` feature, `FILE.h5:/beams/NAME:INDEX` reads one map of a product file.
+pub fn read_hp_map(prov: &mut Provenance, path: &str) -> CliResult<Vec<f64>> {
+    #[cfg(feature = "hdf5")]
+    if let Some(h5path) = crate::cli::h5::H5Path::parse(path) {
+        prov.record_input(h5path.file);
+        return h5path.read_map();
+    }
+    let hdus = read_hdus(path)?;
+    prov.record_input(path);
+    let primary = hdus
+        .first()
+        .map(|h| h.0.clone())
+        .ok_or_else(|| io_error(path, "empty fits file"))?;
+    let (header, offset, size) = hdus
+        .get(1)
+        .ok_or_else(|| io_error(path, "no binary table extension"))?;
+
+    let nested = match header_str(header, &primary, "ORDERING") {
+        Some(o) if o.eq_ignore_ascii_case("NESTED") => true,
+        Some(o) if o.eq_ignore_ascii_case("RING") => false,
+        None => false,
+        Some(o) => return Err(io_error(path, format!("unknown ORDERING '{}'", o))),
+    };
+    let explicit = header_str(header, &primary, "INDXSCHM")
+        .map(|s| s.eq_ignore_ascii_case("EXPLICIT"))
+        .unwrap_or(false)
+        || header_str(header, &primary, "OBJECT")
+            .map(|s| s.eq_ignore_ascii_case("PARTIAL"))
+            .unwrap_or(false);
+
+    let map = if explicit {
+        let nside = header_str(header, &primary, "NSIDE")
+            .and_then(|s| s.parse::<usize>().ok())
+            .ok_or_else(|| io_error(path, "cut-sky map without NSIDE"))?;
+        check_nside(nside)?;
+        let npix = nside2npix(nside);
+        let value_col = header
+            .get("TTYPE2")
+            .cloned()
+            .ok_or_else(|| io_error(path, "cut-sky map without a value column"))?;
+        let mut cols = read_columns(
+            path,
+            header,
+            &read_data(path, *offset, *size)?,
+            &["PIXEL", &value_col],
+        )?;
+        let (values, pixels) = (cols.pop().unwrap(), cols.pop().unwrap());
+        let mut map = vec![0.0; npix];
+        for (&p, &v) in pixels.iter().zip(values.iter()) {
+            let p = p as usize;
+            if p >= npix {
+                return Err(io_error(path, format!("pixel {} out of range", p)));
+            }
+            map[p] = v;
+        }
+        map
+    } else {
+        read_map::<f64>(path, &["TEMPERATURE"], 1)
+            .pop()
+            .ok_or_else(|| io_error(path, "no TEMPERATURE column"))?
+    };
+
+    if nested {
+        Ok(HealpixNested::from_npix(map.len())?.map_nest2ring(&map)?)
+    } else {
+        Ok(map)
+    }
+}
+
+/// Writes the upper hemisphere of a RING map (the first `npix / 2` pixels,
+/// as left by the ground cut) as an explicit-index cut-sky map.
+pub fn write_hp_map_hemi(
+    prov: &Provenance,
+    path: &str,
+    hemi: &[f64],
+    nside: usize,
+) -> CliResult<()> {
+    check_no_h5(path)?;
+    check_nside(nside)?;
+    if hemi.len() != hemi_npix(nside) {
+        return Err(CliError::Invalid(format!(
+            "{}: expected {} pixels for the hemisphere of nside {}, found {}",
+            path,
+            hemi_npix(nside),
+            nside,
+            hemi.len()
+        )));
+    }
+    let mut buf = Vec::new();
+    let mut primary = vec![
+        card("SIMPLE", "T"),
+        card("BITPIX", "8"),
+        card("NAXIS", "0"),
+        card("EXTEND", "T"),
+    ];
+    primary.extend(prov.cards());
+    push_header(&mut buf, &primary);
+    push_header(
+        &mut buf,
+        &[
+            str_card("XTENSION", "BINTABLE"),
+            card("BITPIX", "8"),
+            card("NAXIS", "2"),
+            card("NAXIS1", "16"),
+            card("NAXIS2", &hemi.len().to_string()),
+            card("PCOUNT", "0"),
+            card("GCOUNT", "1"),
+            card("TFIELDS", "2"),
+            str_card("TTYPE1", "PIXEL"),
+            str_card("TFORM1", "1K"),
+            str_card("TTYPE2", "TEMPERATURE"),
+            str_card("TFORM2", "1D"),
+            str_card("PIXTYPE", "HEALPIX"),
+            str_card("ORDERING", "RING"),
+            str_card("INDXSCHM", "EXPLICIT"),
+            str_card("OBJECT", "PARTIAL"),
+            card("NSIDE", &nside.to_string()),
+            card("FIRSTPIX", "0"),
+            card("LASTPIX", &(nside2npix(nside) - 1).to_string()),
+        ],
+    );
+    let start = buf.len();
+    for (i, &x) in hemi.iter().enumerate() {
+        buf.extend_from_slice(&(i as i64).to_be_bytes());
+        buf.extend_from_slice(&x.to_be_bytes());
+    }
+    let len = buf.len() - start;
+    buf.resize(start + padded(len), 0);
+
+    File::create(path)
+        .and_then(|mut f| f.write_all(&buf))
+        .map_err(|e| io_error(path, e))
+}
+
+/// Writes a full-sky RING map, or only its upper hemisphere if `hemi`; a map
+/// already computed over the upper hemisphere is always written as such. With
+/// the `hdf5` feature, `FILE.h5:/beams/NAME` writes the map, full sky, into a
+/// product file.
+pub fn write_hp_map(prov: &Provenance, path: &str, map: &[f64], hemi: bool) -> CliResult<()> {
+    #[cfg(feature = "hdf5")]
+    if let Some(h5path) = crate::cli::h5::H5Path::parse(path) {
+        return match checked_hemi_npix2nside(map.len()) {
+            Ok(_) => h5path.write_maps(prov, &[&crate::utils::hemi2full(map)?], None),
+            Err(_) => h5path.write_maps(prov, &[map], None),
+        };
+    }
+    if let Ok(nside) = checked_hemi_npix2nside(map.len()) {
+        write_hp_map_hemi(prov, path, map, nside)
+    } else if hemi {
+        let nside = checked_npix2nside(map.len())?;
+        write_hp_map_hemi(prov, path, &map[..hemi_npix(nside)], nside)
+    } else {
+        write_hp_columns(prov, path, &[map], false)
+    }
+}
+
+/// Writes maps of the same nside as the columns of one file, with the
+/// provenance of the run in the primary header. With the `hdf5` feature,
+/// `FILE.h5:/beams/NAME` writes them, in RING order, into a product file.
+pub fn write_hp_columns(
+    prov: &Provenance,
+    path: &str,
+    columns: &[&[f64]],
+    nested: bool,
+) -> CliResult<()> {
+    #[cfg(feature = "hdf5")]
+    if let Some(h5path) = crate::cli::h5::H5Path::parse(path) {
+        if !nested {
+            return h5path.write_maps(prov, columns, None);
+        }
+        let ring = columns
+            .iter()
+            .map(|m| HealpixNested::from_npix(m.len())?.map_nest2ring(m))
+            .collect::<Result<Vec<_>, _>>()?;
+        let ring: Vec<&[f64]> = ring.iter().map(|m| &m[..]).collect();
+        return h5path.write_maps(prov, &ring, None);
+    }
+    check_no_h5(path)?;
+    write_map(path, columns, nested, true);
+    prov.stamp_fits(path)
+}
+
+/// `write_hp_columns` for one RING map per freq, the freqs being kept in an
+/// HDF5 product file.
+pub fn write_hp_cube(
+    prov: &Provenance,
+    path: &str,
+    maps: &[&[f64]],
+    freqs_hz: &[f64],
+) -> CliResult<()> {
+    #[cfg(feature = "hdf5")]
+    if let Some(h5path) = crate::cli::h5::H5Path::parse(path) {
+        return h5path.write_maps(prov, maps, Some(freqs_hz));
+    }
+    if maps.len() != freqs_hz.len() {
+        return Err(CliError::Invalid(format!(
+            "{}: {} maps for {} freqs",
+            path,
+            maps.len(),
+            freqs_hz.len()
+        )));
+    }
+    write_hp_columns(prov, path, maps, false)
+}
@@ -0,0 +1,614 @@
+pub mod args;
+pub mod commands;
+mod fits;
+#[cfg(feature = "hdf5")]
+pub mod h5;
+pub mod hpmap;
+pub mod progress;
+pub mod provenance;
+pub mod wgtfile;
+
+use std::{
+    env::args_os,
+    fmt::{self, Display},
+    fs::{read_to_string, File},
+    io::{stdout, BufWriter, Write},
+    path::Path,
+    process::exit,
+    str::FromStr,
+};
+
+use clap::{ArgMatches, Command};
+
+use ndarray::{Array2, Ix2};
+
+use rand::{rngs::StdRng, thread_rng, Rng, SeedableRng};
+
+use serde::de::DeserializeOwned;
+
+use serde_yaml::from_reader;
+
+use fitsimg::{read_img, write_img};
+
+use scorus::healpix::utils::nside2npix;
+
+pub use hpmap::{read_hp_map, write_hp_columns, write_hp_cube, write_hp_map, write_hp_map_hemi};
+pub use provenance::Provenance;
+pub use wgtfile::{
+    read_complex_wgt, read_wgt_meta, wgt_freq_spacing, write_complex_wgt, write_wgt_cube,
+    write_wgt_meta, WgtMeta,
+};
+
+use crate::{
+    array_cfg::ArrayCfg, constants::LIGHT_SPEED, error::DbfError, quantize::check_bits,
+    regular_array::solver::Solver, utils::checked_npix2nside,
+};
+
+#[derive(Debug)]
+pub enum CliError {
+    InvalidValue {
+        arg: String,
+        value: String,
+        reason: String,
+    },
+    MissingArg(String),
+    Io {
+        path: String,
+        reason: String,
+    },
+    Invalid(String),
+    Dbf(DbfError),
+}
+
+impl Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CliError::InvalidValue { arg, value, reason } => {
+                write!(f, "invalid value '{}' for '{}': {}", value, arg, reason)
+            }
+            CliError::MissingArg(arg) => write!(f, "missing argument '{}'", arg),
+            CliError::Io { path, reason } => write!(f, "{}: {}", path, reason),
+            CliError::Invalid(msg) => write!(f, "{}", msg),
+            CliError::Dbf(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for CliError {}
+
+impl From<DbfError> for CliError {
+    fn from(e: DbfError) -> Self {
+        CliError::Dbf(e)
+    }
+}
+
+pub type CliResult<T> = Result<T, CliError>;
+
+pub fn io_error<E: fmt::Debug>(path: &str, e: E) -> CliError {
+    CliError::Io {
+        path: path.to_string(),
+        reason: format!("{:?}", e),
+    }
+}
+
+pub fn required<'a>(matches: &'a ArgMatches, name: &str) -> CliResult<&'a str> {
+    matches
+        .value_of(name)
+        .ok_or_else(|| CliError::MissingArg(name.to_string()))
+}
+
+pub fn value<T>(matches: &ArgMatches, name: &str) -> CliResult<T>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    opt_value(matches, name)?.ok_or_else(|| CliError::MissingArg(name.to_string()))
+}
+
+pub fn opt_value<T>(matches: &ArgMatches, name: &str) -> CliResult<Option<T>>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    matches
+        .value_of(name)
+        .map(|s| {
+            s.parse::<T>().map_err(|e| CliError::InvalidValue {
+                arg: name.to_string(),
+                value: s.to_string(),
+                reason: e.to_string(),
+            })
+        })
+        .transpose()
+}
+
+fn split_unit(s: &str) -> (&str, &str) {
+    let s = s.trim();
+    let pos = s
+        .rfind(|c: char| c.is_ascii_digit() || c == '.')
+        .map(|i| i + 1)
+        .unwrap_or(0);
+    (s[..pos].trim(), s[pos..].trim())
+}
+
+/// Frequency with an optional unit suffix (Hz, kHz, MHz or GHz); a bare number is in MHz.
+pub fn parse_freq_hz(s: &str) -> Result<f64, String> {
+    let (num, unit) = split_unit(s);
+    let x = num.parse::<f64>().map_err(|e| e.to_string())?;
+    match unit.to_lowercase().as_str() {
+        "" | "mhz" => Ok(x * 1e6),
+        "hz" => Ok(x),
+        "khz" => Ok(x * 1e3),
+        "ghz" => Ok(x * 1e9),
+        u => Err(format!("unknown freq unit '{}'", u)),
+    }
+}
+
+/// Length with an optional unit suffix (m, or lambda/λ for multiples of the
+/// wavelength at `freq_hz`); a bare number is in metre.
+pub fn parse_length_m(s: &str, freq_hz: Option<f64>) -> Result<f64, String> {
+    let (num, unit) = split_unit(s);
+    let x = num.parse::<f64>().map_err(|e| e.to_string())?;
+    match unit.to_lowercase().as_str() {
+        "" | "m" => Ok(x),
+        "lambda" | "lam" | "λ" => freq_hz
+            .map(|f| x * LIGHT_SPEED / f)
+            .ok_or_else(|| "a freq is needed to convert wavelengths to metre".to_string()),
+        u => Err(format!("unknown length unit '{}'", u)),
+    }
+}
+
+pub fn freq_hz(matches: &ArgMatches, name: &str) -> CliResult<f64> {
+    opt_freq_hz(matches, name)?.ok_or_else(|| CliError::MissingArg(name.to_string()))
+}
+
+pub fn opt_freq_hz(matches: &ArgMatches, name: &str) -> CliResult<Option<f64>> {
+    matches
+        .value_of(name)
+        .map(|s| {
+            parse_freq_hz(s).map_err(|reason| CliError::InvalidValue {
+                arg: name.to_string(),
+                value: s.to_string(),
+                reason,
+            })
+        })
+        .transpose()
+}
+
+pub fn length_m(matches: &ArgMatches, name: &str, freq_hz: Option<f64>) -> CliResult<f64> {
+    let s = required(matches, name)?;
+    parse_length_m(s, freq_hz).map_err(|reason| CliError::InvalidValue {
+        arg: name.to_string(),
+        value: s.to_string(),
+        reason,
+    })
+}
+
+/// Equally spaced freqs from the `fmin`, `fmax` and `nfreq` args, in Hz.
+pub fn freq_list_hz(matches: &ArgMatches) -> CliResult<Vec<f64>> {
+    let fmin = freq_hz(matches, "fmin")?;
+    let fmax = freq_hz(matches, "fmax")?;
+    let nfreq = value::<usize>(matches, "nfreq")?;
+    Ok((0..nfreq)
+        .map(|i| {
+            if nfreq > 1 {
+                fmin + (fmax - fmin) * i as f64 / (nfreq - 1) as f64
+            } else {
+                fmin
+            }
+        })
+        .collect())
+}
+
+pub fn check_exists(path: &str) -> CliResult<()> {
+    if Path::new(path).exists() {
+        Ok(())
+    } else {
+        Err(CliError::Io {
+            path: path.to_string(),
+            reason: "no such file".to_string(),
+        })
+    }
+}
+
+/// Reads a map and checks that it has `npix` pixels.
+pub fn read_hp_map_npix(prov: &mut Provenance, path: &str, npix: usize) -> CliResult<Vec<f64>> {
+    let data = read_hp_map(prov, path)?;
+    if data.len() != npix {
+        return Err(CliError::Invalid(format!(
+            "{}: expected {} pixels, found {}",
+            path,
+            npix,
+            data.len()
+        )));
+    }
+    Ok(data)
+}
+
+pub fn read_wgt(prov: &mut Provenance, path: &str) -> CliResult<Array2<f64>> {
+    check_exists(path)?;
+    prov.record_input(path);
+    read_img::<f64>(path.to_string(), 0)
+        .map_err(|e| io_error(path, e))?
+        .into_dimensionality::<Ix2>()
+        .map_err(|e| io_error(path, e))
+}
+
+/// Writes an image with no wgt parameters, e.g. a map on a theta/phi grid.
+pub fn write_wgt(prov: &Provenance, path: &str, wgt: Array2<f64>) -> CliResult<()> {
+    #[cfg(feature = "hdf5")]
+    if let Some(h5path) = h5::H5Path::parse(path) {
+        return h5path.write_image(prov, &wgt);
+    }
+    check_no_h5(path)?;
+    write_img(path.to_string(), &wgt.into_dyn()).map_err(|e| io_error(path, e))?;
+    prov.stamp_fits(path)
+}
+
+/// Writes columns of equal length as csv, to stdout if `path` is not given,
+/// or with the `hdf5` feature as `FILE.h5:/outputs/NAME`, where `prov` is
+/// written as its attrs.
+#[cfg_attr(not(feature = "hdf5"), allow(unused_variables))]
+pub fn write_table(
+    prov: &Provenance,
+    path: Option<&str>,
+    columns: &[(&str, &[f64])],
+) -> CliResult<()> {
+    let nrows = columns.first().map_or(0, |c| c.1.len());
+    if columns.iter().any(|c| c.1.len() != nrows) {
+        return Err(CliError::Invalid(format!(
+            "{}: columns of a table must have the same length",
+            path.unwrap_or("stdout")
+        )));
+    }
+    #[cfg(feature = "hdf5")]
+    if let Some(h5path) = path.and_then(h5::H5Path::parse) {
+        return h5path.write_table(prov, columns);
+    }
+    let (mut out, fname): (Box<dyn Write>, &str) = match path {
+        Some(fname) => {
+            check_no_h5(fname)?;
+            (
+                Box::new(BufWriter::new(
+                    File::create(fname).map_err(|e| io_error(fname, e))?,
+                )),
+                fname,
+            )
+        }
+        None => (Box::new(stdout()), "stdout"),
+    };
+    let names: Vec<&str> = columns.iter().map(|c| c.0).collect();
+    writeln!(out, "{}", names.join(",")).map_err(|e| io_error(fname, e))?;
+    for i in 0..nrows {
+        let row: Vec<String> = columns.iter().map(|c| c.1[i].to_string()).collect();
+        writeln!(out, "{}", row.join(",")).map_err(|e| io_error(fname, e))?;
+    }
+    out.flush().map_err(|e| io_error(fname, e))
+}
+
+/// Rejects an HDF5 product path, `FILE.h5:/GROUP/NAME`, when the `hdf5`
+/// feature is off, rather than writing a file of that name.
+pub(crate) fn check_no_h5(path: &str) -> CliResult<()> {
+    let lower = path.to_lowercase();
+    if cfg!(not(feature = "hdf5")) && (lower.contains(".h5:") || lower.contains(".hdf5:")) {
+        Err(CliError::Invalid(format!(
+            "{}: hdf5 output needs the hdf5 feature",
+            path
+        )))
+    } else {
+        Ok(())
+    }
+}
+
+pub fn read_yaml<T: DeserializeOwned>(prov: &mut Provenance, path: &str) -> CliResult<T> {
+    let f = File::open(path).map_err(|e| io_error(path, e))?;
+    prov.record_input(path);
+    from_reader(f).map_err(|e| io_error(path, e))
+}
+
+/// Reads a TOML file if the extension is `.toml`, YAML otherwise.
+pub fn read_cfg<T: DeserializeOwned>(prov: &mut Provenance, path: &str) -> CliResult<T> {
+    if Path::new(path).extension() == Some("toml".as_ref()) {
+        let src = read_to_string(path).map_err(|e| io_error(path, e))?;
+        prov.record_input(path);
+        toml::from_str(&src).map_err(|e| io_error(path, e))
+    } else {
+        read_yaml(prov, path)
+    }
+}
+
+pub fn load_array_cfg(prov: &mut Provenance, path: &str) -> CliResult<ArrayCfg> {
+    let cfg: ArrayCfg = read_yaml(prov, path)?;
+    if let Ok(yaml) = serde_yaml::to_string(&cfg) {
+        prov.record_array_cfg(&yaml);
+    }
+    Ok(cfg)
+}
+
+/// The element beam from the `ant_beam` arg, or an isotropic one at `nside`.
+pub fn load_ant_beam(prov: &mut Provenance, matches: &ArgMatches) -> CliResult<(Vec<f64>, usize)> {
+    if let Some(fname) = matches.value_of("ant_beam") {
+        let ant_beam = read_hp_map(prov, fname)?;
+        let nside = checked_npix2nside(ant_beam.len())?;
+        Ok((ant_beam, nside))
+    } else {
+        let nside = value::<usize>(matches, "nside")?;
+        Ok((vec![1.0; nside2npix(nside)], nside))
+    }
+}
+
+/// The element beam from the `ant_beam` arg if given, otherwise an isotropic
+/// one with `npix` pixels.
+pub fn load_ant_beam_npix(
+    prov: &mut Provenance,
+    matches: &ArgMatches,
+    npix: usize,
+) -> CliResult<Vec<f64>> {
+    if let Some(fname) = matches.value_of("ant_beam") {
+        read_hp_map_npix(prov, fname, npix)
+    } else {
+        Ok(vec![1.0; npix])
+    }
+}
+
+pub fn seeded_rng(prov: &mut Provenance, matches: &ArgMatches) -> CliResult<(StdRng, u64)> {
+    let seed = opt_value::<u64>(matches, "seed")?.unwrap_or_else(|| thread_rng().gen());
+    eprintln!("seed: {}", seed);
+    prov.record_seed(seed);
+    Ok((StdRng::seed_from_u64(seed), seed))
+}
+
+/// The solver from the `solver` arg with the hyperparameters given in the
+/// `solver_params` args.
+pub fn solver(matches: &ArgMatches) -> CliResult<Solver> {
+    let name = matches.value_of("solver").unwrap_or("pso");
+    let mut solver = Solver::from_name(name).ok_or_else(|| CliError::InvalidValue {
+        arg: "solver".to_string(),
+        value: name.to_string(),
+        reason: format!("must be one of {}", Solver::NAMES.join(", ")),
+    })?;
+    let set = |x: &mut f64, arg: &str| -> CliResult<()> {
+        if let Some(v) = opt_value(matches, arg)? {
+            *x = v;
+        }
+        Ok(())
+    };
+    match &mut solver {
+        Solver::Pso {
+            omega,
+            phi_p,
+            phi_g,
+        } => {
+            set(omega, "omega")?;
+            set(phi_p, "phi_p")?;
+            set(phi_g, "phi_g")?;
+        }
+        Solver::De { f, cr } => {
+            set(f, "de_f")?;
+            set(cr, "de_cr")?;
+        }
+        Solver::CmaEs { sigma0 } => set(sigma0, "sigma0")?,
+        Solver::Anneal { t0, cooling, step } => {
+            set(t0, "t0")?;
+            set(cooling, "cooling")?;
+            set(step, "step")?;
+        }
+    }
+    Ok(solver)
+}
+
+/// The optional `bits` arg, checked to be a usable bit depth.
+pub fn opt_bits(matches: &ArgMatches) -> CliResult<Option<u32>> {
+    let bits = opt_value::<u32>(matches, "bits")?;
+    if let Some(b) = bits {
+        check_bits(b).map_err(|e| CliError::InvalidValue {
+            arg: "bits".to_string(),
+            value: b.to_string(),
+            reason: e.to_string(),
+        })?;
+    }
+    Ok(bits)
+}
+
+pub struct Tool {
+    pub name: &'static str,
+    /// name of the standalone tool this replaces, for the tools that predate
+    /// `dbfsim`
+    pub legacy_name: Option<&'static str>,
+    pub command: fn() -> Command<'static>,
+    /// runs the tool, recording what its outputs are made from in the
+    /// `Provenance` written with them
+    pub run: fn(&ArgMatches, &mut Provenance) -> CliResult<()>,
+}
+
+pub fn tools() -> Vec<Tool> {
+    use commands::*;
+    vec![
+        Tool {
+            name: "ant-beam",
+            legacy_name: Some("calc_ant_beam"),
+            command: ant_beam::command,
+            run: ant_beam::run,
+        },
+        Tool {
+            name: "gaussian",
+            legacy_name: Some("gaussian_beam"),
+            command: gaussian::command,
+            run: gaussian::run,
+        },
+        Tool {
+            name: "beam2wgt",
+            legacy_name: Some("beam2regular_wgt"),
+            command: beam2wgt::command,
+            run: beam2wgt::run,
+        },
+        Tool {
+            name: "opt",
+            legacy_name: Some("opt_regular_wgt"),
+            command: opt::command,
+            run: opt::run,
+        },
+        Tool {
+            name: "wgt2beam",
+            legacy_name: Some("regular_wgt2beam"),
+            command: wgt2beam::command,
+            run: wgt2beam::run,
+        },
+        Tool {
+            name: "steer",
+            legacy_name: Some("steer_beam"),
+            command: steer::command,
+            run: steer::run,
+        },
+        Tool {
+            name: "az-mean",
+            legacy_name: Some("mean_by_az"),
+            command: az_mean::command,
+            run: az_mean::run,
+        },
+        Tool {
+            name: "beam-cut",
+            legacy_name: None,
+            command: beam_cut::command,
+            run: beam_cut::run,
+        },
+        Tool {
+            name: "trim-ants",
+            legacy_name: Some("trim_ants"),
+            command: trim_ants::command,
+            run: trim_ants::run,
+        },
+        Tool {
+            name: "squint",
+            legacy_name: None,
+            command: squint::command,
+            run: squint::run,
+        },
+        Tool {
+            name: "multibeam",
+            legacy_name: None,
+            command: multibeam::command,
+            run: multibeam::run,
+        },
+        Tool {
+            name: "quantize",
+            legacy_name: None,
+            command: quantize::command,
+            run: quantize::run,
+        },
+        Tool {
+            name: "tolerance",
+            legacy_name: None,
+            command: tolerance::command,
+            run: tolerance::run,
+        },
+        Tool {
+            name: "fg-residual",
+            legacy_name: None,
+            command: fg_residual::command,
+            run: fg_residual::run,
+        },
+        Tool {
+            name: "regrid",
+            legacy_name: None,
+            command: regrid::command,
+            run: regrid::run,
+        },
+        Tool {
+            name: "render",
+            legacy_name: None,
+            command: render::command,
+            run: render::run,
+        },
+        Tool {
+            name: "band-opt",
+            legacy_name: None,
+            command: band_opt::command,
+            run: band_opt::run,
+        },
+        Tool {
+            name: "target",
+            legacy_name: None,
+            command: target::command,
+            run: target::run,
+        },
+        Tool {
+            name: "smooth",
+            legacy_name: None,
+            command: smooth::command,
+            run: smooth::run,
+        },
+        Tool {
+            name: "beam-bl",
+            legacy_name: None,
+            command: beam_bl::command,
+            run: beam_bl::run,
+        },
+        Tool {
+            name: "adaptive",
+            legacy_name: None,
+            command: adaptive::command,
+            run: adaptive::run,
+        },
+        Tool {
+            name: "run",
+            legacy_name: None,
+            command: run_job::command,
+            run: run_job::run,
+        },
+    ]
+}
+
+fn finish(result: CliResult<()>) {
+    if let Err(e) = result {
+        eprintln!("error: {}", e);
+        exit(1);
+    }
+}
+
+/// Entry point of `dbfsim`. If the executable is invoked under the name of
+/// one of the old tools (e.g. through a symlink), it behaves as that tool.
+pub fn main_multi() {
+    let argv0 = args_os()
+        .next()
+        .and_then(|a| {
+            Path::new(&a)
+                .file_stem()
+                .map(|s| s.to_string_lossy().into_owned())
+        })
+        .unwrap_or_default();
+    if tools().iter().any(|t| t.legacy_name == Some(argv0.as_str())) {
+        return main_single(&argv0);
+    }
+
+    let tools = tools();
+    let matches = tools
+        .iter()
+        .fold(
+            Command::new("dbfsim")
+                .version(env!("CARGO_PKG_VERSION"))
+                .subcommand_required(true)
+                .arg_required_else_help(true),
+            |cmd, t| {
+                let sub = (t.command)().name(t.name);
+                cmd.subcommand(match t.legacy_name {
+                    Some(legacy_name) => sub.alias(legacy_name),
+                    None => sub,
+                })
+            },
+        )
+        .get_matches();
+    let (name, sub_matches) = matches.subcommand().unwrap();
+    let tool = tools.iter().find(|t| t.name == name).unwrap();
+    finish((tool.run)(sub_matches, &mut Provenance::new()));
+}
+
+/// Entry point of the standalone binaries kept for the old tool names.
+pub fn main_single(legacy_name: &str) {
+    let tool = tools()
+        .into_iter()
+        .find(|t| t.legacy_name == Some(legacy_name))
+        .unwrap();
+    let matches = (tool.command)().name(legacy_name).get_matches();
+    finish((tool.run)(&matches, &mut Provenance::new()));
+}
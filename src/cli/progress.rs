@@ -0,0 +1,119 @@
+//! Progress of a weight optimization, one line per solver iteration on
+//! stderr and optionally in a log file: CSV, or JSON lines if the name ends
+//! with `.jsonl` or `.json`.
+
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+};
+
+use clap::ArgMatches;
+
+use crate::{
+    cli::{io_error, opt_value, CliError, CliResult},
+    regular_array::opt::{Progress, StopRule},
+};
+
+pub struct ProgressLog {
+    path: String,
+    out: Option<BufWriter<File>>,
+    json: bool,
+    nlines: usize,
+    /// first write error, reported by `finish`
+    error: Option<CliError>,
+}
+
+impl ProgressLog {
+    pub fn create(path: Option<&str>) -> CliResult<Self> {
+        let out = path
+            .map(|p| File::create(p).map_err(|e| io_error(p, e)))
+            .transpose()?
+            .map(BufWriter::new);
+        let path = path.unwrap_or_default().to_string();
+        Ok(ProgressLog {
+            json: path.ends_with(".jsonl") || path.ends_with(".json"),
+            path,
+            out,
+            nlines: 0,
+            error: None,
+        })
+    }
+
+    /// Logs the state after one iteration with tool specific `extra` columns,
+    /// e.g. the relative error of the antenna temperature.
+    pub fn log(&mut self, progress: &Progress, extra: &[(&str, f64)]) {
+        let columns: Vec<(&str, f64)> = [
+            ("iter", progress.iter as f64),
+            ("fitness", progress.fitness),
+        ]
+        .into_iter()
+        .chain(extra.iter().cloned())
+        .chain([
+            ("diversity", progress.diversity),
+            ("elapsed_s", progress.elapsed.as_secs_f64()),
+        ])
+        .collect();
+
+        eprintln!(
+            "{}",
+            columns
+                .iter()
+                .map(|(k, v)| format!("{} {}", k, v))
+                .collect::<Vec<_>>()
+                .join(" ")
+        );
+
+        let out = match &mut self.out {
+            Some(out) if self.error.is_none() => out,
+            _ => return,
+        };
+        let mut line = String::new();
+        if self.json {
+            let fields: Vec<String> = columns
+                .iter()
+                .map(|(k, v)| {
+                    if v.is_finite() {
+                        format!("\"{}\": {}", k, v)
+                    } else {
+                        format!("\"{}\": null", k)
+                    }
+                })
+                .collect();
+            line = format!("{{{}}}\n", fields.join(", "));
+        } else {
+            if self.nlines == 0 {
+                let names: Vec<&str> = columns.iter().map(|(k, _)| *k).collect();
+                line = names.join(",") + "\n";
+            }
+            let values: Vec<String> = columns.iter().map(|(_, v)| v.to_string()).collect();
+            line += &(values.join(",") + "\n");
+        }
+        self.nlines += 1;
+        if let Err(e) = out.write_all(line.as_bytes()) {
+            self.error = Some(io_error(&self.path, e));
+        }
+    }
+
+    /// Reports why the optimization stopped, flushes the log file and
+    /// reports the first write error.
+    pub fn finish(self, stop_reason: &str) -> CliResult<()> {
+        eprintln!("stopped: {}", stop_reason);
+        if let Some(e) = self.error {
+            return Err(e);
+        }
+        match self.out {
+            Some(mut out) => out.flush().map_err(|e| io_error(&self.path, e)),
+            None => Ok(()),
+        }
+    }
+}
+
+/// The stopping rule from the `max_iter`, `max_time` and `target_fitness`
+/// args.
+pub fn stop_rule(matches: &ArgMatches) -> CliResult<StopRule> {
+    Ok(StopRule {
+        max_iter: opt_value(matches, "max_iter")?,
+        max_time_s: opt_value(matches, "max_time")?,
+        target_fitness: opt_value(matches, "target_fitness")?,
+    })
+}
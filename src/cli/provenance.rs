@@ -0,0 +1,265 @@
+//! How an output was made: the command line, crate version, hashes of the
+//! files read, the array config and the RNG seed.
+//!
+//! The I/O helpers of `cli` record every input in the `Provenance` of the
+//! tool run as it is read, so a tool only has to write its output through
+//! them with the same `Provenance`. FITS outputs get `CREATOR`, `DATE`
+//! and `SEED` keywords and `HISTORY` cards, HDF5 files attrs of `/meta`, PNG
+//! images text chunks and YAML or plain text outputs `#` comments.
+
+use std::{
+    env::args,
+    fs::{read, write},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use sha2::{Digest, Sha256};
+
+use crate::cli::{
+    fits::{card, padded, str_card, BLOCK, CARD},
+    io_error, CliResult,
+};
+
+/// Provenance of the outputs of one tool run, or one job.
+#[derive(Clone, Debug, Default)]
+pub struct Provenance {
+    /// path and sha256 of every file read
+    inputs: Vec<(String, String)>,
+    seed: Option<u64>,
+    /// YAML of the array config
+    array_cfg: Option<String>,
+    /// other results worth keeping, e.g. the final fitness of an optimization
+    values: Vec<(String, String)>,
+}
+
+impl Provenance {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records an input file with its sha256; files that cannot be read are
+    /// left to the reader to report.
+    pub fn record_input(&mut self, path: &str) {
+        if self.inputs.iter().any(|(q, _)| q == path) {
+            return;
+        }
+        if let Ok(data) = read(path) {
+            self.inputs.push((path.to_string(), sha256_hex(&data)));
+        }
+    }
+
+    pub fn record_seed(&mut self, seed: u64) {
+        self.seed = Some(seed);
+    }
+
+    pub fn record_array_cfg(&mut self, yaml: &str) {
+        self.array_cfg = Some(yaml.to_string());
+    }
+
+    /// Records a result under `key`, at most 8 chars to be a FITS keyword.
+    pub fn record_value(&mut self, key: &str, value: &str) {
+        self.values.retain(|(k, _)| k != key);
+        self.values.push((key.to_string(), value.to_string()));
+    }
+
+    /// Lines describing the run, as written to HISTORY cards and comments.
+    fn history_lines(&self) -> Vec<String> {
+        let mut lines = vec![format!("command: {}", command_line())];
+        for (path, hash) in &self.inputs {
+            lines.push(format!("input: {} sha256={}", path, hash));
+        }
+        if let Some(seed) = self.seed {
+            lines.push(format!("seed: {}", seed));
+        }
+        for (key, value) in &self.values {
+            lines.push(format!("{}: {}", key.to_lowercase(), value));
+        }
+        if let Some(cfg) = &self.array_cfg {
+            lines.push("array config:".to_string());
+            lines.extend(cfg.lines().map(|l| format!("  {}", l)));
+        }
+        lines
+    }
+
+    /// Header cards to append to the primary HDU of a FITS output.
+    pub fn cards(&self) -> Vec<String> {
+        let mut cards = vec![
+            str_card("CREATOR", &creator()),
+            str_card("DATE", &utc_now()),
+        ];
+        if let Some(seed) = self.seed {
+            cards.push(card("SEED", &seed.to_string()));
+        }
+        for (key, value) in &self.values {
+            cards.push(str_card(&key.to_uppercase(), value));
+        }
+        for line in self.history_lines() {
+            let line: Vec<char> = line
+                .chars()
+                .filter(|c| c.is_ascii() && !c.is_ascii_control())
+                .collect();
+            for chunk in line.chunks(72) {
+                cards.push(format!("HISTORY {}", chunk.iter().collect::<String>()));
+            }
+        }
+        cards
+    }
+
+    /// Key and value pairs for the attrs of an HDF5 file or PNG text chunks.
+    pub fn attrs(&self) -> Vec<(String, String)> {
+        let mut attrs = vec![
+            ("creator".to_string(), creator()),
+            ("date".to_string(), utc_now()),
+            ("command".to_string(), command_line()),
+        ];
+        let inputs: Vec<String> = self
+            .inputs
+            .iter()
+            .map(|(path, hash)| format!("{}  {}", hash, path))
+            .collect();
+        attrs.push(("inputs".to_string(), inputs.join("\n")));
+        if let Some(seed) = self.seed {
+            attrs.push(("seed".to_string(), seed.to_string()));
+        }
+        if let Some(cfg) = &self.array_cfg {
+            attrs.push(("array_cfg".to_string(), cfg.clone()));
+        }
+        for (key, value) in &self.values {
+            attrs.push((key.to_lowercase(), value.clone()));
+        }
+        attrs
+    }
+
+    /// `#` comment lines for YAML and plain text outputs.
+    pub fn comments(&self) -> String {
+        std::iter::once(format!("created by {} at {}", creator(), utc_now()))
+            .chain(self.history_lines())
+            .map(|l| format!("# {}\n", l))
+            .collect()
+    }
+
+    /// Appends the provenance cards to the primary header of a FITS file
+    /// that has already been written, e.g. by `healpix_fits` or `fitsimg`.
+    pub fn stamp_fits(&self, path: &str) -> CliResult<()> {
+        let data = read(path).map_err(|e| io_error(path, e))?;
+        let end = data
+            .chunks(CARD)
+            .take_while(|c| c.len() == CARD)
+            .position(|c| c.starts_with(b"END") && c[3..8].iter().all(|&b| b == b' '))
+            .ok_or_else(|| io_error(path, "no END in the primary header"))?;
+        let header_len = padded((end + 1) * CARD);
+
+        let cards = self.cards();
+        let keys: Vec<&str> = cards.iter().map(|c| c[..8].trim()).collect();
+        let mut buf: Vec<u8> = data[..end * CARD]
+            .chunks(CARD)
+            // drop blank cards and those of an earlier stamp
+            .filter(|c| {
+                let key = String::from_utf8_lossy(&c[..8]);
+                !key.trim().is_empty() && !keys.contains(&key.trim())
+            })
+            .flatten()
+            .cloned()
+            .collect();
+        for c in cards.iter().map(|s| s.as_str()).chain(["END"]) {
+            buf.extend(format!("{:<80}", c).bytes());
+        }
+        buf.resize(padded(buf.len()), b' ');
+        debug_assert_eq!(buf.len() % BLOCK, 0);
+        buf.extend_from_slice(&data[header_len..]);
+        write(path, buf).map_err(|e| io_error(path, e))
+    }
+}
+
+pub fn creator() -> String {
+    format!("dbf_beam_simulator {}", env!("CARGO_PKG_VERSION"))
+}
+
+pub fn command_line() -> String {
+    args().collect::<Vec<_>>().join(" ")
+}
+
+/// Current UTC time as the FITS `DATE` value.
+pub fn utc_now() -> String {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0);
+    unix2date(secs)
+}
+
+/// UTC date and time of a Unix timestamp, as `YYYY-MM-DDThh:mm:ss`.
+fn unix2date(secs: i64) -> String {
+    let (days, rem) = (secs.div_euclid(86400), secs.rem_euclid(86400));
+    // civil date from days since 1970-01-01
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = doy - (153 * mp + 2) / 5 + 1;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 };
+    let y = yoe + era * 400 + if m <= 2 { 1 } else { 0 };
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}",
+        y,
+        m,
+        d,
+        rem / 3600,
+        rem / 60 % 60,
+        rem % 60
+    )
+}
+
+/// SHA-256 of `data` as lower case hex.
+pub fn sha256_hex(data: &[u8]) -> String {
+    Sha256::digest(data)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sha256_test_vectors() {
+        // FIPS 180-4 examples
+        let cases: [(&[u8], &str); 4] = [
+            (
+                b"",
+                "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855",
+            ),
+            (
+                b"abc",
+                "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad",
+            ),
+            (
+                b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq",
+                "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1",
+            ),
+            (
+                b"abcdefghbcdefghicdefghijdefghijkefghijklfghijklmghijklmnhijklmnoijklmnopjklmnopqklmnopqrlmnopqrsmnopqrstnopqrstu",
+                "cf5b16a778af8380036ce59e7b0492370b249b11e8f07a51afac45037afee9d1",
+            ),
+        ];
+        for (msg, hash) in cases {
+            assert_eq!(sha256_hex(msg), hash);
+        }
+        assert_eq!(
+            sha256_hex(&vec![b'a'; 1_000_000]),
+            "cdc76e5c9914fb9281a1c7e284d73e67f1809a48a497200e046d39ccc7112cd0"
+        );
+    }
+
+    #[test]
+    fn unix_to_date() {
+        assert_eq!(unix2date(0), "1970-01-01T00:00:00");
+        assert_eq!(unix2date(-86400), "1969-12-31T00:00:00");
+        assert_eq!(unix2date(951782400), "2000-02-29T00:00:00");
+        assert_eq!(unix2date(1700000000), "2023-11-14T22:13:20");
+        assert_eq!(unix2date(4107542399), "2100-02-28T23:59:59");
+    }
+}
@@ -0,0 +1,408 @@
+//! Regular array weight files with the spacing, freq and layout stored as
+//! header keywords.
+//!
+//! The weights are a primary image, the first axis (`NAXIS1`, along a row)
+//! running along y and the second along x, both in metre with the central
+//! element at `CRPIX1`, `CRPIX2`. A cube has one plane per freq along
+//! `NAXIS3`. Complex weights have the real and imaginary parts as the two
+//! planes of a cube with `CTYPE3 = 'COMPLEX'`. Files without `WGTFMT` are
+//! plain images from older versions.
+
+use std::{fs::File, io::Write};
+
+use clap::ArgMatches;
+
+use num::complex::Complex;
+
+use ndarray::{stack, Array2, ArrayD, ArrayView2, Axis, Ix2, Ix3};
+
+use fitsimg::read_img;
+
+use crate::cli::{
+    check_exists, check_no_h5,
+    fits::{card, padded, push_header, read_hdus, str_card, Header},
+    io_error, length_m, opt_freq_hz,
+    provenance::Provenance,
+    CliError, CliResult,
+};
+
+const WGTFMT: &str = "DBF-WGT-1";
+
+/// Parameters of a regular array weight file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct WgtMeta {
+    /// element spacing, in metre
+    pub spacing_m: f64,
+    /// freq of every plane, in Hz, equally spaced; one for a 2D file
+    pub freqs_hz: Vec<f64>,
+}
+
+impl WgtMeta {
+    pub fn new(spacing_m: f64, freq_hz: f64) -> Self {
+        WgtMeta {
+            spacing_m,
+            freqs_hz: vec![freq_hz],
+        }
+    }
+
+    fn cards(
+        &self,
+        prov: &Provenance,
+        path: &str,
+        shape: &[usize],
+        complex: bool,
+    ) -> CliResult<Vec<String>> {
+        let positive = |key: &str, x: f64| {
+            if x.is_finite() && x > 0.0 {
+                Ok(())
+            } else {
+                Err(io_error(
+                    path,
+                    format!("{} must be positive, found {}", key, x),
+                ))
+            }
+        };
+        positive("spacing", self.spacing_m)?;
+        for &f in &self.freqs_hz {
+            positive("freq", f)?;
+        }
+        let f0 = self.freqs_hz[0];
+        let df = self.freqs_hz.get(1).map_or(0.0, |f1| f1 - f0);
+        if self
+            .freqs_hz
+            .iter()
+            .enumerate()
+            .any(|(i, &f)| (f - f0 - i as f64 * df).abs() > 1e-6 * f)
+        {
+            return Err(io_error(path, "freqs of a wgt cube must be equally spaced"));
+        }
+
+        let mut cards = vec![
+            str_card("WGTFMT", WGTFMT),
+            str_card("WGTCONV", "AMPLITUDE") + " / beam = |sum w exp(2 pi i r.n / lambda)|^2",
+        ];
+        for (i, axis) in ["Y", "X"].iter().enumerate() {
+            let n = i + 1;
+            cards.push(str_card(&format!("CTYPE{}", n), axis));
+            cards.push(str_card(&format!("CUNIT{}", n), "m"));
+            cards.push(card(&format!("CDELT{}", n), &self.spacing_m.to_string()));
+            cards.push(card(
+                &format!("CRPIX{}", n),
+                &((shape[shape.len() - 1 - i] + 1) as f64 / 2.0).to_string(),
+            ));
+            cards.push(card(&format!("CRVAL{}", n), "0.0"));
+        }
+        if shape.len() == 2 {
+            cards.push(card("FREQ", &f0.to_string()) + " / Hz");
+        } else if complex {
+            cards.push(card("FREQ", &f0.to_string()) + " / Hz");
+            cards.push(str_card("CTYPE3", "COMPLEX") + " / real and imaginary parts");
+        } else {
+            cards.push(str_card("CTYPE3", "FREQ"));
+            cards.push(str_card("CUNIT3", "Hz"));
+            cards.push(card("CRPIX3", "1.0"));
+            cards.push(card("CRVAL3", &f0.to_string()));
+            cards.push(card("CDELT3", &df.to_string()));
+        }
+        cards.extend(prov.cards());
+        Ok(cards)
+    }
+
+    fn from_header(path: &str, header: &Header, shape: &[usize]) -> CliResult<Option<Self>> {
+        let fmt = match header.get("WGTFMT") {
+            Some(fmt) => fmt,
+            None => return Ok(None),
+        };
+        if fmt != WGTFMT {
+            return Err(io_error(path, format!("unknown WGTFMT '{}'", fmt)));
+        }
+        let get = |key: &str| {
+            header
+                .get(key)
+                .map(|s| s.as_str())
+                .ok_or_else(|| io_error(path, format!("no {} keyword", key)))
+        };
+        let num = |key: &str| {
+            get(key)?
+                .parse::<f64>()
+                .map_err(|e| io_error(path, format!("{}: {}", key, e)))
+        };
+        let expect = |key: &str, value: &str| {
+            let found = get(key)?;
+            if found.eq_ignore_ascii_case(value) {
+                Ok(())
+            } else {
+                Err(io_error(
+                    path,
+                    format!("{} must be '{}', found '{}'", key, value, found),
+                ))
+            }
+        };
+
+        expect("WGTCONV", "AMPLITUDE")?;
+        expect("CTYPE1", "Y")?;
+        expect("CTYPE2", "X")?;
+        let spacing_m = num("CDELT1")?;
+        if spacing_m.is_nan() || spacing_m <= 0.0 || num("CDELT2")? != spacing_m {
+            return Err(io_error(
+                path,
+                "CDELT1 and CDELT2 must be the same positive spacing",
+            ));
+        }
+        let freqs_hz = match shape.len() {
+            2 => vec![num("FREQ")?],
+            3 if is_complex(header) => vec![num("FREQ")?],
+            3 => {
+                expect("CTYPE3", "FREQ")?;
+                let (f0, df, p0) = (num("CRVAL3")?, num("CDELT3")?, num("CRPIX3")?);
+                (0..shape[0])
+                    .map(|i| f0 + (i as f64 + 1.0 - p0) * df)
+                    .collect()
+            }
+            n => {
+                return Err(io_error(
+                    path,
+                    format!("wgt must be 2D or 3D, found {}D", n),
+                ))
+            }
+        };
+        if freqs_hz.iter().any(|&f| f.is_nan() || f <= 0.0) {
+            return Err(io_error(path, "freqs must be positive"));
+        }
+        Ok(Some(WgtMeta {
+            spacing_m,
+            freqs_hz,
+        }))
+    }
+}
+
+fn is_complex(header: &Header) -> bool {
+    header
+        .get("CTYPE3")
+        .is_some_and(|t| t.eq_ignore_ascii_case("COMPLEX"))
+}
+
+/// Writes a 2D wgt or a cube with freq along the first axis.
+pub fn write_wgt_file(
+    prov: &Provenance,
+    path: &str,
+    wgt: &ArrayD<f64>,
+    meta: &WgtMeta,
+) -> CliResult<()> {
+    write_image(prov, path, wgt, meta, false)
+}
+
+fn write_image(
+    prov: &Provenance,
+    path: &str,
+    wgt: &ArrayD<f64>,
+    meta: &WgtMeta,
+    complex: bool,
+) -> CliResult<()> {
+    check_no_h5(path)?;
+    let shape = wgt.shape();
+    let nfreq = if shape.len() == 3 && !complex {
+        shape[0]
+    } else {
+        1
+    };
+    if !(shape.len() == 2 || shape.len() == 3) || meta.freqs_hz.len() != nfreq {
+        return Err(CliError::Invalid(format!(
+            "{}: wgt of shape {:?} does not match {} freqs",
+            path,
+            shape,
+            meta.freqs_hz.len()
+        )));
+    }
+
+    let mut cards = vec![
+        card("SIMPLE", "T"),
+        card("BITPIX", "-64"),
+        card("NAXIS", &shape.len().to_string()),
+    ];
+    for (i, n) in shape.iter().rev().enumerate() {
+        cards.push(card(&format!("NAXIS{}", i + 1), &n.to_string()));
+    }
+    cards.extend(meta.cards(prov, path, shape, complex)?);
+
+    let mut buf = Vec::new();
+    push_header(&mut buf, &cards);
+    let start = buf.len();
+    for &x in wgt.iter() {
+        buf.extend_from_slice(&x.to_be_bytes());
+    }
+    let len = buf.len() - start;
+    buf.resize(start + padded(len), 0);
+
+    File::create(path)
+        .and_then(|mut f| f.write_all(&buf))
+        .map_err(|e| io_error(path, e))
+}
+
+/// Reads a 2D wgt or a cube, with its parameters if it has any.
+pub fn read_wgt_file(
+    prov: &mut Provenance,
+    path: &str,
+) -> CliResult<(ArrayD<f64>, Option<WgtMeta>)> {
+    let (wgt, complex, meta) = read_image(prov, path)?;
+    if complex {
+        return Err(io_error(
+            path,
+            "complex wgt, only tools accepting complex weights can read it",
+        ));
+    }
+    Ok((wgt, meta))
+}
+
+/// The image of a wgt file, whether it is complex and its parameters.
+fn read_image(
+    prov: &mut Provenance,
+    path: &str,
+) -> CliResult<(ArrayD<f64>, bool, Option<WgtMeta>)> {
+    check_exists(path)?;
+    prov.record_input(path);
+    let wgt = read_img::<f64>(path.to_string(), 0).map_err(|e| io_error(path, e))?;
+    let header = read_hdus(path)?
+        .into_iter()
+        .next()
+        .map(|h| h.0)
+        .ok_or_else(|| io_error(path, "empty fits file"))?;
+    let meta = WgtMeta::from_header(path, &header, wgt.shape())?;
+    Ok((wgt, is_complex(&header), meta))
+}
+
+/// Writes a 2D wgt, which with the `hdf5` feature may also go to an HDF5
+/// product file as `FILE.h5:/wgts/NAME`.
+pub fn write_wgt_meta(
+    prov: &Provenance,
+    path: &str,
+    wgt: Array2<f64>,
+    meta: &WgtMeta,
+) -> CliResult<()> {
+    #[cfg(feature = "hdf5")]
+    if let Some(h5path) = crate::cli::h5::H5Path::parse(path) {
+        return h5path.write_wgts(prov, &[wgt], meta.spacing_m, &meta.freqs_hz);
+    }
+    write_wgt_file(prov, path, &wgt.into_dyn(), meta)
+}
+
+/// Reads a 2D wgt, which with the `hdf5` feature may also be one plane of a
+/// cube in an HDF5 file.
+pub fn read_wgt_meta(
+    prov: &mut Provenance,
+    path: &str,
+) -> CliResult<(Array2<f64>, Option<WgtMeta>)> {
+    #[cfg(feature = "hdf5")]
+    if let Some(h5path) = crate::cli::h5::H5Path::parse(path) {
+        prov.record_input(h5path.file);
+        let (wgt, spacing_m, freq_hz) = h5path.read_wgt()?;
+        return Ok((wgt, Some(WgtMeta::new(spacing_m, freq_hz))));
+    }
+    let (wgt, meta) = read_wgt_file(prov, path)?;
+    let wgt = wgt
+        .into_dimensionality::<Ix2>()
+        .map_err(|e| io_error(path, e))?;
+    Ok((wgt, meta))
+}
+
+/// Writes complex weights, e.g. of a steered or asymmetric beam.
+pub fn write_complex_wgt(
+    prov: &Provenance,
+    path: &str,
+    wgt: &Array2<Complex<f64>>,
+    meta: &WgtMeta,
+) -> CliResult<()> {
+    if meta.freqs_hz.len() != 1 {
+        return Err(CliError::Invalid(format!(
+            "{}: complex wgt must have one freq, found {}",
+            path,
+            meta.freqs_hz.len()
+        )));
+    }
+    #[cfg(feature = "hdf5")]
+    if let Some(h5path) = crate::cli::h5::H5Path::parse(path) {
+        return h5path.write_complex_wgt(prov, wgt, meta.spacing_m, meta.freqs_hz[0]);
+    }
+    let parts = [wgt.mapv(|w| w.re), wgt.mapv(|w| w.im)];
+    let views: Vec<ArrayView2<f64>> = parts.iter().map(|w| w.view()).collect();
+    let cube = stack(Axis(0), &views).map_err(|e| io_error(path, e))?;
+    write_image(prov, path, &cube.into_dyn(), meta, true)
+}
+
+/// Reads complex weights, or real ones as weights with zero phase.
+pub fn read_complex_wgt(
+    prov: &mut Provenance,
+    path: &str,
+) -> CliResult<(Array2<Complex<f64>>, Option<WgtMeta>)> {
+    #[cfg(feature = "hdf5")]
+    if let Some(h5path) = crate::cli::h5::H5Path::parse(path) {
+        prov.record_input(h5path.file);
+        let (wgt, spacing_m, freq_hz) = h5path.read_complex_wgt()?;
+        return Ok((wgt, Some(WgtMeta::new(spacing_m, freq_hz))));
+    }
+    let (wgt, complex, meta) = read_image(prov, path)?;
+    if !complex {
+        let wgt = wgt
+            .into_dimensionality::<Ix2>()
+            .map_err(|e| io_error(path, e))?;
+        return Ok((wgt.mapv(|x| Complex::new(x, 0.0)), meta));
+    }
+    let wgt = wgt
+        .into_dimensionality::<Ix3>()
+        .map_err(|e| io_error(path, e))?;
+    if wgt.shape()[0] != 2 {
+        return Err(io_error(
+            path,
+            format!("complex wgt must have 2 planes, found {}", wgt.shape()[0]),
+        ));
+    }
+    let (re, im) = (wgt.index_axis(Axis(0), 0), wgt.index_axis(Axis(0), 1));
+    let wgt = Array2::from_shape_fn(re.raw_dim(), |ij| Complex::new(re[ij], im[ij]));
+    Ok((wgt, meta))
+}
+
+/// Writes one wgt per freq, freq along the first axis.
+pub fn write_wgt_cube(
+    prov: &Provenance,
+    path: &str,
+    wgts: &[Array2<f64>],
+    meta: &WgtMeta,
+) -> CliResult<()> {
+    #[cfg(feature = "hdf5")]
+    if let Some(h5path) = crate::cli::h5::H5Path::parse(path) {
+        return h5path.write_wgts(prov, wgts, meta.spacing_m, &meta.freqs_hz);
+    }
+    let views: Vec<ArrayView2<f64>> = wgts.iter().map(|w| w.view()).collect();
+    let cube = stack(Axis(0), &views).map_err(|e| io_error(path, e))?;
+    write_wgt_file(prov, path, &cube.into_dyn(), meta)
+}
+
+/// The `freq` and `spacing` args, falling back to the values stored in a wgt
+/// file; args that disagree with the file override it with a warning.
+pub fn wgt_freq_spacing(matches: &ArgMatches, meta: Option<&WgtMeta>) -> CliResult<(f64, f64)> {
+    let freq_hz = match (opt_freq_hz(matches, "freq")?, meta) {
+        (Some(f), _) => f,
+        (None, Some(m)) => m.freqs_hz[0],
+        (None, None) => return Err(CliError::MissingArg("freq".to_string())),
+    };
+    let d = match (matches.is_present("spacing"), meta) {
+        (true, _) => length_m(matches, "spacing", Some(freq_hz))?,
+        (false, Some(m)) => m.spacing_m,
+        (false, None) => return Err(CliError::MissingArg("spacing".to_string())),
+    };
+    if let Some(m) = meta {
+        if (freq_hz - m.freqs_hz[0]).abs() > 1e-6 * freq_hz {
+            eprintln!(
+                "warning: freq {} Hz overrides {} Hz of the wgt file",
+                freq_hz, m.freqs_hz[0]
+            );
+        }
+        if (d - m.spacing_m).abs() > 1e-6 * d {
+            eprintln!(
+                "warning: spacing {} m overrides {} m of the wgt file",
+                d, m.spacing_m
+            );
+        }
+    }
+    Ok((freq_hz, d))
+}
@@ -0,0 +1,39 @@
+use scorus::coordinates::SphCoord;
+
+use crate::{error::DbfResult, projection::sample, utils::checked_npix2nside};
+
+/// Samples a healpix map along the great circle through the zenith in the
+/// vertical plane of `az_deg` (from north towards east). `theta_deg` is the
+/// signed zenith angle, positive towards `az_deg` and negative towards the
+/// opposite azimuth.
+pub fn azimuth_cut(map: &[f64], az_deg: f64, theta_deg: &[f64]) -> DbfResult<Vec<f64>> {
+    let nside = checked_npix2nside(map.len())?;
+    Ok(theta_deg
+        .iter()
+        .map(|&t| {
+            let az = if t >= 0.0 { az_deg } else { az_deg + 180.0 };
+            let dir = SphCoord::new(
+                t.abs().to_radians(),
+                (90.0 - az).rem_euclid(360.0).to_radians(),
+            );
+            sample(map, nside, dir)
+        })
+        .collect())
+}
+
+/// Samples a healpix map along the cone of constant elevation `el_deg` at
+/// the azimuths `az_deg` (from north towards east).
+pub fn elevation_cut(map: &[f64], el_deg: f64, az_deg: &[f64]) -> DbfResult<Vec<f64>> {
+    let nside = checked_npix2nside(map.len())?;
+    let pol = (90.0 - el_deg).to_radians();
+    Ok(az_deg
+        .iter()
+        .map(|&az| {
+            sample(
+                map,
+                nside,
+                SphCoord::new(pol, (90.0 - az).rem_euclid(360.0).to_radians()),
+            )
+        })
+        .collect())
+}
@@ -0,0 +1,71 @@
+use std::fmt::{self, Display};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DbfError {
+    /// two inputs that must match element by element have different lengths
+    LengthMismatch {
+        what: &'static str,
+        expected: usize,
+        found: usize,
+    },
+    /// not 12 * nside^2 pixels
+    InvalidNpix(usize),
+    /// not 4 * nside - 1 rings
+    InvalidNring(usize),
+    InvalidShape {
+        what: &'static str,
+        shape: Vec<usize>,
+        reason: &'static str,
+    },
+    InvalidValue {
+        what: &'static str,
+        reason: String,
+    },
+}
+
+impl Display for DbfError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DbfError::LengthMismatch {
+                what,
+                expected,
+                found,
+            } => write!(f, "{}: expected length {}, found {}", what, expected, found),
+            DbfError::InvalidNpix(npix) => write!(f, "{} is not a valid healpix npix", npix),
+            DbfError::InvalidNring(nring) => {
+                write!(f, "{} is not a valid healpix num of rings", nring)
+            }
+            DbfError::InvalidShape {
+                what,
+                shape,
+                reason,
+            } => write!(f, "{}: invalid shape {:?}, {}", what, shape, reason),
+            DbfError::InvalidValue { what, reason } => write!(f, "{}: {}", what, reason),
+        }
+    }
+}
+
+impl std::error::Error for DbfError {}
+
+pub type DbfResult<T> = Result<T, DbfError>;
+
+pub fn check_len(what: &'static str, expected: usize, found: usize) -> DbfResult<()> {
+    if expected == found {
+        Ok(())
+    } else {
+        Err(DbfError::LengthMismatch {
+            what,
+            expected,
+            found,
+        })
+    }
+}
+
+/// Checks that the per-element lists of an array all have the same length.
+pub fn check_array_lists(lists: &[(&'static str, &[f64])]) -> DbfResult<usize> {
+    let n = lists.first().map(|(_, l)| l.len()).unwrap_or(0);
+    for (what, l) in lists {
+        check_len(what, n, l.len())?;
+    }
+    Ok(n)
+}
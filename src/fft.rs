@@ -4,11 +4,19 @@ use rustfft::{FftDirection, FftNum, FftPlanner};
 
 use ndarray::{s, Array2, ArrayView2, ArrayViewMut, ArrayViewMut2, Dimension};
 
-pub fn fftshift2<T>(in_data: ArrayView2<T>) -> Array2<T>
+use crate::error::{check_len, DbfError, DbfResult};
+
+pub fn fftshift2<T>(in_data: ArrayView2<T>) -> DbfResult<Array2<T>>
 where
     T: Copy,
 {
-    assert!(in_data.shape()[0] % 2 == 0);
+    if in_data.shape()[0] % 2 != 0 || in_data.shape()[1] % 2 != 0 {
+        return Err(DbfError::InvalidShape {
+            what: "fftshift2 input",
+            shape: in_data.shape().to_vec(),
+            reason: "both sides must be even",
+        });
+    }
     let h = in_data.shape()[0];
     let w = in_data.shape()[1];
     let mut result =
@@ -27,10 +35,15 @@ where
     result
         .slice_mut(s![h / 2..h, w / 2..w])
         .assign(&in_data.slice(s![0..h / 2, 0..w / 2]));
-    result
+    Ok(result)
 }
 
-fn _fft<T: FftNum>(input: &mut [Complex<T>], output: &mut [Complex<T>], inverse: bool) {
+fn _fft<T: FftNum>(
+    input: &mut [Complex<T>],
+    output: &mut [Complex<T>],
+    inverse: bool,
+) -> DbfResult<()> {
+    check_len("fft output", input.len(), output.len())?;
     let mut planner = FftPlanner::new();
     let len = input.len();
     let fft = planner.plan_fft(
@@ -44,42 +57,70 @@ fn _fft<T: FftNum>(input: &mut [Complex<T>], output: &mut [Complex<T>], inverse:
     let scratch_len = fft.get_outofplace_scratch_len();
     let mut scratch = vec![Complex::zero(); scratch_len];
     fft.process_outofplace_with_scratch(input, output, &mut scratch);
+    Ok(())
 }
 
-pub fn fft<T: FftNum>(input: &mut [Complex<T>], output: &mut [Complex<T>]) {
-    _fft(input, output, false);
+pub fn fft<T: FftNum>(input: &mut [Complex<T>], output: &mut [Complex<T>]) -> DbfResult<()> {
+    _fft(input, output, false)
 }
 
-pub fn ifft<T: FftNum>(input: &mut [Complex<T>], output: &mut [Complex<T>]) {
-    _fft(input, output, true);
+pub fn ifft<T: FftNum>(input: &mut [Complex<T>], output: &mut [Complex<T>]) -> DbfResult<()> {
+    _fft(input, output, true)
     /*
     for v in output.iter_mut() {
         *v = v.unscale(T::from(input.len() as u32));
     }*/
 }
 
-pub fn fft2(input: ArrayViewMut2<Complex<f64>>, output: ArrayViewMut2<Complex<f64>>) {
-    fftnd(input, output, &[0, 1]);
+pub fn fft2(
+    input: ArrayViewMut2<Complex<f64>>,
+    output: ArrayViewMut2<Complex<f64>>,
+) -> DbfResult<()> {
+    fftnd(input, output, &[0, 1])
 }
 
-pub fn ifft2(input: ArrayViewMut2<Complex<f64>>, output: ArrayViewMut2<Complex<f64>>) {
-    ifftnd(input, output, &[1, 0]);
+pub fn ifft2(
+    input: ArrayViewMut2<Complex<f64>>,
+    output: ArrayViewMut2<Complex<f64>>,
+) -> DbfResult<()> {
+    ifftnd(input, output, &[1, 0])
 }
 
 pub fn fftn<T: FftNum, D: Dimension>(
     input: &mut ArrayViewMut<Complex<T>, D>,
     output: &mut ArrayViewMut<Complex<T>, D>,
     axis: usize,
-) {
-    _fftn(input, output, axis, false);
+) -> DbfResult<()> {
+    _fftn(input, output, axis, false)
 }
 
 pub fn ifftn<T: FftNum, D: Dimension>(
     input: &mut ArrayViewMut<Complex<T>, D>,
     output: &mut ArrayViewMut<Complex<T>, D>,
     axis: usize,
-) {
-    _fftn(input, output, axis, true);
+) -> DbfResult<()> {
+    _fftn(input, output, axis, true)
+}
+
+fn check_shapes<T, D: Dimension>(
+    input: &ArrayViewMut<T, D>,
+    output: &ArrayViewMut<T, D>,
+    axes: &[usize],
+) -> DbfResult<()> {
+    if input.shape() != output.shape() {
+        return Err(DbfError::InvalidShape {
+            what: "fft output",
+            shape: output.shape().to_vec(),
+            reason: "must match the input shape",
+        });
+    }
+    if axes.iter().any(|&a| a >= input.ndim()) {
+        return Err(DbfError::InvalidValue {
+            what: "fft axes",
+            reason: format!("{:?} out of range for {} dims", axes, input.ndim()),
+        });
+    }
+    Ok(())
 }
 
 fn _fftn<T: FftNum, D: Dimension>(
@@ -87,7 +128,8 @@ fn _fftn<T: FftNum, D: Dimension>(
     output: &mut ArrayViewMut<Complex<T>, D>,
     axis: usize,
     inverse: bool,
-) {
+) -> DbfResult<()> {
+    check_shapes(input, output, &[axis])?;
     if inverse {
         mutate_lane(input, output, ifft, axis)
     } else {
@@ -99,16 +141,24 @@ pub fn fftnd<T: FftNum, D: Dimension>(
     mut input: ArrayViewMut<Complex<T>, D>,
     mut output: ArrayViewMut<Complex<T>, D>,
     axes: &[usize],
-) {
-    _fftnd(&mut input, &mut output, axes, false);
+) -> DbfResult<()> {
+    _fftnd(&mut input, &mut output, axes, false)
 }
 
 pub fn ifftnd<T: FftNum, D: Dimension>(
     mut input: ArrayViewMut<Complex<T>, D>,
     mut output: ArrayViewMut<Complex<T>, D>,
     axes: &[usize],
-) {
-    _fftnd(&mut input, &mut output, axes, true);
+) -> DbfResult<()> {
+    _fftnd(&mut input, &mut output, axes, true)
+}
+
+fn not_contiguous<T, D: Dimension>(a: &ArrayViewMut<T, D>) -> DbfError {
+    DbfError::InvalidShape {
+        what: "fft input",
+        shape: a.shape().to_vec(),
+        reason: "rows must be contiguous",
+    }
 }
 
 fn _fftnd<T: FftNum, D: Dimension>(
@@ -116,50 +166,60 @@ fn _fftnd<T: FftNum, D: Dimension>(
     output: &mut ArrayViewMut<Complex<T>, D>,
     axes: &[usize],
     inverse: bool,
-) {
+) -> DbfResult<()> {
+    check_shapes(input, output, axes)?;
     let len = axes.len();
     for (i, &axis) in axes.iter().enumerate() {
-        _fftn(input, output, axis, inverse);
+        _fftn(input, output, axis, inverse)?;
         if i < len - 1 {
+            let err = not_contiguous(input);
             let mut outrows = output.rows_mut().into_iter();
             for mut row in input.rows_mut() {
                 let mut outrow = outrows.next().unwrap();
                 row.as_slice_mut()
-                    .unwrap()
-                    .copy_from_slice(outrow.as_slice_mut().unwrap());
+                    .ok_or_else(|| err.clone())?
+                    .copy_from_slice(outrow.as_slice_mut().ok_or_else(|| err.clone())?);
             }
         }
     }
+    Ok(())
 }
 
 fn mutate_lane<T: Zero + Clone, D: Dimension>(
     input: &mut ArrayViewMut<T, D>,
     output: &mut ArrayViewMut<T, D>,
-    f: fn(&mut [T], &mut [T]) -> (),
+    f: fn(&mut [T], &mut [T]) -> DbfResult<()>,
     axis: usize,
-) {
+) -> DbfResult<()> {
     if axis > 0 {
         input.swap_axes(0, axis);
         output.swap_axes(0, axis);
-        {
+        let result = (|| {
             let mut outrows = output.rows_mut().into_iter();
             for row in input.rows_mut() {
                 let mut outrow = outrows.next().unwrap();
                 let mut vec = row.to_vec();
                 let mut out = vec![Zero::zero(); outrow.len()];
-                f(&mut vec, &mut out);
+                f(&mut vec, &mut out)?;
                 for i in 0..outrow.len() {
                     outrow[i] = out.remove(0);
                 }
             }
-        }
+            Ok(())
+        })();
         input.swap_axes(0, axis);
         output.swap_axes(0, axis);
+        result
     } else {
+        let err = not_contiguous(input);
         let mut outrows = output.rows_mut().into_iter();
         for mut row in input.rows_mut() {
             let mut outrow = outrows.next().unwrap();
-            f(row.as_slice_mut().unwrap(), outrow.as_slice_mut().unwrap());
+            f(
+                row.as_slice_mut().ok_or_else(|| err.clone())?,
+                outrow.as_slice_mut().ok_or_else(|| err.clone())?,
+            )?;
         }
+        Ok(())
     }
 }
@@ -0,0 +1,192 @@
+use std::f64::consts::PI;
+
+use rand::Rng;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    arbitrary_array::calc_averaged_array_beam,
+    error::{check_len, DbfError, DbfResult},
+    tolerance::gaussian,
+    utils::calc_averaged_ant_output,
+};
+
+/// Frequency dependent multiplicative gain error of the receiver chain.
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize)]
+pub struct CalibrationError {
+    /// fractional amplitude of a sinusoidal gain ripple
+    pub ripple_amp: f64,
+    /// period of the gain ripple, in Hz
+    pub ripple_period: f64,
+    /// rms fractional gain error, independent between channels
+    pub gain_sigma: f64,
+}
+
+impl CalibrationError {
+    pub fn apply<R: Rng>(&self, freqs_hz: &[f64], temps: &[f64], rng: &mut R) -> Vec<f64> {
+        freqs_hz
+            .iter()
+            .zip(temps.iter())
+            .map(|(&f, &t)| {
+                let ripple = if self.ripple_period > 0.0 {
+                    self.ripple_amp * (2.0 * PI * f / self.ripple_period).sin()
+                } else {
+                    0.0
+                };
+                t * (1.0 + ripple + self.gain_sigma * gaussian(rng))
+            })
+            .collect()
+    }
+}
+
+/// Solves `a x = b` by Gaussian elimination with partial pivoting; fails if
+/// `a` is singular to working precision.
+pub fn solve_linear(mut a: Vec<Vec<f64>>, mut b: Vec<f64>) -> DbfResult<Vec<f64>> {
+    let n = b.len();
+    check_len("a", n, a.len())?;
+    for row in &a {
+        check_len("a row", n, row.len())?;
+    }
+    let scale = a.iter().flatten().fold(0.0_f64, |s, x| s.max(x.abs()));
+    let tol = scale * n as f64 * f64::EPSILON;
+    for k in 0..n {
+        let p = (k..n)
+            .max_by(|&i, &j| a[i][k].abs().total_cmp(&a[j][k].abs()))
+            .unwrap_or(k);
+        let pivot = a[p][k].abs();
+        if pivot.is_nan() || pivot <= tol {
+            return Err(DbfError::InvalidValue {
+                what: "linear system",
+                reason: format!("singular, pivot {} at column {}", pivot, k),
+            });
+        }
+        a.swap(k, p);
+        b.swap(k, p);
+        let pivot_row = a[k].clone();
+        for i in k + 1..n {
+            let f = a[i][k] / pivot_row[k];
+            a[i][k..]
+                .iter_mut()
+                .zip(pivot_row[k..].iter())
+                .for_each(|(x, &y)| *x -= f * y);
+            b[i] -= f * b[k];
+        }
+    }
+    let mut x = vec![0.0; n];
+    for k in (0..n).rev() {
+        let s = (k + 1..n).map(|j| a[k][j] * x[j]).sum::<f64>();
+        x[k] = (b[k] - s) / a[k][k];
+    }
+    Ok(x)
+}
+
+/// Checks that a log-polynomial of `order` is determined by `nfreq` freqs.
+pub fn check_log_poly_order(order: usize, nfreq: usize) -> DbfResult<()> {
+    if nfreq < order + 1 {
+        return Err(DbfError::InvalidValue {
+            what: "order",
+            reason: format!("needs at least {} freqs, found {}", order + 1, nfreq),
+        });
+    }
+    Ok(())
+}
+
+/// Least-squares fit of `ln T = sum_k c_k ln(f / f_ref)^k`, k = 0..=order.
+/// The freqs and temperatures must be positive.
+pub fn fit_log_poly(
+    freqs_hz: &[f64],
+    temps: &[f64],
+    ref_freq_hz: f64,
+    order: usize,
+) -> DbfResult<Vec<f64>> {
+    check_len("temps", freqs_hz.len(), temps.len())?;
+    check_log_poly_order(order, freqs_hz.len())?;
+    let positive =
+        |what, values: &[f64]| match values.iter().find(|&&x| !(x.is_finite() && x > 0.0)) {
+            Some(x) => Err(DbfError::InvalidValue {
+                what,
+                reason: format!("must be positive, found {}", x),
+            }),
+            None => Ok(()),
+        };
+    positive("freqs_hz", freqs_hz)?;
+    positive("temps", temps)?;
+    positive("ref_freq_hz", &[ref_freq_hz])?;
+    let nterms = order + 1;
+    let x: Vec<f64> = freqs_hz.iter().map(|&f| (f / ref_freq_hz).ln()).collect();
+    let y: Vec<f64> = temps.iter().map(|&t| t.ln()).collect();
+    let mut ata = vec![vec![0.0; nterms]; nterms];
+    let mut aty = vec![0.0; nterms];
+    for (&x1, &y1) in x.iter().zip(y.iter()) {
+        let row: Vec<f64> = (0..nterms).map(|k| x1.powi(k as i32)).collect();
+        for i in 0..nterms {
+            for j in 0..nterms {
+                ata[i][j] += row[i] * row[j];
+            }
+            aty[i] += row[i] * y1;
+        }
+    }
+    solve_linear(ata, aty)
+}
+
+pub fn eval_log_poly(coeffs: &[f64], freqs_hz: &[f64], ref_freq_hz: f64) -> Vec<f64> {
+    freqs_hz
+        .iter()
+        .map(|&f| {
+            let x = (f / ref_freq_hz).ln();
+            coeffs
+                .iter()
+                .enumerate()
+                .map(|(k, &c)| c * x.powi(k as i32))
+                .sum::<f64>()
+                .exp()
+        })
+        .collect()
+}
+
+pub fn rms(values: &[f64]) -> f64 {
+    (values.iter().map(|x| x * x).sum::<f64>() / values.len() as f64).sqrt()
+}
+
+/// Antenna temperature of an array observing a power-law sky,
+/// `sky_ref * (f / f_ref)^beta`, at each frequency. The steering phases are
+/// supplied per frequency by `phi_at`.
+#[allow(clippy::too_many_arguments)]
+pub fn simulate_ant_temp_spectrum<F>(
+    sky_ref: &[f64],
+    beta: &[f64],
+    ref_freq_hz: f64,
+    lat_deg: f64,
+    ant_beam: &[f64],
+    x_list: &[f64],
+    y_list: &[f64],
+    z_list: &[f64],
+    w_list: &[f64],
+    phi_at: F,
+    freqs_hz: &[f64],
+) -> DbfResult<Vec<f64>>
+where
+    F: Fn(f64) -> Vec<f64>,
+{
+    freqs_hz
+        .iter()
+        .map(|&f| {
+            let sky: Vec<f64> = sky_ref
+                .iter()
+                .zip(beta.iter())
+                .map(|(&s, &b)| s * (f / ref_freq_hz).powf(b))
+                .collect();
+            let (mean_beam, _weight, _theta) = calc_averaged_array_beam(
+                lat_deg,
+                ant_beam,
+                x_list,
+                y_list,
+                z_list,
+                w_list,
+                &phi_at(f),
+                f,
+            )?;
+            calc_averaged_ant_output(&mean_beam, &sky)
+        })
+        .collect()
+}
@@ -0,0 +1,459 @@
+//! Pixelisations of the sphere other than the HEALPix RING scheme used by the
+//! rest of the crate, so that beams on regular theta/phi grids or in NESTED
+//! order can be evaluated, averaged and converted.
+
+use std::f64::consts::PI;
+
+use scorus::{
+    coordinates::{rotation3d::RotMatrix, SphCoord, Vec3d},
+    healpix::{
+        interp::get_interpol_ring,
+        pix::{pix2ang_ring, pix2ring_ring, ring2z_ring},
+        utils::{nside2npix, nside2nring},
+    },
+};
+
+use crate::{
+    arbitrary_array::array_factor,
+    constants::LIGHT_SPEED,
+    error::{check_array_lists, check_len, DbfError, DbfResult},
+    utils::{check_nside, checked_npix2nside},
+};
+
+pub trait SphericalGrid {
+    fn npix(&self) -> usize;
+
+    /// Centre of pixel `ipix`, as colatitude and az from x.
+    fn pix2ang(&self, ipix: usize) -> SphCoord<f64>;
+
+    fn pix2vec(&self, ipix: usize) -> Vec3d<f64> {
+        Vec3d::from_sph_coord(self.pix2ang(ipix))
+    }
+
+    /// Solid angle of pixel `ipix` in sr.
+    fn pix_area(&self, ipix: usize) -> f64;
+
+    /// Pixels and weights to interpolate a map towards `dir`.
+    fn interp_weights(&self, dir: SphCoord<f64>) -> Vec<(usize, f64)>;
+
+    /// Num of iso-latitude rings.
+    fn nring(&self) -> usize;
+
+    /// Index, from 0 at the north pole, of the ring holding pixel `ipix`.
+    fn pix2ring(&self, ipix: usize) -> usize;
+
+    /// Colatitude of ring `iring`.
+    fn ring_theta(&self, iring: usize) -> f64;
+
+    fn interp(&self, map: &[f64], dir: SphCoord<f64>) -> f64 {
+        self.interp_weights(dir)
+            .iter()
+            .map(|&(p, w)| map[p] * w)
+            .sum()
+    }
+
+    fn check_map(&self, what: &'static str, map: &[f64]) -> DbfResult<()> {
+        check_len(what, self.npix(), map.len())
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct HealpixRing {
+    pub nside: usize,
+}
+
+impl HealpixRing {
+    pub fn new(nside: usize) -> DbfResult<Self> {
+        check_nside(nside)?;
+        Ok(HealpixRing { nside })
+    }
+
+    pub fn from_npix(npix: usize) -> DbfResult<Self> {
+        Ok(HealpixRing {
+            nside: checked_npix2nside(npix)?,
+        })
+    }
+}
+
+impl SphericalGrid for HealpixRing {
+    fn npix(&self) -> usize {
+        nside2npix(self.nside)
+    }
+
+    fn pix2ang(&self, ipix: usize) -> SphCoord<f64> {
+        pix2ang_ring(self.nside, ipix)
+    }
+
+    fn pix_area(&self, _ipix: usize) -> f64 {
+        4.0 * PI / self.npix() as f64
+    }
+
+    fn interp_weights(&self, dir: SphCoord<f64>) -> Vec<(usize, f64)> {
+        let (pix, wgt) = get_interpol_ring(self.nside, dir);
+        pix.iter().cloned().zip(wgt.iter().cloned()).collect()
+    }
+
+    fn nring(&self) -> usize {
+        nside2nring(self.nside)
+    }
+
+    fn pix2ring(&self, ipix: usize) -> usize {
+        pix2ring_ring(self.nside, ipix) - 1
+    }
+
+    fn ring_theta(&self, iring: usize) -> f64 {
+        ring2z_ring::<f64>(self.nside, iring + 1).acos()
+    }
+}
+
+/// HEALPix NESTED scheme, implemented on top of the RING scheme through a
+/// pixel permutation.
+#[derive(Clone, Debug)]
+pub struct HealpixNested {
+    ring: HealpixRing,
+    nest2ring: Vec<usize>,
+    ring2nest: Vec<usize>,
+}
+
+const JRLL: [usize; 12] = [2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4];
+const JPLL: [usize; 12] = [1, 3, 5, 7, 0, 2, 4, 6, 1, 3, 5, 7];
+
+/// Even and odd bits of `v`, deinterleaved.
+fn compress_bits(v: usize) -> (usize, usize) {
+    let (mut x, mut y) = (0, 0);
+    for i in 0..(usize::BITS / 2) {
+        x |= ((v >> (2 * i)) & 1) << i;
+        y |= ((v >> (2 * i + 1)) & 1) << i;
+    }
+    (x, y)
+}
+
+/// Converts a NESTED pixel index to the RING scheme; `nside` must be a power
+/// of 2.
+pub fn nest2ring(nside: usize, ipix: usize) -> usize {
+    let npface = nside * nside;
+    let face = ipix / npface;
+    let (ix, iy) = compress_bits(ipix % npface);
+    let (nside, ix, iy) = (nside as isize, ix as isize, iy as isize);
+    let nl4 = 4 * nside;
+    let npix = 12 * nside * nside;
+    let ncap = 2 * nside * (nside - 1);
+
+    let jr = JRLL[face] as isize * nside - ix - iy - 1;
+    let (nr, n_before, kshift) = if jr < nside {
+        (jr, 2 * jr * (jr - 1), 0)
+    } else if jr > 3 * nside {
+        let nr = nl4 - jr;
+        (nr, npix - 2 * (nr + 1) * nr, 0)
+    } else {
+        (nside, ncap + (jr - nside) * nl4, (jr - nside) & 1)
+    };
+
+    let mut jp = (JPLL[face] as isize * nr + ix - iy + 1 + kshift) / 2;
+    if jp > nl4 {
+        jp -= nl4;
+    } else if jp < 1 {
+        jp += nl4;
+    }
+    (n_before + jp - 1) as usize
+}
+
+impl HealpixNested {
+    pub fn new(nside: usize) -> DbfResult<Self> {
+        let ring = HealpixRing::new(nside)?;
+        if !nside.is_power_of_two() {
+            return Err(DbfError::InvalidValue {
+                what: "nside",
+                reason: format!(
+                    "must be a power of 2 for the NESTED scheme, found {}",
+                    nside
+                ),
+            });
+        }
+        let npix = ring.npix();
+        let nest2ring: Vec<usize> = (0..npix).map(|i| nest2ring(nside, i)).collect();
+        let mut ring2nest = vec![0; npix];
+        nest2ring
+            .iter()
+            .enumerate()
+            .for_each(|(n, &r)| ring2nest[r] = n);
+        Ok(HealpixNested {
+            ring,
+            nest2ring,
+            ring2nest,
+        })
+    }
+
+    pub fn from_npix(npix: usize) -> DbfResult<Self> {
+        HealpixNested::new(checked_npix2nside(npix)?)
+    }
+
+    pub fn nside(&self) -> usize {
+        self.ring.nside
+    }
+
+    /// Reorders a RING map to NESTED.
+    pub fn map_ring2nest(&self, map: &[f64]) -> DbfResult<Vec<f64>> {
+        self.check_map("map", map)?;
+        Ok(self.nest2ring.iter().map(|&r| map[r]).collect())
+    }
+
+    /// Reorders a NESTED map to RING.
+    pub fn map_nest2ring(&self, map: &[f64]) -> DbfResult<Vec<f64>> {
+        self.check_map("map", map)?;
+        Ok(self.ring2nest.iter().map(|&n| map[n]).collect())
+    }
+}
+
+impl SphericalGrid for HealpixNested {
+    fn npix(&self) -> usize {
+        self.ring.npix()
+    }
+
+    fn pix2ang(&self, ipix: usize) -> SphCoord<f64> {
+        self.ring.pix2ang(self.nest2ring[ipix])
+    }
+
+    fn pix_area(&self, _ipix: usize) -> f64 {
+        4.0 * PI / self.npix() as f64
+    }
+
+    fn interp_weights(&self, dir: SphCoord<f64>) -> Vec<(usize, f64)> {
+        self.ring
+            .interp_weights(dir)
+            .into_iter()
+            .map(|(p, w)| (self.ring2nest[p], w))
+            .collect()
+    }
+
+    fn nring(&self) -> usize {
+        self.ring.nring()
+    }
+
+    fn pix2ring(&self, ipix: usize) -> usize {
+        self.ring.pix2ring(self.nest2ring[ipix])
+    }
+
+    fn ring_theta(&self, iring: usize) -> f64 {
+        self.ring.ring_theta(iring)
+    }
+}
+
+/// Regular theta/phi grid as written by most beam tools: `ntheta` rows from
+/// theta = 0 to 180 deg inclusive, each with `nphi` columns from phi = 0
+/// (x axis) in steps of 360 deg / nphi. Pixel `it * nphi + ip` is at row
+/// `it`, column `ip`.
+#[derive(Clone, Copy, Debug)]
+pub struct Equirect {
+    pub ntheta: usize,
+    pub nphi: usize,
+}
+
+impl Equirect {
+    pub fn new(ntheta: usize, nphi: usize) -> DbfResult<Self> {
+        if ntheta < 2 || nphi < 1 {
+            return Err(DbfError::InvalidShape {
+                what: "equirect grid",
+                shape: vec![ntheta, nphi],
+                reason: "needs at least 2 theta rows and 1 phi column",
+            });
+        }
+        Ok(Equirect { ntheta, nphi })
+    }
+
+    pub fn dtheta(&self) -> f64 {
+        PI / (self.ntheta - 1) as f64
+    }
+
+    pub fn dphi(&self) -> f64 {
+        2.0 * PI / self.nphi as f64
+    }
+}
+
+impl SphericalGrid for Equirect {
+    fn npix(&self) -> usize {
+        self.ntheta * self.nphi
+    }
+
+    fn pix2ang(&self, ipix: usize) -> SphCoord<f64> {
+        let (it, ip) = (ipix / self.nphi, ipix % self.nphi);
+        SphCoord::new(it as f64 * self.dtheta(), ip as f64 * self.dphi())
+    }
+
+    fn pix_area(&self, ipix: usize) -> f64 {
+        let theta = self.ring_theta(ipix / self.nphi);
+        let lo = (theta - self.dtheta() / 2.0).max(0.0);
+        let hi = (theta + self.dtheta() / 2.0).min(PI);
+        (lo.cos() - hi.cos()) * self.dphi()
+    }
+
+    fn interp_weights(&self, dir: SphCoord<f64>) -> Vec<(usize, f64)> {
+        let t = (dir.pol / self.dtheta()).clamp(0.0, (self.ntheta - 1) as f64);
+        let it0 = (t.floor() as usize).min(self.ntheta - 2);
+        let ft = t - it0 as f64;
+        let p = dir.az.rem_euclid(2.0 * PI) / self.dphi();
+        let ip0 = (p.floor() as usize) % self.nphi;
+        let ip1 = (ip0 + 1) % self.nphi;
+        let fp = p - p.floor();
+        let pix = |it: usize, ip: usize| it * self.nphi + ip;
+        vec![
+            (pix(it0, ip0), (1.0 - ft) * (1.0 - fp)),
+            (pix(it0, ip1), (1.0 - ft) * fp),
+            (pix(it0 + 1, ip0), ft * (1.0 - fp)),
+            (pix(it0 + 1, ip1), ft * fp),
+        ]
+    }
+
+    fn nring(&self) -> usize {
+        self.ntheta
+    }
+
+    fn pix2ring(&self, ipix: usize) -> usize {
+        ipix / self.nphi
+    }
+
+    fn ring_theta(&self, iring: usize) -> f64 {
+        iring as f64 * self.dtheta()
+    }
+}
+
+/// Resamples a map from one grid to another by interpolation.
+pub fn regrid<S: SphericalGrid, D: SphericalGrid>(
+    src: &S,
+    map: &[f64],
+    dst: &D,
+) -> DbfResult<Vec<f64>> {
+    src.check_map("map", map)?;
+    Ok((0..dst.npix())
+        .map(|i| src.interp(map, dst.pix2ang(i)))
+        .collect())
+}
+
+/// Rotated map, the value towards `d` being that of `map` towards `rot * d`.
+pub fn rotate<G: SphericalGrid>(
+    grid: &G,
+    map: &[f64],
+    rot: &RotMatrix<f64>,
+) -> DbfResult<Vec<f64>> {
+    grid.check_map("map", map)?;
+    Ok((0..grid.npix())
+        .map(|i| grid.interp(map, SphCoord::from_vec3d(rot * grid.pix2vec(i))))
+        .collect())
+}
+
+/// `calc_array_beam` evaluated on an arbitrary grid; with `ground_cut` the
+/// beam is zero below the horizon.
+#[allow(clippy::too_many_arguments)]
+pub fn calc_array_beam<G: SphericalGrid>(
+    grid: &G,
+    x_list: &[f64],
+    y_list: &[f64],
+    z_list: &[f64],
+    w_list: &[f64],
+    phi_list: &[f64],
+    freq_hz: f64,
+    ground_cut: bool,
+) -> DbfResult<Vec<f64>> {
+    check_array_lists(&[
+        ("x_list", x_list),
+        ("y_list", y_list),
+        ("z_list", z_list),
+        ("w_list", w_list),
+        ("phi_list", phi_list),
+    ])?;
+    let lambda = LIGHT_SPEED / freq_hz;
+    Ok((0..grid.npix())
+        .map(|i| {
+            let pointing = grid.pix2vec(i);
+            if ground_cut && pointing[2] < 0.0 {
+                0.0
+            } else {
+                array_factor(&pointing, x_list, y_list, z_list, w_list, phi_list, lambda)
+            }
+        })
+        .collect())
+}
+
+/// Area weighted azimuthal mean per ring, the pixel count per ring and the
+/// ring colatitudes, as `utils::integrate_az` for any grid.
+pub fn integrate_az<G: SphericalGrid>(
+    grid: &G,
+    map: &[f64],
+) -> DbfResult<(Vec<f64>, Vec<usize>, Vec<f64>)> {
+    grid.check_map("map", map)?;
+    let nring = grid.nring();
+    let mut count = vec![0_usize; nring];
+    let mut area = vec![0.0; nring];
+    let mut mean = vec![0.0; nring];
+    for (i, &x) in map.iter().enumerate() {
+        let iring = grid.pix2ring(i);
+        let a = grid.pix_area(i);
+        count[iring] += 1;
+        area[iring] += a;
+        mean[iring] += x * a;
+    }
+    mean.iter_mut().zip(area.iter()).for_each(|(m, &a)| *m /= a);
+    let theta = (0..nring).map(|i| grid.ring_theta(i)).collect();
+    Ok((mean, count, theta))
+}
+
+/// Beam-weighted mean of the sky with both maps on `grid`, weighting every
+/// pixel by its solid angle.
+pub fn calc_ant_output<G: SphericalGrid>(grid: &G, beam: &[f64], sky: &[f64]) -> DbfResult<f64> {
+    grid.check_map("beam", beam)?;
+    grid.check_map("sky", sky)?;
+    let (mut num, mut norm) = (0.0, 0.0);
+    for (i, (&b, &s)) in beam.iter().zip(sky.iter()).enumerate() {
+        let a = grid.pix_area(i);
+        num += b * s * a;
+        norm += b * a;
+    }
+    Ok(num / norm)
+}
+
+/// `arbitrary_array::average_beam_over_lst` for any grid.
+pub fn average_beam_over_lst<G: SphericalGrid>(
+    grid: &G,
+    lat_deg: f64,
+    total_beam: &[f64],
+) -> DbfResult<(Vec<f64>, Vec<usize>, Vec<f64>)> {
+    let rot =
+        RotMatrix::about_axis_by_angle(&Vec3d::new(0.0, 1.0, 0.0), (90.0 - lat_deg).to_radians())
+            * RotMatrix::about_axis_by_angle(&Vec3d::new(0.0, 0.0, 1.0), -90_f64.to_radians());
+    integrate_az(grid, &rotate(grid, total_beam, &rot)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nest2ring_nside2() {
+        // healpy.nest2ring(2, range(48))
+        let expected = [
+            13, 5, 4, 0, 15, 7, 6, 1, 17, 9, 8, 2, 19, 11, 10, 3, 28, 20, 27, 12, 30, 22, 21, 14,
+            32, 24, 23, 16, 34, 26, 25, 18, 44, 37, 36, 29, 45, 39, 38, 31, 46, 41, 40, 33, 47, 43,
+            42, 35,
+        ];
+        let found: Vec<usize> = (0..48).map(|i| nest2ring(2, i)).collect();
+        assert_eq!(found, expected);
+    }
+
+    #[test]
+    fn nest2ring_is_bijection() {
+        for nside in [1, 2, 4, 8, 16] {
+            let npix = 12 * nside * nside;
+            let mut seen = vec![false; npix];
+            for i in 0..npix {
+                let r = nest2ring(nside, i);
+                assert!(
+                    r < npix && !seen[r],
+                    "nside {}: pixel {} hit twice",
+                    nside,
+                    r
+                );
+                seen[r] = true;
+            }
+        }
+    }
+}
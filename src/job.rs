@@ -0,0 +1,185 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    constants::LIGHT_SPEED,
+    error::DbfResult,
+    quantize::check_bits,
+    regular_array::{opt::StopRule, solver::Solver},
+    target::TargetBeam,
+};
+
+/// A full simulation run: element beam -> wgt -> (optimized wgt) -> array beam,
+/// repeated for every freq.
+///
+/// ```yaml
+/// name: gauss10
+/// site: {lat_deg: 42.0}
+/// array: {size: 16, spacing_lambda: 0.5}
+/// element: {nec: ant.nec}
+/// target: {gaussian: {sigma_deg: 10.0}}
+/// freqs: {fmin_mhz: 50.0, fmax_mhz: 100.0, nfreq: 6}
+/// nside: 64
+/// sky: sky.fits
+/// optimize: {npart: 64, max_time_s: 600.0, log: true}
+/// output: {dir: out/gauss10, products: [wgt, beam, ant_temp]}
+/// ```
+#[derive(Clone, Serialize, Deserialize)]
+pub struct JobCfg {
+    pub name: String,
+    pub site: SiteCfg,
+    pub array: RegularArrayCfg,
+    /// isotropic elements if not given
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub element: Option<ElementCfg>,
+    pub target: TargetCfg,
+    pub freqs: FreqCfg,
+    pub nside: usize,
+    /// sky healpix file, used at all freqs; needed by `optimize` and `ant_temp`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sky: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub optimize: Option<OptimizeCfg>,
+    pub output: OutputCfg,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SiteCfg {
+    pub lat_deg: f64,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct RegularArrayCfg {
+    /// num of elements along each side
+    pub size: isize,
+    /// element spacing in metre, takes precedence over `spacing_lambda`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub spacing_m: Option<f64>,
+    /// element spacing in wavelengths at each simulated freq
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub spacing_lambda: Option<f64>,
+}
+
+impl RegularArrayCfg {
+    pub fn spacing(&self, freq_hz: f64) -> Option<f64> {
+        self.spacing_m
+            .or_else(|| self.spacing_lambda.map(|x| x * LIGHT_SPEED / freq_hz))
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ElementCfg {
+    /// NEC model, simulated at every freq
+    Nec(String),
+    /// healpix beam used at all freqs
+    Healpix(String),
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TargetCfg {
+    Gaussian {
+        sigma_deg: f64,
+    },
+    Healpix(String),
+    /// e.g. `{analytic: {shape: {airy: {fwhm_deg: 20.0}}, ref_freq_mhz: 75.0}}`
+    Analytic(TargetBeam),
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum FreqCfg {
+    List {
+        freqs_mhz: Vec<f64>,
+    },
+    Range {
+        fmin_mhz: f64,
+        fmax_mhz: f64,
+        nfreq: usize,
+    },
+}
+
+impl FreqCfg {
+    pub fn freqs_hz(&self) -> Vec<f64> {
+        match self {
+            FreqCfg::List { freqs_mhz } => freqs_mhz.iter().map(|f| f * 1e6).collect(),
+            &FreqCfg::Range {
+                fmin_mhz,
+                fmax_mhz,
+                nfreq,
+            } => (0..nfreq)
+                .map(|i| {
+                    if nfreq > 1 {
+                        (fmin_mhz + (fmax_mhz - fmin_mhz) * i as f64 / (nfreq - 1) as f64) * 1e6
+                    } else {
+                        fmin_mhz * 1e6
+                    }
+                })
+                .collect(),
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct OptimizeCfg {
+    #[serde(default = "default_npart")]
+    pub npart: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bits: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+    /// e.g. `{de: {f: 0.7, cr: 0.9}}`, pso with its default coefficients if
+    /// not given
+    #[serde(default)]
+    pub solver: Solver,
+    /// max_iter, max_time_s and target_fitness, on top of the solver convergence
+    #[serde(flatten)]
+    pub stop: StopRule,
+    /// write the progress at every freq to `opt_log_{freq}MHz.csv` in the
+    /// output dir
+    #[serde(default)]
+    pub log: bool,
+}
+
+impl OptimizeCfg {
+    pub fn check(&self) -> DbfResult<()> {
+        self.bits.map(check_bits).transpose()?;
+        self.solver.check()
+    }
+}
+
+fn default_npart() -> usize {
+    64
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Product {
+    AntBeam,
+    TargetBeam,
+    Wgt,
+    Beam,
+    AntTemp,
+}
+
+impl Product {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Product::AntBeam => "ant_beam",
+            Product::TargetBeam => "target_beam",
+            Product::Wgt => "wgt",
+            Product::Beam => "beam",
+            Product::AntTemp => "ant_temp",
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct OutputCfg {
+    pub dir: String,
+    pub products: Vec<Product>,
+    /// HDF5 file in `dir` holding all products and the job instead of one
+    /// file per product and freq; needs the `hdf5` feature
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hdf5: Option<String>,
+}
@@ -1,11 +1,34 @@
 #![allow(non_snake_case)]
 
+pub mod adaptive;
 pub mod arbitrary_array;
 pub mod array_cfg;
+#[cfg(not(target_family = "wasm"))]
+pub mod capi;
+#[cfg(not(target_family = "wasm"))]
+pub mod cli;
 pub mod constants;
+pub mod cut;
+pub mod error;
 pub mod fft;
+pub mod foreground;
+pub mod grid;
+pub mod job;
+pub mod metrics;
+pub mod multibeam;
+#[cfg(feature = "python")]
+mod python;
+pub mod projection;
+pub mod quantize;
 pub mod regular_array;
+pub mod render;
+pub mod sht;
+pub mod steering;
+pub mod target;
+pub mod tolerance;
 pub mod utils;
+#[cfg(target_family = "wasm")]
+mod wasm;
 
 //pub use regular_array::*;
 
@@ -17,7 +17,9 @@ use dbf_beam_simulator::{
 
 pub fn main() {
     let ant_cfg=AntCfg{
-        pos:(0.0, 0.0, 0.0)
+        pos:(0.0, 0.0, 0.0),
+        wgt:None,
+        delay:None
     };
     let array_cfg=ArrayCfg{
         ants:vec![ant_cfg.clone(), ant_cfg.clone()]
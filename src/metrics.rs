@@ -0,0 +1,76 @@
+use std::f64::consts::PI;
+
+use scorus::{
+    coordinates::{SphCoord, Vec3d},
+    healpix::pix::{pix2ang_ring, pix2vec_ring},
+};
+
+use crate::{error::DbfResult, utils::checked_npix2nside};
+
+#[derive(Clone, Copy, Debug)]
+pub struct BeamMetrics {
+    pub peak: f64,
+    pub peak_dir: SphCoord<f64>,
+    /// radius of the first null around the peak, in rad
+    pub main_lobe_radius: f64,
+    pub sidelobe_level_db: f64,
+    /// beam solid angle normalised to the peak, in sr
+    pub solid_angle: f64,
+}
+
+pub fn angular_distance(a: &SphCoord<f64>, b: &SphCoord<f64>) -> f64 {
+    let va = Vec3d::from_sph_coord(*a);
+    let vb = Vec3d::from_sph_coord(*b);
+    (va[0] * vb[0] + va[1] * vb[1] + va[2] * vb[2])
+        .clamp(-1.0, 1.0)
+        .acos()
+}
+
+/// The main lobe is delimited by the first minimum of the largest beam value
+/// found in rings of one pixel width around the peak; the sidelobe level is
+/// the largest value outside of it.
+pub fn calc_beam_metrics(beam: &[f64]) -> DbfResult<BeamMetrics> {
+    let npix = beam.len();
+    let nside = checked_npix2nside(npix)?;
+    let (ipeak, peak) = beam
+        .iter()
+        .cloned()
+        .enumerate()
+        .fold((0, f64::MIN), |a, b| if b.1 > a.1 { b } else { a });
+    let peak_vec = pix2vec_ring::<f64>(nside, ipeak);
+
+    let dtheta = (4.0 * PI / npix as f64).sqrt();
+    let nbins = (PI / dtheta).ceil() as usize + 1;
+    let dist: Vec<f64> = (0..npix)
+        .map(|i| {
+            let v = pix2vec_ring::<f64>(nside, i);
+            (v[0] * peak_vec[0] + v[1] * peak_vec[1] + v[2] * peak_vec[2])
+                .clamp(-1.0, 1.0)
+                .acos()
+        })
+        .collect();
+    let mut bin_max = vec![0.0_f64; nbins];
+    dist.iter().zip(beam.iter()).for_each(|(&d, &b)| {
+        let k = (d / dtheta) as usize;
+        bin_max[k] = bin_max[k].max(b);
+    });
+    let first_null = (1..nbins - 1)
+        .find(|&k| bin_max[k + 1] > bin_max[k])
+        .unwrap_or(nbins - 1);
+    let main_lobe_radius = (first_null + 1) as f64 * dtheta;
+
+    let sidelobe = dist
+        .iter()
+        .zip(beam.iter())
+        .filter(|(&d, _)| d > main_lobe_radius)
+        .map(|(_, &b)| b)
+        .fold(0.0, f64::max);
+
+    Ok(BeamMetrics {
+        peak,
+        peak_dir: pix2ang_ring::<f64>(nside, ipeak),
+        main_lobe_radius,
+        sidelobe_level_db: 10.0 * (sidelobe / peak).log10(),
+        solid_angle: beam.iter().sum::<f64>() / peak * 4.0 * PI / npix as f64,
+    })
+}
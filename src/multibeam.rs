@@ -0,0 +1,207 @@
+use std::f64::consts::PI;
+
+use ndarray::Array2;
+
+use num::complex::Complex;
+
+use serde::{Deserialize, Serialize};
+
+use scorus::healpix::{pix::pix2vec_ring, utils::nside2npix};
+
+use crate::{
+    arbitrary_array::calc_phase_from_pointing,
+    constants::LIGHT_SPEED,
+    error::{check_array_lists, check_len, DbfError, DbfResult},
+};
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct BeamCfg {
+    pub az_deg: f64,
+    pub zenith_deg: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wgt: Option<Vec<f64>>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct MultiBeamCfg {
+    pub beams: Vec<BeamCfg>,
+}
+
+#[derive(Clone)]
+pub struct WeightSet {
+    pub w_list: Vec<f64>,
+    pub phi_list: Vec<f64>,
+}
+
+fn check_nbeams(nbeams: usize) -> DbfResult<()> {
+    if nbeams == 0 {
+        return Err(DbfError::InvalidValue {
+            what: "beams",
+            reason: "at least one beam is needed".to_string(),
+        });
+    }
+    Ok(())
+}
+
+impl MultiBeamCfg {
+    pub fn check(&self) -> DbfResult<()> {
+        check_nbeams(self.beams.len())
+    }
+
+    /// Beams without their own `wgt` use `w_list`, the element weights of
+    /// the array cfg.
+    pub fn weight_sets(
+        &self,
+        x_list: &[f64],
+        y_list: &[f64],
+        z_list: &[f64],
+        w_list: &[f64],
+        freq_hz: f64,
+    ) -> DbfResult<Vec<WeightSet>> {
+        self.check()?;
+        check_array_lists(&[
+            ("x_list", x_list),
+            ("y_list", y_list),
+            ("z_list", z_list),
+            ("w_list", w_list),
+        ])?;
+        self.beams
+            .iter()
+            .map(|b| {
+                let w_list = b.wgt.clone().unwrap_or_else(|| w_list.to_vec());
+                check_len("beam wgt", x_list.len(), w_list.len())?;
+                let phi_list = calc_phase_from_pointing(
+                    x_list,
+                    y_list,
+                    z_list,
+                    b.az_deg.to_radians(),
+                    b.zenith_deg.to_radians(),
+                    freq_hz,
+                )?;
+                Ok(WeightSet { w_list, phi_list })
+            })
+            .collect()
+    }
+}
+
+/// Evaluates all beams in one pass over the sky, so that the geometric
+/// phase of every element is computed only once per pixel.
+pub fn calc_multi_array_beam(
+    nside: usize,
+    x_list: &[f64],
+    y_list: &[f64],
+    z_list: &[f64],
+    weight_sets: &[WeightSet],
+    freq_hz: f64,
+    ground_cut: bool,
+) -> DbfResult<Vec<Vec<f64>>> {
+    check_array_lists(&[("x_list", x_list), ("y_list", y_list), ("z_list", z_list)])?;
+    for ws in weight_sets {
+        check_len("w_list", x_list.len(), ws.w_list.len())?;
+        check_len("phi_list", x_list.len(), ws.phi_list.len())?;
+    }
+    let npix = nside2npix(nside);
+    let lambda = LIGHT_SPEED / freq_hz;
+    let coeffs: Vec<Vec<Complex<f64>>> = weight_sets
+        .iter()
+        .map(|ws| {
+            ws.w_list
+                .iter()
+                .zip(ws.phi_list.iter())
+                .map(|(&w, &phi)| Complex::from_polar(w, -phi))
+                .collect()
+        })
+        .collect();
+
+    let mut result = vec![vec![0.0; npix]; weight_sets.len()];
+    let mut geo = vec![Complex::<f64>::new(0.0, 0.0); x_list.len()];
+    for i in 0..npix {
+        if i >= npix / 2 && ground_cut {
+            continue;
+        }
+        let pointing = pix2vec_ring::<f64>(nside, i);
+        geo.iter_mut()
+            .zip(x_list.iter().zip(y_list.iter().zip(z_list.iter())))
+            .for_each(|(g, (&x, (&y, &z)))| {
+                let dl = pointing[0] * x + pointing[1] * y + pointing[2] * z;
+                *g = Complex::from_polar(1.0, dl / lambda * 2.0 * PI);
+            });
+        for (beam, c) in result.iter_mut().zip(coeffs.iter()) {
+            beam[i] = c
+                .iter()
+                .zip(geo.iter())
+                .map(|(&c1, &g1)| c1 * g1)
+                .sum::<Complex<f64>>()
+                .norm_sqr();
+        }
+    }
+    Ok(result)
+}
+
+fn peak(beam: &[f64]) -> f64 {
+    beam.iter().cloned().fold(f64::MIN, f64::max)
+}
+
+/// Normalised inner product between every pair of beams.
+pub fn beam_overlap(beams: &[Vec<f64>]) -> Array2<f64> {
+    let n = beams.len();
+    let norms: Vec<f64> = beams
+        .iter()
+        .map(|b| b.iter().map(|x| x * x).sum::<f64>().sqrt())
+        .collect();
+    Array2::from_shape_fn((n, n), |(i, j)| {
+        beams[i]
+            .iter()
+            .zip(beams[j].iter())
+            .map(|(&a, &b)| a * b)
+            .sum::<f64>()
+            / (norms[i] * norms[j])
+    })
+}
+
+/// Level in dB, relative to the peaks, at which each pair of beams crosses.
+pub fn crossover_level(beams: &[Vec<f64>]) -> Array2<f64> {
+    let n = beams.len();
+    let peaks: Vec<f64> = beams.iter().map(|b| peak(b)).collect();
+    Array2::from_shape_fn((n, n), |(i, j)| {
+        let x = beams[i]
+            .iter()
+            .zip(beams[j].iter())
+            .map(|(&a, &b)| (a / peaks[i]).min(b / peaks[j]))
+            .fold(0.0, f64::max);
+        10.0 * x.log10()
+    })
+}
+
+/// Fraction of the upper hemisphere in which at least one beam is above
+/// `threshold_db` relative to its own peak.
+/// All beams must have the same length and a positive peak.
+pub fn tiling_coverage(beams: &[Vec<f64>], threshold_db: f64) -> DbfResult<f64> {
+    check_nbeams(beams.len())?;
+    let npix = beams[0].len();
+    let threshold = 10_f64.powf(threshold_db / 10.0);
+    let peaks = beams
+        .iter()
+        .map(|b| {
+            check_len("beam", npix, b.len())?;
+            let p = peak(b);
+            if p > 0.0 {
+                Ok(p)
+            } else {
+                Err(DbfError::InvalidValue {
+                    what: "beam",
+                    reason: format!("peak must be positive, found {}", p),
+                })
+            }
+        })
+        .collect::<DbfResult<Vec<f64>>>()?;
+    let covered = (0..npix / 2)
+        .filter(|&i| {
+            beams
+                .iter()
+                .zip(peaks.iter())
+                .any(|(b, &p)| b[i] / p >= threshold)
+        })
+        .count();
+    Ok(covered as f64 / (npix / 2) as f64)
+}
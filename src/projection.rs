@@ -0,0 +1,104 @@
+use std::f64::consts::{FRAC_PI_2, PI, SQRT_2};
+
+use serde::{Deserialize, Serialize};
+
+use scorus::{coordinates::SphCoord, healpix::interp::get_interpol_ring};
+
+use crate::{
+    error::{DbfError, DbfResult},
+    utils::checked_npix2nside,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Projection {
+    /// full sky, centred on az = 0 with az increasing to the left as in healpy
+    Mollweide,
+    /// upper hemisphere seen from above, zenith at the centre, x (east) to
+    /// the right and y (north) up
+    Orthographic,
+    /// rectangular grid, az from north increasing to the right over the full
+    /// width and elevation from -90 deg at the bottom to 90 deg at the top
+    AzEl,
+}
+
+impl Projection {
+    pub fn from_name(name: &str) -> DbfResult<Projection> {
+        match name.to_lowercase().as_str() {
+            "mollweide" | "moll" => Ok(Projection::Mollweide),
+            "orthographic" | "ortho" => Ok(Projection::Orthographic),
+            "azel" | "az-el" => Ok(Projection::AzEl),
+            _ => Err(DbfError::InvalidValue {
+                what: "projection",
+                reason: format!("unknown projection '{}'", name),
+            }),
+        }
+    }
+
+    /// Direction seen at the raster position `(u, v)`, both in [-1, 1] with
+    /// v pointing up, or None outside the projected sphere.
+    pub fn inverse(&self, u: f64, v: f64) -> Option<SphCoord<f64>> {
+        match self {
+            Projection::Mollweide => {
+                if u * u + v * v > 1.0 {
+                    return None;
+                }
+                let (x, y) = (2.0 * SQRT_2 * u, SQRT_2 * v);
+                let aux = (y / SQRT_2).asin();
+                let lat = ((2.0 * aux + (2.0 * aux).sin()) / PI)
+                    .clamp(-1.0, 1.0)
+                    .asin();
+                let lon = -PI * x / (2.0 * SQRT_2 * aux.cos());
+                Some(SphCoord::new(FRAC_PI_2 - lat, lon.rem_euclid(2.0 * PI)))
+            }
+            Projection::Orthographic => {
+                let r2 = u * u + v * v;
+                if r2 > 1.0 {
+                    return None;
+                }
+                Some(SphCoord::from_xyz(u, v, (1.0 - r2).sqrt()))
+            }
+            Projection::AzEl => {
+                let az_from_north = (u + 1.0) * PI;
+                let el = v * FRAC_PI_2;
+                Some(SphCoord::new(
+                    FRAC_PI_2 - el,
+                    (FRAC_PI_2 - az_from_north).rem_euclid(2.0 * PI),
+                ))
+            }
+        }
+    }
+}
+
+/// Directions seen at the centres of a `width` x `height` raster, row by row
+/// from the top.
+pub fn raster_dirs(proj: Projection, width: usize, height: usize) -> Vec<Option<SphCoord<f64>>> {
+    let mut dirs = Vec::with_capacity(width * height);
+    for j in 0..height {
+        let v = 1.0 - 2.0 * (j as f64 + 0.5) / height as f64;
+        for i in 0..width {
+            let u = 2.0 * (i as f64 + 0.5) / width as f64 - 1.0;
+            dirs.push(proj.inverse(u, v));
+        }
+    }
+    dirs
+}
+
+/// Value of a healpix map towards `dir`, bilinearly interpolated.
+pub fn sample(map: &[f64], nside: usize, dir: SphCoord<f64>) -> f64 {
+    let (pix, wgt) = get_interpol_ring(nside, dir);
+    pix.iter().zip(wgt.iter()).map(|(&p, &w)| map[p] * w).sum()
+}
+
+/// Samples a healpix map on a `width` x `height` raster, row by row from the
+/// top; pixels outside the projected sphere are NaN.
+pub fn project(map: &[f64], proj: Projection, width: usize, height: usize) -> DbfResult<Vec<f64>> {
+    let nside = checked_npix2nside(map.len())?;
+    Ok(raster_dirs(proj, width, height)
+        .into_iter()
+        .map(|dir| match dir {
+            Some(dir) => sample(map, nside, dir),
+            None => f64::NAN,
+        })
+        .collect())
+}
@@ -0,0 +1,199 @@
+use ndarray::Array2;
+
+use numpy::{IntoPyArray, PyArray1, PyArray2, PyReadonlyArray1, PyReadonlyArray2};
+
+use pyo3::{exceptions::PyValueError, prelude::*};
+
+use rand::{rngs::StdRng, thread_rng, Rng, SeedableRng};
+
+use crate::{
+    arbitrary_array,
+    error::{DbfError, DbfResult},
+    quantize::check_bits,
+    regular_array::{
+        self, deflattern_quarter_wgt, full2quarter,
+        opt::{optimize_wgt, QuarterWgtObjective, StopRule},
+        quarter2full,
+        solver::Solver,
+    },
+    utils::{self, checked_npix2nside},
+};
+
+impl From<DbfError> for PyErr {
+    fn from(e: DbfError) -> Self {
+        PyValueError::new_err(e.to_string())
+    }
+}
+
+type Profile<'py> = (
+    Bound<'py, PyArray1<f64>>,
+    Bound<'py, PyArray1<usize>>,
+    Bound<'py, PyArray1<f64>>,
+);
+
+fn profile_into_py(
+    py: Python<'_>,
+    (mean, weight, theta): (Vec<f64>, Vec<usize>, Vec<f64>),
+) -> Profile<'_> {
+    (
+        mean.into_pyarray(py),
+        weight.into_pyarray(py),
+        theta.into_pyarray(py),
+    )
+}
+
+#[pyfunction]
+#[pyo3(signature = (nside, x, y, z, w, phi, freq_hz, ground_cut = true))]
+#[allow(clippy::too_many_arguments)]
+fn calc_array_beam<'py>(
+    py: Python<'py>,
+    nside: usize,
+    x: PyReadonlyArray1<'py, f64>,
+    y: PyReadonlyArray1<'py, f64>,
+    z: PyReadonlyArray1<'py, f64>,
+    w: PyReadonlyArray1<'py, f64>,
+    phi: PyReadonlyArray1<'py, f64>,
+    freq_hz: f64,
+    ground_cut: bool,
+) -> PyResult<Bound<'py, PyArray1<f64>>> {
+    let (x, y, z, w, phi) = (
+        x.as_slice()?,
+        y.as_slice()?,
+        z.as_slice()?,
+        w.as_slice()?,
+        phi.as_slice()?,
+    );
+    let beam = py
+        .detach(|| arbitrary_array::calc_array_beam(nside, x, y, z, w, phi, freq_hz, ground_cut))?;
+    Ok(beam.into_pyarray(py))
+}
+
+/// Returns the LST-averaged beam per ring, the num of pixels per ring and
+/// the ring colatitudes.
+#[pyfunction]
+#[allow(clippy::too_many_arguments)]
+fn calc_averaged_array_beam<'py>(
+    py: Python<'py>,
+    lat_deg: f64,
+    ant_beam: PyReadonlyArray1<'py, f64>,
+    x: PyReadonlyArray1<'py, f64>,
+    y: PyReadonlyArray1<'py, f64>,
+    z: PyReadonlyArray1<'py, f64>,
+    w: PyReadonlyArray1<'py, f64>,
+    phi: PyReadonlyArray1<'py, f64>,
+    freq_hz: f64,
+) -> PyResult<Profile<'py>> {
+    let (ant_beam, x, y, z, w, phi) = (
+        ant_beam.as_slice()?,
+        x.as_slice()?,
+        y.as_slice()?,
+        z.as_slice()?,
+        w.as_slice()?,
+        phi.as_slice()?,
+    );
+    let profile = py.detach(|| {
+        arbitrary_array::calc_averaged_array_beam(lat_deg, ant_beam, x, y, z, w, phi, freq_hz)
+    })?;
+    Ok(profile_into_py(py, profile))
+}
+
+#[pyfunction]
+fn pattern2wgt<'py>(
+    py: Python<'py>,
+    hp: PyReadonlyArray1<'py, f64>,
+    d: f64,
+    freq_mhz: f64,
+    n: isize,
+) -> PyResult<Bound<'py, PyArray2<f64>>> {
+    let hp = hp.as_slice()?;
+    let wgt = py.detach(|| regular_array::pattern2wgt(hp, d, freq_mhz, n))?;
+    Ok(wgt.into_pyarray(py))
+}
+
+#[pyfunction]
+fn wgt2pattern<'py>(
+    py: Python<'py>,
+    wgt: PyReadonlyArray2<'py, f64>,
+    d: f64,
+    freq_mhz: f64,
+    nside: usize,
+) -> PyResult<Bound<'py, PyArray1<f64>>> {
+    let wgt = wgt.as_array();
+    let beam = py.detach(|| regular_array::wgt2pattern(wgt, d, freq_mhz, nside))?;
+    Ok(beam.into_pyarray(py))
+}
+
+#[pyfunction]
+fn quarter_wgt2pattern<'py>(
+    py: Python<'py>,
+    quarter_wgt: PyReadonlyArray2<'py, f64>,
+    d: f64,
+    freq_mhz: f64,
+    nside: usize,
+) -> PyResult<Bound<'py, PyArray1<f64>>> {
+    let quarter_wgt = quarter_wgt.as_array();
+    let beam = py.detach(|| regular_array::quarter_wgt2pattern(quarter_wgt, d, freq_mhz, nside))?;
+    Ok(beam.into_pyarray(py))
+}
+
+#[pyfunction]
+fn integrate_az<'py>(py: Python<'py>, hmap: PyReadonlyArray1<'py, f64>) -> PyResult<Profile<'py>> {
+    let profile = utils::integrate_az(hmap.as_slice()?)?;
+    Ok(profile_into_py(py, profile))
+}
+
+/// PSO optimisation of four-fold symmetric regular array weights towards
+/// `target_beam`, starting from the full weight grid `wgt0`.
+#[pyfunction]
+#[pyo3(signature = (ant_beam, target_beam, wgt0, d, freq_mhz, npart = 64, bits = None, seed = None))]
+#[allow(clippy::too_many_arguments)]
+fn optimize_regular_wgt<'py>(
+    py: Python<'py>,
+    ant_beam: PyReadonlyArray1<'py, f64>,
+    target_beam: PyReadonlyArray1<'py, f64>,
+    wgt0: PyReadonlyArray2<'py, f64>,
+    d: f64,
+    freq_mhz: f64,
+    npart: usize,
+    bits: Option<u32>,
+    seed: Option<u64>,
+) -> PyResult<Bound<'py, PyArray2<f64>>> {
+    let ant_beam = ant_beam.as_slice()?;
+    let target_beam = target_beam.as_slice()?;
+    let wgt0 = wgt0.as_array();
+    let (h, w) = (wgt0.shape()[0], wgt0.shape()[1]);
+    let nside = checked_npix2nside(ant_beam.len())?;
+    let guess: Vec<f64> = full2quarter(wgt0).iter().skip(1).cloned().collect();
+    let seed = seed.unwrap_or_else(|| thread_rng().gen());
+    bits.map(check_bits).transpose()?;
+
+    let wgt = py.detach(|| -> DbfResult<Array2<f64>> {
+        let obj = QuarterWgtObjective::new(ant_beam, target_beam, d, freq_mhz, nside, h, w)?;
+        let mut rng = StdRng::seed_from_u64(seed);
+        let result = optimize_wgt(
+            &obj,
+            &guess,
+            npart,
+            bits,
+            &Solver::default(),
+            &StopRule::default(),
+            &mut rng,
+            |_, _| {},
+        )?;
+        quarter2full(deflattern_quarter_wgt(&result.x, h, w)?.view())
+    })?;
+    Ok(wgt.into_pyarray(py))
+}
+
+#[pymodule]
+#[pyo3(name = "dbf_beam_simulator")]
+fn py_module(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(calc_array_beam, m)?)?;
+    m.add_function(wrap_pyfunction!(calc_averaged_array_beam, m)?)?;
+    m.add_function(wrap_pyfunction!(pattern2wgt, m)?)?;
+    m.add_function(wrap_pyfunction!(wgt2pattern, m)?)?;
+    m.add_function(wrap_pyfunction!(quarter_wgt2pattern, m)?)?;
+    m.add_function(wrap_pyfunction!(integrate_az, m)?)?;
+    m.add_function(wrap_pyfunction!(optimize_regular_wgt, m)?)?;
+    Ok(())
+}
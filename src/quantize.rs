@@ -0,0 +1,122 @@
+use ndarray::{Array2, ArrayView2};
+
+use num::complex::Complex;
+
+use crate::error::{DbfError, DbfResult};
+
+/// Range of bit depths accepted by the functions of this module.
+pub const MIN_BITS: u32 = 2;
+pub const MAX_BITS: u32 = 32;
+
+/// Checks that `bits` is in `MIN_BITS..=MAX_BITS`, below which there is no
+/// non-zero level and above which the levels overflow.
+pub fn check_bits(bits: u32) -> DbfResult<()> {
+    if (MIN_BITS..=MAX_BITS).contains(&bits) {
+        Ok(())
+    } else {
+        Err(DbfError::InvalidValue {
+            what: "bits",
+            reason: format!("must be in {}..={}, found {}", MIN_BITS, MAX_BITS, bits),
+        })
+    }
+}
+
+/// Number of non-zero levels of a signed fixed-point number with `bits` bits,
+/// which must pass `check_bits`.
+fn signed_levels(bits: u32) -> f64 {
+    ((1_u64 << (bits - 1)) - 1) as f64
+}
+
+/// Number of non-zero levels of an unsigned fixed-point number with `bits` bits,
+/// which must pass `check_bits`.
+fn unsigned_levels(bits: u32) -> f64 {
+    ((1_u64 << bits) - 1) as f64
+}
+
+/// Rounds a value in [0, 1] to the nearest `bits`-bit unsigned level; `bits`
+/// must pass `check_bits`.
+pub(crate) fn quantize_unit(x: f64, bits: u32) -> f64 {
+    let l = unsigned_levels(bits);
+    (x.clamp(0.0, 1.0) * l).round() / l
+}
+
+/// Rounds real weights to signed fixed point, with the largest magnitude
+/// mapped to the full scale.
+pub fn quantize_wgt(wgt: ArrayView2<f64>, bits: u32) -> DbfResult<Array2<f64>> {
+    check_bits(bits)?;
+    let l = signed_levels(bits);
+    let scale = wgt.iter().fold(0.0_f64, |a, &b| a.max(b.abs()));
+    if scale == 0.0 {
+        return Ok(wgt.to_owned());
+    }
+    Ok(wgt.map(|&w| (w / scale * l).round() / l * scale))
+}
+
+/// Rounds the real and imaginary parts of the complex coefficients
+/// `w * exp(i * phi)` to signed fixed point, as done in an FPGA beamformer,
+/// and returns the resulting amplitudes and phases.
+pub fn quantize_complex_wgt(
+    w_list: &[f64],
+    phi_list: &[f64],
+    bits: u32,
+) -> DbfResult<(Vec<f64>, Vec<f64>)> {
+    check_bits(bits)?;
+    let l = signed_levels(bits);
+    let coeffs: Vec<_> = w_list
+        .iter()
+        .zip(phi_list.iter())
+        .map(|(&w, &phi)| Complex::from_polar(w, phi))
+        .collect();
+    let scale = coeffs
+        .iter()
+        .fold(0.0_f64, |a, c| a.max(c.re.abs()).max(c.im.abs()));
+    if scale == 0.0 {
+        return Ok((w_list.to_vec(), phi_list.to_vec()));
+    }
+    Ok(coeffs
+        .iter()
+        .map(|c| {
+            let q = Complex::new(
+                (c.re / scale * l).round() / l * scale,
+                (c.im / scale * l).round() / l * scale,
+            );
+            (q.norm(), q.arg())
+        })
+        .unzip())
+}
+
+/// Greedy search on the `bits`-bit lattice of [0, 1]^n, moving one
+/// coordinate by one level at a time while `fobj` increases.
+/// `x` is expected to be on the lattice already; the final fitness is returned.
+pub fn lattice_polish<F>(fobj: F, x: &mut [f64], bits: u32) -> DbfResult<f64>
+where
+    F: Fn(&[f64]) -> f64,
+{
+    check_bits(bits)?;
+    let step = 1.0 / unsigned_levels(bits);
+    let mut best = fobj(x);
+    loop {
+        let mut improved = false;
+        for i in 0..x.len() {
+            let x0 = x[i];
+            for d in [step, -step] {
+                let x1 = quantize_unit(x0 + d, bits);
+                if x1 == x0 {
+                    continue;
+                }
+                x[i] = x1;
+                let f = fobj(x);
+                if f > best {
+                    best = f;
+                    improved = true;
+                    break;
+                } else {
+                    x[i] = x0;
+                }
+            }
+        }
+        if !improved {
+            break Ok(best);
+        }
+    }
+}
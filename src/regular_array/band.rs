@@ -0,0 +1,168 @@
+//! Joint design of regular array weights over a band, so that the beam
+//! follows one target at every freq.
+
+use crate::{
+    error::{DbfError, DbfResult},
+    metrics::{calc_beam_metrics, BeamMetrics},
+};
+
+use super::opt::{QuarterWgtObjective, WgtObjective};
+
+#[derive(Clone, Copy, Debug)]
+pub enum BandWgtMode {
+    /// independent weights per freq, the squared difference between adjacent
+    /// freqs being penalised by `smoothness`
+    PerFreq { smoothness: f64 },
+    /// one set of weights used at all freqs
+    Shared,
+}
+
+/// Mean misfit over all freqs of a regular array with a fixed spacing.
+pub struct BandWgtObjective<'a> {
+    pub objs: Vec<QuarterWgtObjective<'a>>,
+    pub mode: BandWgtMode,
+}
+
+/// Achieved beam at one freq of a band design.
+#[derive(Clone, Copy, Debug)]
+pub struct BandFreqReport {
+    pub freq_mhz: f64,
+    /// misfit to the target, as in `QuarterWgtObjective::misfit`
+    pub misfit: f64,
+    /// misfit to the mean normalised beam over the band
+    pub deviation: f64,
+    pub metrics: BeamMetrics,
+}
+
+impl<'a> BandWgtObjective<'a> {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        ant_beam: &'a [f64],
+        target_beam: &[f64],
+        d: f64,
+        freqs_mhz: &[f64],
+        nside: usize,
+        h: usize,
+        w: usize,
+        mode: BandWgtMode,
+    ) -> DbfResult<Self> {
+        if freqs_mhz.is_empty() {
+            return Err(DbfError::InvalidValue {
+                what: "freqs",
+                reason: "must not be empty".to_string(),
+            });
+        }
+        Ok(BandWgtObjective {
+            objs: freqs_mhz
+                .iter()
+                .map(|&f| QuarterWgtObjective::new(ant_beam, target_beam, d, f, nside, h, w))
+                .collect::<DbfResult<_>>()?,
+            mode,
+        })
+    }
+
+    pub fn nfreq(&self) -> usize {
+        self.objs.len()
+    }
+
+    /// Num of free weights at one freq.
+    pub fn ndim_per_freq(&self) -> usize {
+        self.objs[0].ndim()
+    }
+
+    /// Flattened quarter weights used at every freq.
+    pub fn wgt_per_freq<'b>(&self, x: &'b [f64]) -> Vec<&'b [f64]> {
+        match self.mode {
+            BandWgtMode::Shared => vec![x; self.nfreq()],
+            BandWgtMode::PerFreq { .. } => x.chunks(self.ndim_per_freq()).collect(),
+        }
+    }
+
+    /// Starting point from one flattened quarter wgt per freq, averaged if
+    /// the weights are shared.
+    pub fn guess(&self, wgt_per_freq: &[Vec<f64>]) -> Vec<f64> {
+        match self.mode {
+            BandWgtMode::Shared => (0..self.ndim_per_freq())
+                .map(|i| wgt_per_freq.iter().map(|x| x[i]).sum::<f64>() / wgt_per_freq.len() as f64)
+                .collect(),
+            BandWgtMode::PerFreq { .. } => wgt_per_freq.concat(),
+        }
+    }
+
+    pub fn misfit(&self, x: &[f64]) -> DbfResult<f64> {
+        let wgts = self.wgt_per_freq(x);
+        let mut misfit = 0.0;
+        for (obj, x1) in self.objs.iter().zip(wgts.iter()) {
+            misfit += obj.misfit(x1)?;
+        }
+        misfit /= self.nfreq() as f64;
+        if let BandWgtMode::PerFreq { smoothness } = self.mode {
+            misfit += smoothness
+                * wgts
+                    .windows(2)
+                    .map(|p| {
+                        p[0].iter()
+                            .zip(p[1].iter())
+                            .map(|(a, b)| (a - b).powi(2))
+                            .sum::<f64>()
+                    })
+                    .sum::<f64>();
+        }
+        Ok(misfit)
+    }
+
+    pub fn total_beams(&self, x: &[f64]) -> DbfResult<Vec<Vec<f64>>> {
+        self.objs
+            .iter()
+            .zip(self.wgt_per_freq(x))
+            .map(|(obj, x1)| obj.total_beam(x1))
+            .collect()
+    }
+
+    /// Misfit, deviation from the band mean and beam metrics at every freq.
+    pub fn report(&self, x: &[f64]) -> DbfResult<Vec<BandFreqReport>> {
+        let beams: Vec<Vec<f64>> = self
+            .total_beams(x)?
+            .into_iter()
+            .map(|b| {
+                let norm = b.iter().sum::<f64>();
+                b.iter().map(|&x| x / norm).collect()
+            })
+            .collect();
+        let npix = beams[0].len();
+        let mean: Vec<f64> = (0..npix)
+            .map(|i| beams.iter().map(|b| b[i]).sum::<f64>() / beams.len() as f64)
+            .collect();
+        self.objs
+            .iter()
+            .zip(self.wgt_per_freq(x))
+            .zip(beams.iter())
+            .map(|((obj, x1), b)| {
+                Ok(BandFreqReport {
+                    freq_mhz: obj.freq_mhz,
+                    misfit: obj.misfit(x1)?,
+                    deviation: b
+                        .iter()
+                        .zip(mean.iter())
+                        .map(|(&a, &m)| (a - m).powi(2))
+                        .sum::<f64>()
+                        * npix as f64,
+                    metrics: calc_beam_metrics(b)?,
+                })
+            })
+            .collect()
+    }
+}
+
+impl<'a> WgtObjective for BandWgtObjective<'a> {
+    fn ndim(&self) -> usize {
+        match self.mode {
+            BandWgtMode::Shared => self.ndim_per_freq(),
+            BandWgtMode::PerFreq { .. } => self.ndim_per_freq() * self.nfreq(),
+        }
+    }
+
+    fn misfit(&self, x: &[f64]) -> DbfResult<f64> {
+        BandWgtObjective::misfit(self, x)
+    }
+}
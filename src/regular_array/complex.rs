@@ -0,0 +1,340 @@
+//! Regular arrays with complex weights over the whole grid, which unlike the
+//! four-fold symmetric real weights can make off-zenith and asymmetric beams.
+//!
+//! As in `wgt2pattern`, the first axis of the grid runs along x and the
+//! second along y, both centred on the middle of the grid.
+
+use std::f64::consts::PI;
+
+use num::complex::Complex;
+
+use ndarray::{Array2, ArrayView2};
+
+use serde::{Deserialize, Serialize};
+
+use scorus::{
+    coordinates::Vec3d,
+    healpix::{pix::pix2vec_ring, utils::nside2npix},
+};
+
+use crate::{
+    constants::LIGHT_SPEED,
+    error::{check_len, DbfError, DbfResult},
+    steering::pointing_vec,
+    utils::{check_beam_npix, check_nside},
+};
+
+use super::opt::{beam_misfit, WgtObjective};
+
+/// Constraint on a complex weight grid, as the cells that must be equal.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WgtSymmetry {
+    None,
+    /// w(-x, y) = w(x, y)
+    MirrorX,
+    /// w(x, -y) = w(x, y)
+    MirrorY,
+    /// both mirrors, the complex counterpart of the quarter weights
+    Quadrant,
+    /// w(-x, -y) = w(x, y)
+    Point,
+}
+
+impl WgtSymmetry {
+    pub const NAMES: [&'static str; 5] = ["none", "mirror-x", "mirror-y", "quadrant", "point"];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "none" => Some(WgtSymmetry::None),
+            "mirror-x" => Some(WgtSymmetry::MirrorX),
+            "mirror-y" => Some(WgtSymmetry::MirrorY),
+            "quadrant" => Some(WgtSymmetry::Quadrant),
+            "point" => Some(WgtSymmetry::Point),
+            _ => None,
+        }
+    }
+
+    /// Cells of an `h` x `w` grid tied to (i, j), including itself.
+    fn orbit(&self, i: usize, j: usize, h: usize, w: usize) -> Vec<(usize, usize)> {
+        let (mi, mj) = (h - 1 - i, w - 1 - j);
+        match self {
+            WgtSymmetry::None => vec![(i, j)],
+            WgtSymmetry::MirrorX => vec![(i, j), (mi, j)],
+            WgtSymmetry::MirrorY => vec![(i, j), (i, mj)],
+            WgtSymmetry::Quadrant => vec![(i, j), (mi, j), (i, mj), (mi, mj)],
+            WgtSymmetry::Point => vec![(i, j), (mi, mj)],
+        }
+    }
+}
+
+/// Maps the free parameters, all in [0, 1], to a complex weight grid: the
+/// amplitudes of the independent cells, then their phases in turns. The
+/// phase of the first independent cell is fixed to 0, as a common phase does
+/// not change the beam.
+#[derive(Clone, Debug)]
+pub struct ComplexWgtLayout {
+    pub h: usize,
+    pub w: usize,
+    pub sym: WgtSymmetry,
+    /// first cell of every group of tied cells
+    reps: Vec<(usize, usize)>,
+    /// group of every cell
+    group: Array2<usize>,
+}
+
+impl ComplexWgtLayout {
+    pub fn new(h: usize, w: usize, sym: WgtSymmetry) -> DbfResult<Self> {
+        if h == 0 || w == 0 {
+            return Err(DbfError::InvalidShape {
+                what: "complex wgt",
+                shape: vec![h, w],
+                reason: "must not be empty",
+            });
+        }
+        let mut group = Array2::from_elem((h, w), usize::MAX);
+        let mut reps = Vec::new();
+        for i in 0..h {
+            for j in 0..w {
+                if group[(i, j)] != usize::MAX {
+                    continue;
+                }
+                for c in sym.orbit(i, j, h, w) {
+                    group[c] = reps.len();
+                }
+                reps.push((i, j));
+            }
+        }
+        Ok(ComplexWgtLayout {
+            h,
+            w,
+            sym,
+            reps,
+            group,
+        })
+    }
+
+    pub fn ndim(&self) -> usize {
+        2 * self.reps.len() - 1
+    }
+
+    pub fn to_wgt(&self, x: &[f64]) -> DbfResult<Array2<Complex<f64>>> {
+        check_len("complex wgt params", self.ndim(), x.len())?;
+        let k = self.reps.len();
+        let values: Vec<Complex<f64>> = (0..k)
+            .map(|g| {
+                let phase = if g == 0 { 0.0 } else { x[k + g - 1] };
+                Complex::from_polar(x[g], 2.0 * PI * phase)
+            })
+            .collect();
+        Ok(self.group.mapv(|g| values[g]))
+    }
+
+    /// Parameters of the tied cells of `wgt` closest to it, the amplitudes
+    /// scaled to a max of 1; the grid must have the shape of the layout.
+    pub fn from_wgt(&self, wgt: ArrayView2<Complex<f64>>) -> DbfResult<Vec<f64>> {
+        if wgt.shape() != [self.h, self.w] {
+            return Err(DbfError::InvalidShape {
+                what: "complex wgt",
+                shape: wgt.shape().to_vec(),
+                reason: "does not match the layout",
+            });
+        }
+        // mean over every group
+        let mut sums = vec![Complex::new(0.0, 0.0); self.reps.len()];
+        let mut counts = vec![0.0; self.reps.len()];
+        for ((i, j), &g) in self.group.indexed_iter() {
+            sums[g] += wgt[(i, j)];
+            counts[g] += 1.0;
+        }
+        let values: Vec<Complex<f64>> =
+            sums.iter().zip(counts.iter()).map(|(s, c)| s / c).collect();
+        let max = values.iter().map(|v| v.norm()).fold(0.0, f64::max);
+        let scale = if max > 0.0 { 1.0 / max } else { 1.0 };
+        let phase0 = values[0].arg();
+        let amps = values.iter().map(|v| v.norm() * scale);
+        let phases = values
+            .iter()
+            .skip(1)
+            .map(|v| ((v.arg() - phase0) / (2.0 * PI)).rem_euclid(1.0));
+        Ok(amps.chain(phases).collect())
+    }
+}
+
+/// Per-pixel phase factors along x and y, so that the array factor of any
+/// weights at one spacing and freq needs no trigonometry.
+struct PhaseTable {
+    npix: usize,
+    h: usize,
+    w: usize,
+    /// exp(2 pi i m u nx) for every upper hemisphere pixel and row m
+    ex: Vec<Complex<f64>>,
+    /// exp(2 pi i n u ny) for every upper hemisphere pixel and column n
+    ey: Vec<Complex<f64>>,
+}
+
+impl PhaseTable {
+    fn new(h: usize, w: usize, d: f64, freq_mhz: f64, nside: usize) -> DbfResult<Self> {
+        check_nside(nside)?;
+        let npix = nside2npix(nside);
+        let u = d / (LIGHT_SPEED / (freq_mhz * 1e6));
+        let mut ex = Vec::with_capacity(npix / 2 * h);
+        let mut ey = Vec::with_capacity(npix / 2 * w);
+        for ipix in 0..npix / 2 {
+            let Vec3d { x: nx, y: ny, z: _ } = pix2vec_ring::<f64>(nside, ipix);
+            for i in 0..h {
+                let m = i as f64 - (h - 1) as f64 / 2.0;
+                ex.push(Complex::from_polar(1.0, 2.0 * PI * m * u * nx));
+            }
+            for j in 0..w {
+                let n = j as f64 - (w - 1) as f64 / 2.0;
+                ey.push(Complex::from_polar(1.0, 2.0 * PI * n * u * ny));
+            }
+        }
+        Ok(PhaseTable { npix, h, w, ex, ey })
+    }
+
+    /// |array factor|^2 above the horizon, i.e. over the upper hemisphere.
+    fn pattern_hemi(&self, wgt: ArrayView2<Complex<f64>>) -> Vec<f64> {
+        let mut result = vec![0.0; self.npix / 2];
+        for (ipix, p) in result.iter_mut().enumerate() {
+            let ex = &self.ex[ipix * self.h..(ipix + 1) * self.h];
+            let ey = &self.ey[ipix * self.w..(ipix + 1) * self.w];
+            let af: Complex<f64> = ex
+                .iter()
+                .enumerate()
+                .map(|(i, &e)| {
+                    e * ey
+                        .iter()
+                        .zip(wgt.row(i))
+                        .map(|(&f, &w1)| f * w1)
+                        .sum::<Complex<f64>>()
+                })
+                .sum();
+            *p = af.norm_sqr();
+        }
+        result
+    }
+}
+
+/// Pattern of a regular array with complex weights, as `wgt2pattern`.
+pub fn complex_wgt2pattern(
+    wgt: ArrayView2<Complex<f64>>,
+    d: f64,
+    freq_mhz: f64,
+    nside: usize,
+) -> DbfResult<Vec<f64>> {
+    let table = PhaseTable::new(wgt.shape()[0], wgt.shape()[1], d, freq_mhz, nside)?;
+    let mut pattern = table.pattern_hemi(wgt);
+    pattern.resize(table.npix, 0.0);
+    Ok(pattern)
+}
+
+/// Beam of a regular array with complex weights, including the element beam
+/// and normalised to a peak of 1, as the beam need not point to the zenith.
+/// The beam has as many pixels as `ant_beam`, which may be full sky or its
+/// upper hemisphere.
+pub fn complex_wgt2beam(
+    wgt: ArrayView2<Complex<f64>>,
+    ant_beam: &[f64],
+    d: f64,
+    freq_mhz: f64,
+    nside: usize,
+) -> DbfResult<Vec<f64>> {
+    let table = PhaseTable::new(wgt.shape()[0], wgt.shape()[1], d, freq_mhz, nside)?;
+    check_beam_npix("ant_beam", nside, ant_beam.len())?;
+    let total_beam: Vec<f64> = table
+        .pattern_hemi(wgt)
+        .iter()
+        .zip(ant_beam.iter())
+        .map(|(&a, &b)| a * b)
+        .collect();
+    let peak = total_beam.iter().cloned().fold(0.0, f64::max);
+    if peak <= 0.0 {
+        return Err(DbfError::InvalidValue {
+            what: "wgt",
+            reason: "the beam is zero above the horizon".to_string(),
+        });
+    }
+    let mut beam: Vec<f64> = total_beam.iter().map(|&x| x / peak).collect();
+    beam.resize(ant_beam.len(), 0.0);
+    Ok(beam)
+}
+
+/// `wgt` with the linear phase that points its beam to `az_from_north`
+/// and `zenith`, both in rad.
+pub fn steer_wgt(
+    wgt: ArrayView2<Complex<f64>>,
+    d: f64,
+    freq_mhz: f64,
+    az_from_north: f64,
+    zenith: f64,
+) -> Array2<Complex<f64>> {
+    let (h, w) = (wgt.shape()[0], wgt.shape()[1]);
+    let u = d / (LIGHT_SPEED / (freq_mhz * 1e6));
+    let dir = pointing_vec(az_from_north, zenith);
+    Array2::from_shape_fn((h, w), |(i, j)| {
+        let m = i as f64 - (h - 1) as f64 / 2.0;
+        let n = j as f64 - (w - 1) as f64 / 2.0;
+        wgt[(i, j)] * Complex::from_polar(1.0, -2.0 * PI * u * (m * dir.x + n * dir.y))
+    })
+}
+
+/// Misfit between the beam of a regular array with complex weights laid out
+/// by `layout` and a target beam.
+pub struct ComplexWgtObjective<'a> {
+    pub ant_beam: &'a [f64],
+    /// target beam normalised to unit sum
+    pub target_beam: Vec<f64>,
+    pub layout: ComplexWgtLayout,
+    table: PhaseTable,
+}
+
+impl<'a> ComplexWgtObjective<'a> {
+    pub fn new(
+        ant_beam: &'a [f64],
+        target_beam: &[f64],
+        d: f64,
+        freq_mhz: f64,
+        nside: usize,
+        layout: ComplexWgtLayout,
+    ) -> DbfResult<Self> {
+        let table = PhaseTable::new(layout.h, layout.w, d, freq_mhz, nside)?;
+        check_len("ant_beam", table.npix, ant_beam.len())?;
+        check_len("target_beam", table.npix, target_beam.len())?;
+        let norm = target_beam.iter().sum::<f64>();
+        Ok(ComplexWgtObjective {
+            ant_beam,
+            target_beam: target_beam.iter().map(|&x| x / norm).collect(),
+            layout,
+            table,
+        })
+    }
+
+    pub fn total_beam(&self, x: &[f64]) -> DbfResult<Vec<f64>> {
+        let wgt = self.layout.to_wgt(x)?;
+        let mut beam: Vec<f64> = self
+            .table
+            .pattern_hemi(wgt.view())
+            .iter()
+            .zip(self.ant_beam.iter())
+            .map(|(&a, &b)| a * b)
+            .collect();
+        beam.resize(self.table.npix, 0.0);
+        Ok(beam)
+    }
+
+    pub fn misfit(&self, x: &[f64]) -> DbfResult<f64> {
+        Ok(beam_misfit(&self.total_beam(x)?, &self.target_beam))
+    }
+}
+
+impl<'a> WgtObjective for ComplexWgtObjective<'a> {
+    fn ndim(&self) -> usize {
+        self.layout.ndim()
+    }
+
+    fn misfit(&self, x: &[f64]) -> DbfResult<f64> {
+        ComplexWgtObjective::misfit(self, x)
+    }
+}
@@ -1,3 +1,7 @@
+pub mod band;
+pub mod complex;
+pub mod opt;
+pub mod solver;
 pub mod utils;
 pub use utils::*;
 
@@ -7,14 +11,18 @@ use num::complex::Complex;
 
 use ndarray::{s, Array2, ArrayView2};
 
-use crate::fft::{fft2, fftshift2};
+use crate::{
+    error::{check_len, DbfError, DbfResult},
+    fft::{fft2, fftshift2},
+    utils::{check_beam_npix, check_nside, checked_npix2nside, hemi_npix},
+};
 
 use scorus::{
     coordinates::{SphCoord, Vec3d},
     healpix::{
         interp::get_interpol_ring,
         pix::pix2vec_ring,
-        utils::{npix2nside, nside2npix},
+        utils::nside2npix,
     },
 };
 
@@ -24,9 +32,9 @@ pub use crate::{
     utils::{averaged_beam_to_healpix, integrate_az},
 };
 
-pub fn beam_opt_func_obj1(beam1: &[f64], beam2: &[f64], wgt: &[f64]) -> f64 {
-    assert_eq!(beam1.len(), wgt.len());
-    assert_eq!(beam2.len(), wgt.len());
+pub fn beam_opt_func_obj1(beam1: &[f64], beam2: &[f64], wgt: &[f64]) -> DbfResult<f64> {
+    check_len("beam1", wgt.len(), beam1.len())?;
+    check_len("beam2", wgt.len(), beam2.len())?;
     let npix = beam1.len();
     let domega = 4.0 * PI / npix as f64;
     let s1 = beam1
@@ -39,13 +47,13 @@ pub fn beam_opt_func_obj1(beam1: &[f64], beam2: &[f64], wgt: &[f64]) -> f64 {
         .zip(wgt.iter())
         .map(|(&a, &b)| a * b)
         .sum::<f64>();
-    beam1
+    Ok(beam1
         .iter()
         .zip(beam2.iter().zip(wgt.iter()))
         .map(|(&b1, (&b2, &w))| (b1 / s1 - b2 / s2) * w)
         .map(|x| x.powi(2))
         .sum::<f64>()
-        / domega
+        / domega)
 }
 
 #[allow(clippy::too_many_arguments)]
@@ -59,15 +67,20 @@ pub fn beam_opt_func_obj2(
     w_list: &[f64],
     phi_list: &[f64],
     freq_hz: f64,
-) -> f64 {
+) -> DbfResult<f64> {
     let (mean_beam, weight, _theta) = calc_averaged_array_beam(
         lat_deg, ant_beam, x_list, y_list, z_list, w_list, phi_list, freq_hz,
-    );
+    )?;
     let weight: Vec<_> = weight.into_iter().map(|x| x as f64).collect();
     beam_opt_func_obj1(beam0, &mean_beam, &weight)
 }
 
-pub fn zenith_ns_sym_array(y: &[f64], w: &[f64]) -> (Vec<f64>, Vec<f64>, Vec<f64>, Vec<f64>) {
+#[allow(clippy::type_complexity)]
+pub fn zenith_ns_sym_array(
+    y: &[f64],
+    w: &[f64],
+) -> DbfResult<(Vec<f64>, Vec<f64>, Vec<f64>, Vec<f64>)> {
+    check_len("w", y.len(), w.len())?;
     let mut ant_x = vec![0.0];
     let mut ant_y = vec![0.0];
     let mut ant_z = vec![0.0];
@@ -84,7 +97,7 @@ pub fn zenith_ns_sym_array(y: &[f64], w: &[f64]) -> (Vec<f64>, Vec<f64>, Vec<f64
         ant_z.push(0.0);
         ant_w.push(w1);
     }
-    (ant_x, ant_y, ant_z, ant_w)
+    Ok((ant_x, ant_y, ant_z, ant_w))
 }
 
 pub fn sym_weight(w: &[f64]) -> Vec<f64> {
@@ -102,7 +115,13 @@ pub fn design_square_array(
     freq_mega_hz: f64,
     sigma_deg: f64,
     n: isize,
-) -> Array2<f64> {
+) -> DbfResult<Array2<f64>> {
+    if n <= 0 || n % 2 != 0 {
+        return Err(DbfError::InvalidValue {
+            what: "array size",
+            reason: format!("{} is not a positive even number", n),
+        });
+    }
     let center = n / 2;
     let lbd = LIGHT_SPEED / (freq_mega_hz * 1e6);
     let sigma = spacing / lbd * sigma_deg.to_radians().sin();
@@ -118,9 +137,9 @@ pub fn design_square_array(
             beam_pattern[(i as usize, j as usize)] = Complex::<f64>::from(bx * by);
         }
     }
-    let mut beam_pattern = fftshift2(beam_pattern.view());
+    let mut beam_pattern = fftshift2(beam_pattern.view())?;
     let mut wgt = Array2::<Complex<f64>>::zeros((n as usize, n as usize));
-    fft2(beam_pattern.view_mut(), wgt.view_mut());
+    fft2(beam_pattern.view_mut(), wgt.view_mut())?;
     let norm = wgt[(0, 0)].re;
     let mut wgt = wgt.map(|x| x.re);
     wgt.iter_mut().for_each(|x| *x /= norm);
@@ -129,10 +148,16 @@ pub fn design_square_array(
     fftshift2(wgt.view())
 }
 
-pub fn pattern2wgt(hp: &[f64], d: f64, freq_mhz: f64, n1: isize) -> Array2<f64> {
+pub fn pattern2wgt(hp: &[f64], d: f64, freq_mhz: f64, n1: isize) -> DbfResult<Array2<f64>> {
+    if n1 <= 0 {
+        return Err(DbfError::InvalidValue {
+            what: "array size",
+            reason: format!("{} is not positive", n1),
+        });
+    }
     let n = if n1 % 2 == 1 { n1 + 1 } else { n1 };
     let npix = hp.len();
-    let nside = npix2nside(npix);
+    let nside = checked_npix2nside(npix)?;
     let center = if n1 % 2 == 1 {
         n as f64 / 2.0
     } else {
@@ -165,43 +190,57 @@ pub fn pattern2wgt(hp: &[f64], d: f64, freq_mhz: f64, n1: isize) -> Array2<f64>
                 .into();
         }
     }
-    let mut projected = fftshift2(projected.view());
+    let mut projected = fftshift2(projected.view())?;
     let mut wgt = Array2::<Complex<f64>>::zeros((n as usize, n as usize));
-    fft2(projected.view_mut(), wgt.view_mut());
+    fft2(projected.view_mut(), wgt.view_mut())?;
     let norm = wgt[(0, 0)].re;
     let mut wgt = wgt.map(|x| x.re);
     wgt.iter_mut().for_each(|x| *x /= norm);
-    let result = fftshift2(wgt.view());
+    let result = fftshift2(wgt.view())?;
     if n1 % 2 == 1 {
-        result.slice(s![1.., 1..]).to_owned()
+        Ok(result.slice(s![1.., 1..]).to_owned())
     } else {
-        result
+        Ok(result)
     }
 }
 
-pub fn wgt2pattern(wgt: ArrayView2<f64>, d: f64, freq_mhz: f64, nside: usize) -> Vec<f64> {
-    let npix = nside2npix(nside);
+pub fn wgt2pattern(
+    wgt: ArrayView2<f64>,
+    d: f64,
+    freq_mhz: f64,
+    nside: usize,
+) -> DbfResult<Vec<f64>> {
+    let mut pattern = wgt2pattern_hemi(wgt, d, freq_mhz, nside)?;
+    pattern.resize(nside2npix(nside), 0.0);
+    Ok(pattern)
+}
+
+/// `wgt2pattern` above the horizon only, as the first `hemi_npix(nside)`
+/// pixels of the RING map.
+pub fn wgt2pattern_hemi(
+    wgt: ArrayView2<f64>,
+    d: f64,
+    freq_mhz: f64,
+    nside: usize,
+) -> DbfResult<Vec<f64>> {
+    check_nside(nside)?;
     let lbd = LIGHT_SPEED / (freq_mhz * 1e6);
     let u = d / lbd;
-    (0..npix)
+    Ok((0..hemi_npix(nside))
         .map(|ipix| {
-            if ipix < npix / 2 {
-                let Vec3d { x: nx, y: ny, z: _ } = pix2vec_ring::<f64>(nside, ipix);
-                let mut p = Complex::<f64>::new(0.0, 0.0);
-                for i in 0..wgt.shape()[0] {
-                    let m = i as f64 - (wgt.shape()[0] - 1) as f64 / 2.0;
-                    for j in 0..wgt.shape()[1] {
-                        let w = wgt[(i, j)];
-                        let n = j as f64 - (wgt.shape()[1] - 1) as f64 / 2.0;
-                        p += Complex::<f64>::from_polar(w, 2.0 * PI * (m * u * nx + n * u * ny));
-                    }
+            let Vec3d { x: nx, y: ny, z: _ } = pix2vec_ring::<f64>(nside, ipix);
+            let mut p = Complex::<f64>::new(0.0, 0.0);
+            for i in 0..wgt.shape()[0] {
+                let m = i as f64 - (wgt.shape()[0] - 1) as f64 / 2.0;
+                for j in 0..wgt.shape()[1] {
+                    let w = wgt[(i, j)];
+                    let n = j as f64 - (wgt.shape()[1] - 1) as f64 / 2.0;
+                    p += Complex::<f64>::from_polar(w, 2.0 * PI * (m * u * nx + n * u * ny));
                 }
-                p.norm_sqr()
-            } else {
-                0.0
             }
+            p.norm_sqr()
         })
-        .collect()
+        .collect())
 }
 
 pub fn quarter_wgt2pattern(
@@ -209,30 +248,111 @@ pub fn quarter_wgt2pattern(
     d: f64,
     freq_mhz: f64,
     nside: usize,
-) -> Vec<f64> {
-    let npix = nside2npix(nside);
+) -> DbfResult<Vec<f64>> {
+    let mut pattern = quarter_wgt2pattern_hemi(quarter_wgt, d, freq_mhz, nside)?;
+    pattern.resize(nside2npix(nside), 0.0);
+    Ok(pattern)
+}
+
+/// `quarter_wgt2pattern` above the horizon only, as the first
+/// `hemi_npix(nside)` pixels of the RING map.
+pub fn quarter_wgt2pattern_hemi(
+    quarter_wgt: ArrayView2<f64>,
+    d: f64,
+    freq_mhz: f64,
+    nside: usize,
+) -> DbfResult<Vec<f64>> {
+    check_nside(nside)?;
     let lbd = LIGHT_SPEED / (freq_mhz * 1e6);
     let u = d / lbd;
-    (0..npix)
+    Ok((0..hemi_npix(nside))
         .map(|ipix| {
-            if ipix < npix / 2 {
-                let Vec3d { x: nx, y: ny, z: _ } = pix2vec_ring::<f64>(nside, ipix);
-                let mut p = 0.0;
-                for i in 0..quarter_wgt.shape()[0] {
-                    for j in 0..quarter_wgt.shape()[1] {
-                        let w = quarter_wgt[(i, j)];
-                        p += w *  
-                            (2.0 * PI * (i as f64) * u * nx).cos()
-                                * (2.0 * PI * (j as f64) * u * ny).cos()
-                                * if i == 0 { 1.0 } else { 2.0 }
-                                * if j == 0 { 1.0 } else { 2.0 }
-                        ;
-                    }
+            let Vec3d { x: nx, y: ny, z: _ } = pix2vec_ring::<f64>(nside, ipix);
+            let mut p = 0.0;
+            for i in 0..quarter_wgt.shape()[0] {
+                for j in 0..quarter_wgt.shape()[1] {
+                    let w = quarter_wgt[(i, j)];
+                    p += w *  
+                        (2.0 * PI * (i as f64) * u * nx).cos()
+                            * (2.0 * PI * (j as f64) * u * ny).cos()
+                            * if i == 0 { 1.0 } else { 2.0 }
+                            * if j == 0 { 1.0 } else { 2.0 }
+                    ;
                 }
-                p.powi(2)
-            } else {
-                0.0
             }
+            p.powi(2)
         })
-        .collect()
+        .collect())
+}
+
+/// Weights of a regular array of `array_size`^2 elements synthesising
+/// `target_beam` once divided by the element beam, normalised at zenith.
+pub fn target_beam2wgt(
+    target_beam: &[f64],
+    ant_beam: &[f64],
+    d: f64,
+    freq_mhz: f64,
+    array_size: isize,
+) -> DbfResult<Array2<f64>> {
+    let nside = checked_npix2nside(target_beam.len())?;
+    check_len("ant_beam", target_beam.len(), ant_beam.len())?;
+    let mut array_beam: Vec<_> = ant_beam
+        .iter()
+        .zip(target_beam.iter())
+        .map(|(&a, &t)| t / a)
+        .collect();
+
+    let beam_norm = {
+        let (p, w) = get_interpol_ring(nside, SphCoord::<f64>::new(0.0, 0.0));
+        p.iter()
+            .zip(w.iter())
+            .map(|(&ipix, &w1)| array_beam[ipix] * w1)
+            .sum::<f64>()
+    };
+
+    array_beam.iter_mut().for_each(|x| {
+        *x /= beam_norm;
+        if *x > 2.0 {
+            eprintln!("warning beam max >2: {}", *x);
+        }
+    });
+
+    pattern2wgt(&array_beam, d, freq_mhz, array_size)
+}
+
+/// Beam of a regular array with four-fold symmetric weights, including the
+/// element beam and normalised at zenith. The beam has as many pixels as
+/// `ant_beam`, which may be full sky or its upper hemisphere.
+pub fn regular_wgt2beam(
+    wgt: ArrayView2<f64>,
+    ant_beam: &[f64],
+    d: f64,
+    freq_mhz: f64,
+    nside: usize,
+) -> DbfResult<Vec<f64>> {
+    let h = wgt.shape()[0];
+    let w = wgt.shape()[1];
+    check_beam_npix("ant_beam", nside, ant_beam.len())?;
+    let array_beam =
+        quarter_wgt2pattern_hemi(wgt.slice(s![h / 2..h, w / 2..w]).view(), d, freq_mhz, nside)?;
+
+    let mut total_beam: Vec<_> = array_beam
+        .iter()
+        .zip(ant_beam.iter())
+        .map(|(&a, &b)| a * b)
+        .collect();
+
+    let beam_norm = {
+        let (p, w) = get_interpol_ring(nside, SphCoord::<f64>::new(0.0, 0.0));
+        p.iter()
+            .zip(w.iter())
+            .map(|(&ipix, &w1)| total_beam[ipix] * w1)
+            .sum::<f64>()
+    };
+
+    total_beam.iter_mut().for_each(|x| {
+        *x /= beam_norm;
+    });
+    total_beam.resize(ant_beam.len(), 0.0);
+    Ok(total_beam)
 }
@@ -0,0 +1,307 @@
+use std::time::{Duration, Instant};
+
+use rand::Rng;
+
+use serde::{Deserialize, Serialize};
+
+use scorus::{linear_space::type_wrapper::LsVec, opt::pso::ParticleSwarmMaximizer};
+
+use scorus::healpix::utils::nside2npix;
+
+use crate::{
+    error::{check_len, DbfResult},
+    quantize::{check_bits, lattice_polish, quantize_unit},
+    utils::check_nside,
+};
+
+use super::{
+    deflattern_quarter_wgt, quarter_wgt2pattern,
+    solver::{Anneal, CmaEs, DiffEvolution, Pso, Search, Solver},
+};
+
+/// Misfit between the beam of a regular array with four-fold symmetric
+/// weights and a target beam. The free parameters are the quarter weights
+/// without the central one, which is fixed to 1.
+pub struct QuarterWgtObjective<'a> {
+    pub ant_beam: &'a [f64],
+    /// target beam normalised to unit sum
+    pub target_beam: Vec<f64>,
+    pub d: f64,
+    pub freq_mhz: f64,
+    pub nside: usize,
+    /// shape of the full weight grid
+    pub h: usize,
+    pub w: usize,
+}
+
+impl<'a> QuarterWgtObjective<'a> {
+    pub fn new(
+        ant_beam: &'a [f64],
+        target_beam: &[f64],
+        d: f64,
+        freq_mhz: f64,
+        nside: usize,
+        h: usize,
+        w: usize,
+    ) -> DbfResult<Self> {
+        check_nside(nside)?;
+        check_len("ant_beam", nside2npix(nside), ant_beam.len())?;
+        check_len("target_beam", nside2npix(nside), target_beam.len())?;
+        let norm = target_beam.iter().sum::<f64>();
+        Ok(QuarterWgtObjective {
+            ant_beam,
+            target_beam: target_beam.iter().map(|&x| x / norm).collect(),
+            d,
+            freq_mhz,
+            nside,
+            h,
+            w,
+        })
+    }
+
+    pub fn ndim(&self) -> usize {
+        (self.h + 1) / 2 * ((self.w + 1) / 2) - 1
+    }
+
+    pub fn total_beam(&self, x: &[f64]) -> DbfResult<Vec<f64>> {
+        let wgt = deflattern_quarter_wgt(x, self.h, self.w)?;
+        let array_beam = quarter_wgt2pattern(wgt.view(), self.d, self.freq_mhz, self.nside)?;
+        Ok(array_beam
+            .iter()
+            .zip(self.ant_beam.iter())
+            .map(|(&a, &b)| a * b)
+            .collect())
+    }
+
+    pub fn misfit(&self, x: &[f64]) -> DbfResult<f64> {
+        Ok(beam_misfit(&self.total_beam(x)?, &self.target_beam))
+    }
+}
+
+/// Squared difference between `total_beam` normalised to unit sum and
+/// `target_beam`, normalised already, times npix.
+pub fn beam_misfit(total_beam: &[f64], target_beam: &[f64]) -> f64 {
+    let npix = total_beam.len();
+    let norm = total_beam.iter().sum::<f64>();
+    total_beam
+        .iter()
+        .zip(target_beam.iter())
+        .map(|(&x, &y)| (x / norm - y).powi(2))
+        .sum::<f64>()
+        * npix as f64
+}
+
+/// Misfit minimised by `optimize_wgt` over weights in [0, 1], as the
+/// fitness `-log10(misfit)` maximised by the solvers.
+pub trait WgtObjective: Sync {
+    fn ndim(&self) -> usize;
+    fn misfit(&self, x: &[f64]) -> DbfResult<f64>;
+
+    fn fitness(&self, x: &[f64]) -> DbfResult<f64> {
+        Ok(-self.misfit(x)?.log10())
+    }
+}
+
+impl<'a> WgtObjective for QuarterWgtObjective<'a> {
+    fn ndim(&self) -> usize {
+        QuarterWgtObjective::ndim(self)
+    }
+
+    fn misfit(&self, x: &[f64]) -> DbfResult<f64> {
+        QuarterWgtObjective::misfit(self, x)
+    }
+}
+
+/// Limits on the solver on top of its own convergence check; no limit if not
+/// given.
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize)]
+pub struct StopRule {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_iter: Option<usize>,
+    /// wall-clock limit, in seconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_time_s: Option<f64>,
+    /// stop once the best fitness reaches this value
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target_fitness: Option<f64>,
+}
+
+impl StopRule {
+    /// Why the optimization should stop at `progress`, if it should.
+    pub fn reached(&self, progress: &Progress) -> Option<&'static str> {
+        if self.max_iter.is_some_and(|n| progress.iter >= n) {
+            Some("max iterations reached")
+        } else if self
+            .max_time_s
+            .is_some_and(|t| progress.elapsed.as_secs_f64() >= t)
+        {
+            Some("time limit reached")
+        } else if self.target_fitness.is_some_and(|f| progress.fitness >= f) {
+            Some("target fitness reached")
+        } else {
+            None
+        }
+    }
+}
+
+/// State of the solver after one iteration.
+#[derive(Clone, Copy, Debug)]
+pub struct Progress {
+    /// num of iterations done
+    pub iter: usize,
+    /// best fitness so far
+    pub fitness: f64,
+    /// rms distance of the population from its centroid, or the step size
+    /// of annealing
+    pub diversity: f64,
+    pub elapsed: Duration,
+}
+
+/// Best weights found by `optimize_wgt` and why the search ended.
+#[derive(Clone, Debug)]
+pub struct OptResult {
+    pub x: Vec<f64>,
+    /// the solver converged or a limit of the `StopRule` was reached
+    pub stop_reason: &'static str,
+}
+
+pub fn snap_to_lattice(x: &[f64], bits: Option<u32>) -> DbfResult<Vec<f64>> {
+    bits.map(check_bits).transpose()?;
+    Ok(snap(x, bits))
+}
+
+/// `snap_to_lattice` with checked `bits`.
+fn snap(x: &[f64], bits: Option<u32>) -> Vec<f64> {
+    if let Some(bits) = bits {
+        x.iter().map(|&x1| quantize_unit(x1, bits)).collect()
+    } else {
+        x.to_vec()
+    }
+}
+
+/// Maximises the fitness with `solver`, starting from `guess`, with every
+/// weight in [0, 1], until the solver converges or `stop` is reached. `npart`
+/// is the population size, or the num of proposals per iteration of
+/// annealing. With `bits` the weights are restricted to that bit depth and
+/// the result is polished on the integer lattice.
+/// `on_progress` is called with the best weights after every iteration, once
+/// the solver has a best point.
+#[allow(clippy::too_many_arguments)]
+pub fn optimize_wgt<O, R, F>(
+    obj: &O,
+    guess: &[f64],
+    npart: usize,
+    bits: Option<u32>,
+    solver: &Solver,
+    stop: &StopRule,
+    rng: &mut R,
+    on_progress: F,
+) -> DbfResult<OptResult>
+where
+    O: WgtObjective,
+    R: Rng,
+    F: FnMut(&[f64], &Progress),
+{
+    let ndim = obj.ndim();
+    check_len("guess", ndim, guess.len())?;
+    solver.check()?;
+    bits.map(check_bits).transpose()?;
+    if ndim == 0 {
+        // e.g. an array of at most 2 x 2 elements, all tied to the central one
+        return Ok(OptResult {
+            x: Vec::new(),
+            stop_reason: "no free weights",
+        });
+    }
+    // every point has the checked length, so the fitness cannot fail
+    let fitness = |x: &[f64]| obj.fitness(x).unwrap_or(f64::NEG_INFINITY);
+    let snapped = |x: &[f64]| fitness(&snap(x, bits));
+    let guess = snap(guess, bits);
+
+    let (opt_weights, stop_reason) = match *solver {
+        Solver::Pso {
+            omega,
+            phi_p,
+            phi_g,
+        } => {
+            let fobj = |x: &LsVec<f64, Vec<f64>>| snapped(&x.0);
+            let pso = ParticleSwarmMaximizer::new(
+                &fobj,
+                &LsVec(vec![0.0; ndim]),
+                &LsVec(vec![1.0; ndim]),
+                Some(LsVec(guess.clone())),
+                npart,
+                rng,
+            );
+            let pso = Pso {
+                pso,
+                omega,
+                phi_p,
+                phi_g,
+            };
+            search(pso, &guess, stop, rng, on_progress)
+        }
+        Solver::De { f, cr } => {
+            let de = DiffEvolution::new(&snapped, &guess, npart, f, cr, rng);
+            search(de, &guess, stop, rng, on_progress)
+        }
+        Solver::CmaEs { sigma0 } => {
+            let cma = CmaEs::new(&snapped, &guess, npart, sigma0);
+            search(cma, &guess, stop, rng, on_progress)
+        }
+        Solver::Anneal { t0, cooling, step } => {
+            let sa = Anneal::new(&snapped, &guess, npart, t0, cooling, step);
+            search(sa, &guess, stop, rng, on_progress)
+        }
+    };
+
+    let mut x = snap(&opt_weights, bits);
+    if let Some(bits) = bits {
+        lattice_polish(fitness, &mut x, bits)?;
+    }
+    Ok(OptResult { x, stop_reason })
+}
+
+/// Iterates `solver` until it converges or `stop` is reached, and returns
+/// the best point, or `guess` if there is none yet, with the reason to stop.
+/// The iteration and time limits hold even before the solver has a best
+/// point, the target fitness only once it has.
+fn search<S, R, F>(
+    mut solver: S,
+    guess: &[f64],
+    stop: &StopRule,
+    rng: &mut R,
+    mut on_progress: F,
+) -> (Vec<f64>, &'static str)
+where
+    S: Search,
+    R: Rng,
+    F: FnMut(&[f64], &Progress),
+{
+    let start = Instant::now();
+    let mut iter = 0;
+    let reason = loop {
+        if solver.converged() {
+            break "converged";
+        }
+        let best = solver.best();
+        let progress = Progress {
+            iter,
+            fitness: best.map_or(f64::NEG_INFINITY, |(_, f)| f),
+            diversity: solver.diversity(),
+            elapsed: start.elapsed(),
+        };
+        if let Some((x, _)) = best {
+            on_progress(x, &progress);
+        }
+        if let Some(reason) = stop.reached(&progress) {
+            break reason;
+        }
+        solver.step(rng);
+        iter += 1;
+    };
+    let x = solver
+        .best()
+        .map_or_else(|| guess.to_vec(), |(x, _)| x.to_vec());
+    (x, reason)
+}
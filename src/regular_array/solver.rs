@@ -0,0 +1,757 @@
+//! Global optimizers for the weight design, all maximising a fitness over
+//! [0, 1]^n, so that they can be compared on the same objective.
+
+use std::thread;
+
+use rand::Rng;
+
+use serde::{Deserialize, Serialize};
+
+use scorus::{linear_space::type_wrapper::LsVec, opt::pso::ParticleSwarmMaximizer};
+
+use crate::{
+    error::{DbfError, DbfResult},
+    tolerance::gaussian,
+};
+
+/// Solver and its hyperparameters; the population size is given separately.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Solver {
+    /// particle swarm, with the inertia and the weights of the personal and
+    /// global best
+    Pso {
+        #[serde(default = "default_omega")]
+        omega: f64,
+        #[serde(default = "default_phi_p")]
+        phi_p: f64,
+        #[serde(default = "default_phi_g")]
+        phi_g: f64,
+    },
+    /// differential evolution DE/rand/1/bin, with the differential weight
+    /// and the crossover probability
+    De {
+        #[serde(default = "default_de_f")]
+        f: f64,
+        #[serde(default = "default_de_cr")]
+        cr: f64,
+    },
+    /// CMA-ES with the standard learning rates, starting from the guess with
+    /// step size `sigma0`
+    CmaEs {
+        #[serde(default = "default_sigma0")]
+        sigma0: f64,
+    },
+    /// simulated annealing, the temperature falling from `t0` by `cooling`
+    /// every iteration of npart proposals, the gaussian proposal step
+    /// shrinking from `step` as sqrt(T / t0)
+    Anneal {
+        #[serde(default = "default_t0")]
+        t0: f64,
+        #[serde(default = "default_cooling")]
+        cooling: f64,
+        #[serde(default = "default_step")]
+        step: f64,
+    },
+}
+
+fn default_omega() -> f64 {
+    0.75
+}
+
+fn default_phi_p() -> f64 {
+    0.5
+}
+
+fn default_phi_g() -> f64 {
+    1.0
+}
+
+fn default_de_f() -> f64 {
+    0.7
+}
+
+fn default_de_cr() -> f64 {
+    0.9
+}
+
+fn default_sigma0() -> f64 {
+    0.2
+}
+
+fn default_t0() -> f64 {
+    0.1
+}
+
+fn default_cooling() -> f64 {
+    0.95
+}
+
+fn default_step() -> f64 {
+    0.1
+}
+
+impl Default for Solver {
+    fn default() -> Self {
+        Solver::Pso {
+            omega: default_omega(),
+            phi_p: default_phi_p(),
+            phi_g: default_phi_g(),
+        }
+    }
+}
+
+impl Solver {
+    pub const NAMES: [&'static str; 4] = ["pso", "de", "cma_es", "anneal"];
+
+    /// Solver `name`, one of `NAMES`, with default hyperparameters.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "pso" => Some(Solver::default()),
+            "de" => Some(Solver::De {
+                f: default_de_f(),
+                cr: default_de_cr(),
+            }),
+            "cma_es" => Some(Solver::CmaEs {
+                sigma0: default_sigma0(),
+            }),
+            "anneal" => Some(Solver::Anneal {
+                t0: default_t0(),
+                cooling: default_cooling(),
+                step: default_step(),
+            }),
+            _ => None,
+        }
+    }
+
+    pub fn check(&self) -> DbfResult<()> {
+        let check = |what, x: f64, ok: bool| {
+            if ok {
+                Ok(())
+            } else {
+                Err(DbfError::InvalidValue {
+                    what,
+                    reason: format!("out of range, found {}", x),
+                })
+            }
+        };
+        match *self {
+            Solver::Pso {
+                omega,
+                phi_p,
+                phi_g,
+            } => {
+                check("omega", omega, omega >= 0.0)?;
+                check("phi_p", phi_p, phi_p >= 0.0)?;
+                check("phi_g", phi_g, phi_g >= 0.0)
+            }
+            Solver::De { f, cr } => {
+                check("f", f, f > 0.0 && f <= 2.0)?;
+                check("cr", cr, (0.0..=1.0).contains(&cr))
+            }
+            Solver::CmaEs { sigma0 } => check("sigma0", sigma0, sigma0 > 0.0),
+            Solver::Anneal { t0, cooling, step } => {
+                check("t0", t0, t0 > 0.0)?;
+                check("cooling", cooling, cooling > 0.0 && cooling < 1.0)?;
+                check("step", step, step > 0.0)
+            }
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Solver::Pso { .. } => "pso",
+            Solver::De { .. } => "de",
+            Solver::CmaEs { .. } => "cma_es",
+            Solver::Anneal { .. } => "anneal",
+        }
+    }
+}
+
+/// One solver in progress.
+pub(super) trait Search {
+    fn step<R: Rng>(&mut self, rng: &mut R);
+    /// best point so far and its fitness
+    fn best(&self) -> Option<(&[f64], f64)>;
+    /// spread of the search, e.g. rms distance of the population from its
+    /// centroid
+    fn diversity(&self) -> f64;
+    fn converged(&self) -> bool;
+}
+
+/// Rms distance of `points` from their centroid.
+pub(super) fn diversity(points: &[&[f64]]) -> f64 {
+    let n = points.len() as f64;
+    let ndim = points.first().map_or(0, |x| x.len());
+    let centroid: Vec<f64> = (0..ndim)
+        .map(|i| points.iter().map(|x| x[i]).sum::<f64>() / n)
+        .collect();
+    (points
+        .iter()
+        .map(|x| {
+            x.iter()
+                .zip(centroid.iter())
+                .map(|(a, c)| (a - c).powi(2))
+                .sum::<f64>()
+        })
+        .sum::<f64>()
+        / n)
+        .sqrt()
+}
+
+/// Fitness of every point, spread over the available cores.
+fn eval_all<F>(fitness: &F, xs: &[Vec<f64>]) -> Vec<f64>
+where
+    F: Fn(&[f64]) -> f64 + Sync,
+{
+    let nthreads = thread::available_parallelism().map_or(1, |n| n.get());
+    let chunk = xs.len().div_ceil(nthreads).max(1);
+    thread::scope(|s| {
+        let handles: Vec<_> = xs
+            .chunks(chunk)
+            .map(|c| s.spawn(move || c.iter().map(|x| fitness(x)).collect::<Vec<_>>()))
+            .collect();
+        handles
+            .into_iter()
+            .flat_map(|h| h.join().unwrap())
+            .collect()
+    })
+}
+
+fn clamp_unit(x: &mut [f64]) {
+    for x1 in x.iter_mut() {
+        *x1 = x1.clamp(0.0, 1.0);
+    }
+}
+
+/// Index of the largest fitness.
+fn argmax(fit: &[f64]) -> usize {
+    (0..fit.len())
+        .max_by(|&i, &j| fit[i].total_cmp(&fit[j]))
+        .unwrap_or(0)
+}
+
+pub(super) struct Pso<'a> {
+    pub pso: ParticleSwarmMaximizer<'a, LsVec<f64, Vec<f64>>, f64>,
+    pub omega: f64,
+    pub phi_p: f64,
+    pub phi_g: f64,
+}
+
+impl<'a> Search for Pso<'a> {
+    fn step<R: Rng>(&mut self, rng: &mut R) {
+        self.pso.sample(rng, self.omega, self.phi_p, self.phi_g);
+    }
+
+    fn best(&self) -> Option<(&[f64], f64)> {
+        self.pso
+            .gbest
+            .as_ref()
+            .map(|p| (&p.position.0[..], p.fitness))
+    }
+
+    fn diversity(&self) -> f64 {
+        let points: Vec<&[f64]> = self.pso.swarm.iter().map(|p| &p.position.0[..]).collect();
+        diversity(&points)
+    }
+
+    fn converged(&self) -> bool {
+        self.pso.converged(0.7, 1e-9, 1e-9)
+    }
+}
+
+pub(super) struct DiffEvolution<'a, F> {
+    fitness: &'a F,
+    pop: Vec<Vec<f64>>,
+    fit: Vec<f64>,
+    f: f64,
+    cr: f64,
+}
+
+impl<'a, F> DiffEvolution<'a, F>
+where
+    F: Fn(&[f64]) -> f64 + Sync,
+{
+    /// The guess and `npart - 1` uniformly random members.
+    pub fn new<R: Rng>(
+        fitness: &'a F,
+        guess: &[f64],
+        npart: usize,
+        f: f64,
+        cr: f64,
+        rng: &mut R,
+    ) -> Self {
+        let npart = npart.max(4);
+        let pop: Vec<Vec<f64>> = std::iter::once(guess.to_vec())
+            .chain((1..npart).map(|_| (0..guess.len()).map(|_| rng.gen()).collect()))
+            .collect();
+        let fit = eval_all(fitness, &pop);
+        DiffEvolution {
+            fitness,
+            pop,
+            fit,
+            f,
+            cr,
+        }
+    }
+}
+
+impl<'a, F> Search for DiffEvolution<'a, F>
+where
+    F: Fn(&[f64]) -> f64 + Sync,
+{
+    fn step<R: Rng>(&mut self, rng: &mut R) {
+        let (npart, ndim) = (self.pop.len(), self.pop[0].len());
+        let trials: Vec<Vec<f64>> = (0..npart)
+            .map(|i| {
+                // three distinct members other than i
+                let mut abc = [i; 3];
+                for k in 0..3 {
+                    abc[k] = loop {
+                        let j = rng.gen_range(0..npart);
+                        if j != i && !abc[..k].contains(&j) {
+                            break j;
+                        }
+                    };
+                }
+                let [a, b, c] = abc;
+                let jrand = rng.gen_range(0..ndim);
+                let mut y: Vec<f64> = (0..ndim)
+                    .map(|k| {
+                        if k == jrand || rng.gen::<f64>() < self.cr {
+                            self.pop[a][k] + self.f * (self.pop[b][k] - self.pop[c][k])
+                        } else {
+                            self.pop[i][k]
+                        }
+                    })
+                    .collect();
+                clamp_unit(&mut y);
+                y
+            })
+            .collect();
+        let fit = eval_all(self.fitness, &trials);
+        for (i, (y, fy)) in trials.into_iter().zip(fit).enumerate() {
+            if fy >= self.fit[i] {
+                self.pop[i] = y;
+                self.fit[i] = fy;
+            }
+        }
+    }
+
+    fn best(&self) -> Option<(&[f64], f64)> {
+        let i = argmax(&self.fit);
+        Some((&self.pop[i], self.fit[i]))
+    }
+
+    fn diversity(&self) -> f64 {
+        let points: Vec<&[f64]> = self.pop.iter().map(|x| &x[..]).collect();
+        diversity(&points)
+    }
+
+    fn converged(&self) -> bool {
+        let max = self.fit.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+        let min = self.fit.iter().cloned().fold(f64::INFINITY, f64::min);
+        max - min < 1e-9 && self.diversity() < 1e-9
+    }
+}
+
+/// Eigenvalues and eigenvectors (as columns) of a symmetric `n` x `n` row
+/// major matrix, by cyclic Jacobi rotations.
+fn sym_eigen(a: &[f64], n: usize) -> (Vec<f64>, Vec<f64>) {
+    let mut a = a.to_vec();
+    let mut v = vec![0.0; n * n];
+    for i in 0..n {
+        v[i * n + i] = 1.0;
+    }
+    for _ in 0..50 {
+        let diag: f64 = (0..n).map(|i| a[i * n + i].powi(2)).sum();
+        let off: f64 = (0..n)
+            .flat_map(|p| (p + 1..n).map(move |q| (p, q)))
+            .map(|(p, q)| a[p * n + q].powi(2))
+            .sum();
+        if off <= 1e-30 * diag {
+            break;
+        }
+        for p in 0..n {
+            for q in p + 1..n {
+                let apq = a[p * n + q];
+                if apq == 0.0 {
+                    continue;
+                }
+                let theta = (a[q * n + q] - a[p * n + p]) / (2.0 * apq);
+                let t = theta.signum() / (theta.abs() + (theta * theta + 1.0).sqrt());
+                let c = 1.0 / (t * t + 1.0).sqrt();
+                let s = t * c;
+                for k in 0..n {
+                    let (akp, akq) = (a[k * n + p], a[k * n + q]);
+                    a[k * n + p] = c * akp - s * akq;
+                    a[k * n + q] = s * akp + c * akq;
+                }
+                for k in 0..n {
+                    let (apk, aqk) = (a[p * n + k], a[q * n + k]);
+                    a[p * n + k] = c * apk - s * aqk;
+                    a[q * n + k] = s * apk + c * aqk;
+                }
+                for k in 0..n {
+                    let (vkp, vkq) = (v[k * n + p], v[k * n + q]);
+                    v[k * n + p] = c * vkp - s * vkq;
+                    v[k * n + q] = s * vkp + c * vkq;
+                }
+            }
+        }
+    }
+    ((0..n).map(|i| a[i * n + i]).collect(), v)
+}
+
+pub(super) struct CmaEs<'a, F> {
+    fitness: &'a F,
+    n: usize,
+    lambda: usize,
+    weights: Vec<f64>,
+    mueff: f64,
+    mean: Vec<f64>,
+    sigma: f64,
+    /// covariance, row major
+    c: Vec<f64>,
+    pc: Vec<f64>,
+    ps: Vec<f64>,
+    gen: usize,
+    /// last population, clamped to the bounds
+    xs: Vec<Vec<f64>>,
+    best: (Vec<f64>, f64),
+}
+
+impl<'a, F> CmaEs<'a, F>
+where
+    F: Fn(&[f64]) -> f64 + Sync,
+{
+    pub fn new(fitness: &'a F, guess: &[f64], npart: usize, sigma0: f64) -> Self {
+        let n = guess.len();
+        let lambda = npart.max(4);
+        let mu = lambda / 2;
+        let w: Vec<f64> = (0..mu)
+            .map(|i| (mu as f64 + 0.5).ln() - (i as f64 + 1.0).ln())
+            .collect();
+        let sum = w.iter().sum::<f64>();
+        let weights: Vec<f64> = w.iter().map(|x| x / sum).collect();
+        let mueff = 1.0 / weights.iter().map(|x| x * x).sum::<f64>();
+        let mut c = vec![0.0; n * n];
+        for i in 0..n {
+            c[i * n + i] = 1.0;
+        }
+        let f0 = fitness(guess);
+        CmaEs {
+            fitness,
+            n,
+            lambda,
+            weights,
+            mueff,
+            mean: guess.to_vec(),
+            sigma: sigma0,
+            c,
+            pc: vec![0.0; n],
+            ps: vec![0.0; n],
+            gen: 0,
+            xs: vec![guess.to_vec()],
+            best: (guess.to_vec(), f0),
+        }
+    }
+
+    /// Largest std along a principal axis.
+    fn max_std(&self) -> f64 {
+        (0..self.n)
+            .map(|i| self.c[i * self.n + i])
+            .fold(0.0, f64::max)
+            .sqrt()
+            * self.sigma
+    }
+}
+
+impl<'a, F> Search for CmaEs<'a, F>
+where
+    F: Fn(&[f64]) -> f64 + Sync,
+{
+    fn step<R: Rng>(&mut self, rng: &mut R) {
+        let n = self.n;
+        let nf = n as f64;
+        let mueff = self.mueff;
+        let cc = (4.0 + mueff / nf) / (nf + 4.0 + 2.0 * mueff / nf);
+        let cs = (mueff + 2.0) / (nf + mueff + 5.0);
+        let c1 = 2.0 / ((nf + 1.3).powi(2) + mueff);
+        let cmu = (1.0 - c1).min(2.0 * (mueff - 2.0 + 1.0 / mueff) / ((nf + 2.0).powi(2) + mueff));
+        let damps = 1.0 + 2.0 * (((mueff - 1.0) / (nf + 1.0)).sqrt() - 1.0).max(0.0) + cs;
+        let chi_n = nf.sqrt() * (1.0 - 1.0 / (4.0 * nf) + 1.0 / (21.0 * nf * nf));
+
+        let (eigvals, b) = sym_eigen(&self.c, n);
+        let d: Vec<f64> = eigvals.iter().map(|&e| e.max(1e-300).sqrt()).collect();
+
+        // y = B D z, x = mean + sigma y
+        let ys: Vec<Vec<f64>> = (0..self.lambda)
+            .map(|_| {
+                let dz: Vec<f64> = d.iter().map(|&di| di * gaussian(rng)).collect();
+                (0..n)
+                    .map(|i| (0..n).map(|j| b[i * n + j] * dz[j]).sum())
+                    .collect()
+            })
+            .collect();
+        let unbounded: Vec<Vec<f64>> = ys
+            .iter()
+            .map(|y| {
+                self.mean
+                    .iter()
+                    .zip(y.iter())
+                    .map(|(m, y1)| m + self.sigma * y1)
+                    .collect()
+            })
+            .collect();
+        let xs: Vec<Vec<f64>> = unbounded
+            .iter()
+            .map(|x| {
+                let mut x = x.clone();
+                clamp_unit(&mut x);
+                x
+            })
+            .collect();
+        let fit = eval_all(self.fitness, &xs);
+        // ranked by the fitness at the nearest point in bounds less the
+        // squared distance to it, so that the search is pulled back inside
+        // rather than drifting where the fitness is flat
+        let penalized: Vec<f64> = (0..self.lambda)
+            .map(|k| {
+                fit[k]
+                    - unbounded[k]
+                        .iter()
+                        .zip(xs[k].iter())
+                        .map(|(a, b)| (a - b).powi(2))
+                        .sum::<f64>()
+            })
+            .collect();
+        let mut order: Vec<usize> = (0..self.lambda).collect();
+        order.sort_by(|&i, &j| penalized[j].total_cmp(&penalized[i]));
+        let ibest = argmax(&fit);
+        if fit[ibest] > self.best.1 {
+            self.best = (xs[ibest].clone(), fit[ibest]);
+        }
+
+        let yw: Vec<f64> = (0..n)
+            .map(|k| {
+                self.weights
+                    .iter()
+                    .zip(order.iter())
+                    .map(|(w, &i)| w * ys[i][k])
+                    .sum()
+            })
+            .collect();
+        for (m, y1) in self.mean.iter_mut().zip(yw.iter()) {
+            *m = (*m + self.sigma * y1).clamp(0.0, 1.0);
+        }
+
+        // C^-1/2 yw = B D^-1 B^T yw
+        let bty: Vec<f64> = (0..n)
+            .map(|j| (0..n).map(|i| b[i * n + j] * yw[i]).sum::<f64>() / d[j])
+            .collect();
+        let c_inv_sqrt_yw: Vec<f64> = (0..n)
+            .map(|i| (0..n).map(|j| b[i * n + j] * bty[j]).sum())
+            .collect();
+        let a = (cs * (2.0 - cs) * mueff).sqrt();
+        for (p, z) in self.ps.iter_mut().zip(c_inv_sqrt_yw.iter()) {
+            *p = (1.0 - cs) * *p + a * z;
+        }
+        let ps_norm = self.ps.iter().map(|x| x * x).sum::<f64>().sqrt();
+        self.gen += 1;
+        let hsig = ps_norm / (1.0 - (1.0 - cs).powi(2 * self.gen as i32)).sqrt() / chi_n
+            < 1.4 + 2.0 / (nf + 1.0);
+        let hsig = if hsig { 1.0 } else { 0.0 };
+        let a = (cc * (2.0 - cc) * mueff).sqrt();
+        for (p, y1) in self.pc.iter_mut().zip(yw.iter()) {
+            *p = (1.0 - cc) * *p + hsig * a * y1;
+        }
+
+        let dh = (1.0 - hsig) * cc * (2.0 - cc);
+        for i in 0..n {
+            for j in 0..n {
+                let rank_mu: f64 = self
+                    .weights
+                    .iter()
+                    .zip(order.iter())
+                    .map(|(w, &k)| w * ys[k][i] * ys[k][j])
+                    .sum();
+                let cij = self.c[i * n + j];
+                self.c[i * n + j] = (1.0 - c1 - cmu) * cij
+                    + c1 * (self.pc[i] * self.pc[j] + dh * cij)
+                    + cmu * rank_mu;
+            }
+        }
+        self.sigma *= (cs / damps * (ps_norm / chi_n - 1.0)).exp();
+        self.xs = xs;
+    }
+
+    fn best(&self) -> Option<(&[f64], f64)> {
+        Some((&self.best.0, self.best.1))
+    }
+
+    fn diversity(&self) -> f64 {
+        let points: Vec<&[f64]> = self.xs.iter().map(|x| &x[..]).collect();
+        diversity(&points)
+    }
+
+    fn converged(&self) -> bool {
+        self.max_std() < 1e-9
+    }
+}
+
+pub(super) struct Anneal<'a, F> {
+    fitness: &'a F,
+    x: Vec<f64>,
+    fx: f64,
+    best: (Vec<f64>, f64),
+    nprop: usize,
+    t0: f64,
+    t: f64,
+    cooling: f64,
+    step: f64,
+}
+
+impl<'a, F> Anneal<'a, F>
+where
+    F: Fn(&[f64]) -> f64 + Sync,
+{
+    pub fn new(
+        fitness: &'a F,
+        guess: &[f64],
+        npart: usize,
+        t0: f64,
+        cooling: f64,
+        step: f64,
+    ) -> Self {
+        let fx = fitness(guess);
+        Anneal {
+            fitness,
+            x: guess.to_vec(),
+            fx,
+            best: (guess.to_vec(), fx),
+            nprop: npart.max(1),
+            t0,
+            t: t0,
+            cooling,
+            step,
+        }
+    }
+
+    fn step_size(&self) -> f64 {
+        self.step * (self.t / self.t0).sqrt()
+    }
+}
+
+impl<'a, F> Search for Anneal<'a, F>
+where
+    F: Fn(&[f64]) -> f64 + Sync,
+{
+    fn step<R: Rng>(&mut self, rng: &mut R) {
+        let step = self.step_size();
+        for _ in 0..self.nprop {
+            let mut y: Vec<f64> = self.x.iter().map(|x| x + step * gaussian(rng)).collect();
+            clamp_unit(&mut y);
+            let fy = (self.fitness)(&y);
+            if fy >= self.fx || rng.gen::<f64>() < ((fy - self.fx) / self.t).exp() {
+                self.x = y;
+                self.fx = fy;
+                if fy > self.best.1 {
+                    self.best = (self.x.clone(), fy);
+                }
+            }
+        }
+        self.t *= self.cooling;
+    }
+
+    fn best(&self) -> Option<(&[f64], f64)> {
+        Some((&self.best.0, self.best.1))
+    }
+
+    fn diversity(&self) -> f64 {
+        self.step_size() * (self.x.len() as f64).sqrt()
+    }
+
+    fn converged(&self) -> bool {
+        self.t < self.t0 * 1e-9
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+
+    const CENTRE: [f64; 3] = [0.3, 0.6, 0.45];
+
+    /// Negated squared distance from `CENTRE`, maximal at 0.
+    fn sphere(x: &[f64]) -> f64 {
+        -x.iter()
+            .zip(CENTRE.iter())
+            .map(|(a, c)| (a - c).powi(2))
+            .sum::<f64>()
+    }
+
+    fn run<S: Search>(mut solver: S, niter: usize) -> Vec<f64> {
+        let mut rng = StdRng::seed_from_u64(1);
+        for _ in 0..niter {
+            if solver.converged() {
+                break;
+            }
+            solver.step(&mut rng);
+        }
+        solver.best().unwrap().0.to_vec()
+    }
+
+    fn assert_near_centre(x: &[f64], tol: f64) {
+        for (a, c) in x.iter().zip(CENTRE.iter()) {
+            assert!((a - c).abs() < tol, "{:?} is not near {:?}", x, CENTRE);
+        }
+    }
+
+    #[test]
+    fn de_converges_on_sphere() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let de = DiffEvolution::new(&sphere, &[0.9; 3], 20, 0.6, 0.9, &mut rng);
+        assert_near_centre(&run(de, 500), 1e-4);
+    }
+
+    #[test]
+    fn cma_es_converges_on_sphere() {
+        let cma = CmaEs::new(&sphere, &[0.9; 3], 10, 0.3);
+        assert_near_centre(&run(cma, 500), 1e-4);
+    }
+
+    #[test]
+    fn anneal_converges_on_sphere() {
+        let sa = Anneal::new(&sphere, &[0.9; 3], 10, 0.1, 0.95, 0.2);
+        assert_near_centre(&run(sa, 500), 1e-2);
+    }
+
+    #[test]
+    fn sym_eigen_of_known_matrix() {
+        // second difference matrix, with eigenvalues 2 - sqrt(2), 2 and
+        // 2 + sqrt(2)
+        let a = [2.0, -1.0, 0.0, -1.0, 2.0, -1.0, 0.0, -1.0, 2.0];
+        let (eigvals, v) = sym_eigen(&a, 3);
+        let mut sorted = eigvals.clone();
+        sorted.sort_by(f64::total_cmp);
+        let sqrt2 = 2_f64.sqrt();
+        for (l, expected) in sorted.iter().zip([2.0 - sqrt2, 2.0, 2.0 + sqrt2]) {
+            assert!((l - expected).abs() < 1e-12);
+        }
+        // A v_k = l_k v_k for every column, and the columns are orthonormal
+        for k in 0..3 {
+            for i in 0..3 {
+                let av: f64 = (0..3).map(|j| a[i * 3 + j] * v[j * 3 + k]).sum();
+                assert!((av - eigvals[k] * v[i * 3 + k]).abs() < 1e-12);
+            }
+            for m in 0..3 {
+                let dot: f64 = (0..3).map(|i| v[i * 3 + k] * v[i * 3 + m]).sum();
+                let expected = if k == m { 1.0 } else { 0.0 };
+                assert!((dot - expected).abs() < 1e-12);
+            }
+        }
+    }
+}
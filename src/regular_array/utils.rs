@@ -1,5 +1,7 @@
 use ndarray::{s, Array1, Array2, ArrayView2};
 
+use crate::error::{DbfError, DbfResult};
+
 pub fn full2quarter<T>(full: ArrayView2<T>) -> Array2<T>
 where
     T: Copy,
@@ -9,10 +11,17 @@ where
     full.slice(s![h / 2..h, w / 2..w]).to_owned()
 }
 
-pub fn quarter2full<T>(quarter: ArrayView2<T>) -> Array2<T>
+pub fn quarter2full<T>(quarter: ArrayView2<T>) -> DbfResult<Array2<T>>
 where
     T: Copy + Default,
 {
+    if quarter.is_empty() {
+        return Err(DbfError::InvalidShape {
+            what: "quarter wgt",
+            shape: quarter.shape().to_vec(),
+            reason: "must not be empty",
+        });
+    }
     let h = quarter.shape()[0] * 2;
     let w = quarter.shape()[1] * 2;
     let mut full = Array2::<T>::default((h, w));
@@ -25,7 +34,7 @@ where
         .assign(&quarter.slice(s![.., 1..w / 2]));
     full.slice_mut(s![1..h/2;-1, w/2..w])
         .assign(&quarter.slice(s![1..h / 2, ..]));
-    full.slice(s![1.., 1..]).to_owned()
+    Ok(full.slice(s![1.., 1..]).to_owned())
 
     
 }
@@ -34,13 +43,13 @@ pub fn flattern_quarter_wgt(wgt: ArrayView2<f64>) -> Vec<f64> {
     wgt.iter().skip(1).cloned().collect()
 }
 
-pub fn deflattern_quarter_wgt(wgt: &[f64], h: usize, w: usize) -> Array2<f64> {
+pub fn deflattern_quarter_wgt(wgt: &[f64], h: usize, w: usize) -> DbfResult<Array2<f64>> {
     let one = [1.0];
-    if wgt.len()+1!=(h+1)/2*((w+1)/2)
-    {
-        println!("{} {} {}", wgt.len()+1, h, w);
-    }
     Array1::from_iter(one.iter().chain(wgt.iter()).cloned())
         .into_shape(((h+1) / 2, (w+1) / 2))
-        .unwrap()
+        .map_err(|_| DbfError::LengthMismatch {
+            what: "flattened quarter wgt",
+            expected: (h+1)/2*((w+1)/2)-1,
+            found: wgt.len(),
+        })
 }
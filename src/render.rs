@@ -0,0 +1,162 @@
+use std::io::Write;
+
+use crate::{
+    error::{DbfError, DbfResult},
+    projection::{project, raster_dirs, Projection},
+};
+
+const BACKGROUND: [u8; 3] = [255, 255, 255];
+const GRATICULE: [u8; 3] = [160, 160, 160];
+
+/// viridis sampled at 0, 0.25, 0.5, 0.75 and 1
+const COLORMAP: [[f64; 3]; 5] = [
+    [68.0, 1.0, 84.0],
+    [59.0, 82.0, 139.0],
+    [33.0, 145.0, 140.0],
+    [94.0, 201.0, 98.0],
+    [253.0, 231.0, 37.0],
+];
+
+#[derive(Clone, Copy, Debug)]
+pub struct RenderCfg {
+    pub projection: Projection,
+    pub width: usize,
+    pub height: usize,
+    /// dB below the peak mapped to the bottom of the color scale
+    pub db_range: f64,
+    /// spacing of the az/el graticule in deg, no graticule if None
+    pub graticule_deg: Option<f64>,
+}
+
+fn colormap(t: f64) -> [u8; 3] {
+    let x = t.clamp(0.0, 1.0) * (COLORMAP.len() - 1) as f64;
+    let i = (x.floor() as usize).min(COLORMAP.len() - 2);
+    let f = x - i as f64;
+    let mut rgb = [0; 3];
+    for (k, c) in rgb.iter_mut().enumerate() {
+        *c = (COLORMAP[i][k] * (1.0 - f) + COLORMAP[i + 1][k] * f).round() as u8;
+    }
+    rgb
+}
+
+/// Converts a raster to dB relative to its peak; NaN stays NaN and
+/// non-positive values become -inf.
+pub fn to_db(raster: &[f64]) -> Vec<f64> {
+    let peak = raster
+        .iter()
+        .cloned()
+        .filter(|x| x.is_finite())
+        .fold(0.0, f64::max);
+    raster
+        .iter()
+        .map(|&x| {
+            if x.is_nan() {
+                f64::NAN
+            } else if x > 0.0 && peak > 0.0 {
+                10.0 * (x / peak).log10()
+            } else {
+                f64::NEG_INFINITY
+            }
+        })
+        .collect()
+}
+
+/// Raster positions crossed by a line of constant elevation or az.
+fn graticule_mask(cfg: &RenderCfg, step_deg: f64) -> Vec<bool> {
+    let (w, h) = (cfg.width, cfg.height);
+    // (elevation, elevation cell, az cell) of every raster position
+    let cells: Vec<Option<(f64, f64, f64)>> = raster_dirs(cfg.projection, w, h)
+        .into_iter()
+        .map(|dir| {
+            dir.map(|d| {
+                let el = 90.0 - d.pol.to_degrees();
+                let az = (90.0 - d.az.to_degrees()).rem_euclid(360.0);
+                (el, (el / step_deg).floor(), (az / step_deg).floor())
+            })
+        })
+        .collect();
+    let crosses = |a: usize, b: usize| match (cells[a], cells[b]) {
+        // az lines are not drawn close to the poles where they converge
+        (Some(ca), Some(cb)) => ca.1 != cb.1 || (ca.2 != cb.2 && ca.0.abs() < 90.0 - step_deg),
+        _ => false,
+    };
+    let mut mask = vec![false; w * h];
+    for j in 0..h {
+        for i in 0..w {
+            let k = j * w + i;
+            if (i + 1 < w && crosses(k, k + 1)) || (j + 1 < h && crosses(k, k + w)) {
+                mask[k] = true;
+            }
+        }
+    }
+    mask
+}
+
+/// Renders a healpix map to an RGB8 raster, row by row from the top, with
+/// the color scale running from `-db_range` to 0 dB relative to the peak.
+pub fn render(map: &[f64], cfg: &RenderCfg) -> DbfResult<Vec<u8>> {
+    if cfg.width == 0 || cfg.height == 0 {
+        return Err(DbfError::InvalidShape {
+            what: "image",
+            shape: vec![cfg.height, cfg.width],
+            reason: "width and height must be positive",
+        });
+    }
+    if cfg.db_range.is_nan() || cfg.db_range <= 0.0 {
+        return Err(DbfError::InvalidValue {
+            what: "db_range",
+            reason: format!("must be positive, found {}", cfg.db_range),
+        });
+    }
+    if let Some(step) = cfg.graticule_deg {
+        if step.is_nan() || step <= 0.0 {
+            return Err(DbfError::InvalidValue {
+                what: "graticule",
+                reason: format!("spacing must be positive, found {}", step),
+            });
+        }
+    }
+
+    let db = to_db(&project(map, cfg.projection, cfg.width, cfg.height)?);
+    let mask = cfg
+        .graticule_deg
+        .map(|step| graticule_mask(cfg, step))
+        .unwrap_or_else(|| vec![false; db.len()]);
+
+    let mut rgb = Vec::with_capacity(db.len() * 3);
+    for (&x, &line) in db.iter().zip(mask.iter()) {
+        rgb.extend_from_slice(&if x.is_nan() {
+            BACKGROUND
+        } else if line {
+            GRATICULE
+        } else {
+            colormap(1.0 + x / cfg.db_range)
+        });
+    }
+    Ok(rgb)
+}
+
+/// Horizontal color bar of the scale, `width` x `height` RGB8 pixels.
+pub fn colorbar(width: usize, height: usize) -> Vec<u8> {
+    let row: Vec<u8> = (0..width)
+        .flat_map(|i| colormap((i as f64 + 0.5) / width as f64))
+        .collect();
+    row.repeat(height)
+}
+
+/// Writes an RGB image, with `text` as key and value text chunks.
+pub fn write_png<W: Write>(
+    w: W,
+    width: usize,
+    height: usize,
+    rgb: &[u8],
+    text: &[(String, String)],
+) -> Result<(), png::EncodingError> {
+    let mut encoder = png::Encoder::new(w, width as u32, height as u32);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    for (key, value) in text {
+        encoder.add_text_chunk(key.clone(), value.clone())?;
+    }
+    encoder.write_header()?.write_image_data(rgb)
+}
@@ -0,0 +1,291 @@
+//! Spherical harmonic transforms of RING maps, for band-limiting and
+//! smoothing beams and skies and for beam window functions.
+
+use std::f64::consts::PI;
+
+use num::{Complex, Zero};
+
+use rustfft::FftPlanner;
+
+use scorus::healpix::utils::{nside2npix, nside2nring};
+
+use crate::{
+    error::{check_len, DbfError, DbfResult},
+    utils::{check_nside, checked_npix2nside},
+};
+
+/// Harmonic coefficients of a real map up to `lmax`, for m >= 0 only, in
+/// the healpy order (`index`).
+#[derive(Clone, Debug)]
+pub struct Alm {
+    pub lmax: usize,
+    pub data: Vec<Complex<f64>>,
+}
+
+impl Alm {
+    pub fn new(lmax: usize) -> Self {
+        Alm {
+            lmax,
+            data: vec![Complex::zero(); (lmax + 1) * (lmax + 2) / 2],
+        }
+    }
+
+    pub fn index(&self, l: usize, m: usize) -> usize {
+        m * (2 * self.lmax + 1 - m) / 2 + l
+    }
+
+    pub fn get(&self, l: usize, m: usize) -> Complex<f64> {
+        self.data[self.index(l, m)]
+    }
+
+    /// Multiplies every a_lm by `fl[l]`, zeroing l beyond `fl`.
+    pub fn scale_l(&mut self, fl: &[f64]) {
+        for m in 0..=self.lmax {
+            for l in m..=self.lmax {
+                let i = self.index(l, m);
+                self.data[i] *= fl.get(l).cloned().unwrap_or(0.0);
+            }
+        }
+    }
+}
+
+/// Ring `iring` (1-based) of a RING map: first pixel, num of pixels, z and
+/// az of the first pixel.
+fn ring_info(nside: usize, iring: usize) -> (usize, usize, f64, f64) {
+    let npix = nside2npix(nside);
+    let ncap = 2 * nside * (nside - 1);
+    let ns = nside as f64;
+    if iring < nside {
+        let i = iring as f64;
+        (
+            2 * iring * (iring - 1),
+            4 * iring,
+            1.0 - i * i / (3.0 * ns * ns),
+            PI / (4.0 * i),
+        )
+    } else if iring <= 3 * nside {
+        let phi0 = if (iring - nside) % 2 == 0 {
+            PI / (4.0 * ns)
+        } else {
+            0.0
+        };
+        (
+            ncap + (iring - nside) * 4 * nside,
+            4 * nside,
+            4.0 / 3.0 - 2.0 * iring as f64 / (3.0 * ns),
+            phi0,
+        )
+    } else {
+        let ir = 4 * nside - iring;
+        let i = ir as f64;
+        (
+            npix - 2 * ir * (ir + 1),
+            4 * ir,
+            i * i / (3.0 * ns * ns) - 1.0,
+            PI / (4.0 * i),
+        )
+    }
+}
+
+/// Normalised associated Legendre functions lambda_lm(z) for l in m..=lmax,
+/// such that Y_lm = lambda_lm(cos theta) e^{i m phi}.
+fn legendre_m(lmax: usize, m: usize, z: f64, out: &mut Vec<f64>) {
+    out.clear();
+    let sin_theta = (1.0 - z * z).max(0.0).sqrt();
+    let mut lmm = (1.0 / (4.0 * PI)).sqrt();
+    for k in 1..=m {
+        lmm *= -((2 * k + 1) as f64 / (2 * k) as f64).sqrt() * sin_theta;
+    }
+    out.push(lmm);
+    if m == lmax {
+        return;
+    }
+    out.push(z * ((2 * m + 3) as f64).sqrt() * lmm);
+    for l in m + 2..=lmax {
+        let (lf, mf) = (l as f64, m as f64);
+        let a = ((4.0 * lf * lf - 1.0) / (lf * lf - mf * mf)).sqrt();
+        let b = (((lf - 1.0).powi(2) - mf * mf) / (4.0 * (lf - 1.0).powi(2) - 1.0)).sqrt();
+        let n = out.len();
+        out.push(a * (z * out[n - 1] - b * out[n - 2]));
+    }
+}
+
+/// Harmonic coefficients of a RING map up to `lmax`, by quadrature with
+/// equal pixel weights.
+pub fn map2alm(map: &[f64], lmax: usize) -> DbfResult<Alm> {
+    let nside = checked_npix2nside(map.len())?;
+    let omega = 4.0 * PI / map.len() as f64;
+    let mut alm = Alm::new(lmax);
+    let mut planner = FftPlanner::<f64>::new();
+    let mut lambda = Vec::with_capacity(lmax + 1);
+    for iring in 1..=nside2nring(nside) {
+        let (start, nphi, z, phi0) = ring_info(nside, iring);
+        let mut buf: Vec<Complex<f64>> = map[start..start + nphi]
+            .iter()
+            .map(|&x| Complex::new(x, 0.0))
+            .collect();
+        planner.plan_fft_forward(nphi).process(&mut buf);
+        for m in 0..=lmax {
+            let fm = buf[m % nphi] * Complex::from_polar(omega, -(m as f64) * phi0);
+            legendre_m(lmax, m, z, &mut lambda);
+            for (l, &lam) in (m..=lmax).zip(lambda.iter()) {
+                let i = alm.index(l, m);
+                alm.data[i] += fm * lam;
+            }
+        }
+    }
+    Ok(alm)
+}
+
+/// Num of Jacobi iterations used by `smooth`, as the healpy default.
+pub const DEFAULT_ITER: usize = 3;
+
+/// `map2alm` refined by `niter` Jacobi iterations on the residual map, which
+/// removes most of the quadrature error for band-limited maps.
+pub fn map2alm_iter(map: &[f64], lmax: usize, niter: usize) -> DbfResult<Alm> {
+    let nside = checked_npix2nside(map.len())?;
+    let mut alm = map2alm(map, lmax)?;
+    for _ in 0..niter {
+        let resid: Vec<f64> = alm2map(&alm, nside)?
+            .iter()
+            .zip(map.iter())
+            .map(|(&a, &b)| b - a)
+            .collect();
+        let d = map2alm(&resid, lmax)?;
+        alm.data
+            .iter_mut()
+            .zip(d.data.iter())
+            .for_each(|(a, &b)| *a += b);
+    }
+    Ok(alm)
+}
+
+/// RING map at `nside` synthesised from `alm`.
+pub fn alm2map(alm: &Alm, nside: usize) -> DbfResult<Vec<f64>> {
+    check_nside(nside)?;
+    let lmax = alm.lmax;
+    let mut map = vec![0.0; nside2npix(nside)];
+    let mut planner = FftPlanner::<f64>::new();
+    let mut lambda = Vec::with_capacity(lmax + 1);
+    for iring in 1..=nside2nring(nside) {
+        let (start, nphi, z, phi0) = ring_info(nside, iring);
+        let mut buf = vec![Complex::<f64>::zero(); nphi];
+        for m in 0..=lmax {
+            legendre_m(lmax, m, z, &mut lambda);
+            let gm: Complex<f64> = (m..=lmax)
+                .zip(lambda.iter())
+                .map(|(l, &lam)| alm.get(l, m) * lam)
+                .sum();
+            let gm = gm * Complex::from_polar(1.0, m as f64 * phi0);
+            buf[m % nphi] += gm;
+            if m > 0 {
+                buf[(nphi - m % nphi) % nphi] += gm.conj();
+            }
+        }
+        planner.plan_fft_inverse(nphi).process(&mut buf);
+        map[start..start + nphi]
+            .iter_mut()
+            .zip(buf.iter())
+            .for_each(|(x, c)| *x = c.re);
+    }
+    Ok(map)
+}
+
+/// Angular power spectrum C_l of `alm`.
+pub fn alm2cl(alm: &Alm) -> Vec<f64> {
+    (0..=alm.lmax)
+        .map(|l| {
+            let p: f64 = (0..=l)
+                .map(|m| {
+                    let n = alm.get(l, m).norm_sqr();
+                    if m == 0 {
+                        n
+                    } else {
+                        2.0 * n
+                    }
+                })
+                .sum();
+            p / (2 * l + 1) as f64
+        })
+        .collect()
+}
+
+/// Beam window function B_l = sqrt(C_l / C_0), normalised to B_0 = 1; for a
+/// beam symmetric about its axis this is the usual Legendre transform.
+pub fn beam_bl(beam: &[f64], lmax: usize) -> DbfResult<Vec<f64>> {
+    let cl = alm2cl(&map2alm_iter(beam, lmax, DEFAULT_ITER)?);
+    if cl[0].is_nan() || cl[0] <= 0.0 {
+        return Err(DbfError::InvalidValue {
+            what: "beam",
+            reason: "has no monopole".to_string(),
+        });
+    }
+    Ok(cl.iter().map(|&c| (c / cl[0]).sqrt()).collect())
+}
+
+/// B_l of a gaussian beam of the given fwhm.
+pub fn gaussian_bl(fwhm_deg: f64, lmax: usize) -> Vec<f64> {
+    let sigma = fwhm_deg.to_radians() / (8.0 * 2_f64.ln()).sqrt();
+    (0..=lmax)
+        .map(|l| (-0.5 * (l * (l + 1)) as f64 * sigma * sigma).exp())
+        .collect()
+}
+
+/// Multiplies the harmonic coefficients of a RING map up to `lmax` by `bl`,
+/// dropping everything above `lmax`.
+pub fn smooth(map: &[f64], bl: &[f64], lmax: usize) -> DbfResult<Vec<f64>> {
+    if bl.len() <= lmax {
+        return Err(DbfError::LengthMismatch {
+            what: "bl",
+            expected: lmax + 1,
+            found: bl.len(),
+        });
+    }
+    let nside = checked_npix2nside(map.len())?;
+    let mut alm = map2alm_iter(map, lmax, DEFAULT_ITER)?;
+    alm.scale_l(bl);
+    alm2map(&alm, nside)
+}
+
+/// Removes structure above `lmax` from a RING map.
+pub fn band_limit(map: &[f64], lmax: usize) -> DbfResult<Vec<f64>> {
+    smooth(map, &vec![1.0; lmax + 1], lmax)
+}
+
+/// Sky as seen through `beam` pointed everywhere, treating the beam as
+/// symmetric about its axis (only its B_l is used).
+pub fn convolve(sky: &[f64], beam: &[f64], lmax: usize) -> DbfResult<Vec<f64>> {
+    check_len("beam", sky.len(), beam.len())?;
+    smooth(sky, &beam_bl(beam, lmax)?, lmax)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn single_alm_round_trip() {
+        let (lmax, nside) = (8, 16);
+        let mut alm = Alm::new(lmax);
+        let i = alm.index(5, 3);
+        alm.data[i] = Complex::new(0.7, -0.4);
+        let map = alm2map(&alm, nside).unwrap();
+        let back = map2alm_iter(&map, lmax, DEFAULT_ITER).unwrap();
+        for m in 0..=lmax {
+            for l in m..=lmax {
+                let err = (back.get(l, m) - alm.get(l, m)).norm();
+                assert!(err < 1e-6, "a_{},{} off by {}", l, m, err);
+            }
+        }
+    }
+
+    #[test]
+    fn monopole_is_constant() {
+        // Y_00 = 1 / sqrt(4 pi)
+        let mut alm = Alm::new(4);
+        alm.data[0] = Complex::new((4.0 * PI).sqrt(), 0.0);
+        for x in alm2map(&alm, 4).unwrap() {
+            assert!((x - 1.0).abs() < 1e-12);
+        }
+    }
+}
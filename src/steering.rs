@@ -0,0 +1,201 @@
+use std::f64::consts::PI;
+
+use scorus::{
+    coordinates::{SphCoord, Vec3d},
+    healpix::{pix::pix2vec_ring, utils::nside2npix},
+};
+
+use crate::{
+    arbitrary_array::array_factor,
+    constants::LIGHT_SPEED,
+    error::{check_array_lists, check_len, DbfResult},
+};
+
+/// How the per-element steering is realised in the beamformer.
+/// `TrueTimeDelay` applies the delays at every frequency, while `PhaseOnly`
+/// freezes the corresponding phases at `ref_freq_hz`, which makes the beam squint.
+#[derive(Clone, Copy, Debug)]
+pub enum Steering {
+    TrueTimeDelay,
+    PhaseOnly { ref_freq_hz: f64 },
+}
+
+impl Steering {
+    pub fn phase(&self, delay: &[f64], freq_hz: f64) -> Vec<f64> {
+        match *self {
+            Steering::TrueTimeDelay => delay2phase(delay, freq_hz),
+            Steering::PhaseOnly { ref_freq_hz } => delay2phase(delay, ref_freq_hz),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct SquintReport {
+    pub freq_hz: f64,
+    pub peak: SphCoord<f64>,
+    pub squint_deg: f64,
+    pub gain_loss_db: f64,
+}
+
+pub fn pointing_vec(az_from_north: f64, zenith: f64) -> Vec3d<f64> {
+    Vec3d::from_sph_coord(SphCoord::new(zenith, PI / 2.0 - az_from_north))
+}
+
+pub fn calc_delay_from_pointing(
+    x_list: &[f64],
+    y_list: &[f64],
+    z_list: &[f64],
+    az_from_north: f64,
+    zenith: f64,
+) -> Vec<f64> {
+    let dir = pointing_vec(az_from_north, zenith);
+    x_list
+        .iter()
+        .zip(y_list.iter().zip(z_list.iter()))
+        .map(|(&x, (&y, &z))| (dir[0] * x + dir[1] * y + dir[2] * z) / LIGHT_SPEED)
+        .collect()
+}
+
+pub fn delay2phase(delay: &[f64], freq_hz: f64) -> Vec<f64> {
+    delay.iter().map(|&t| 2.0 * PI * freq_hz * t).collect()
+}
+
+fn angle_between(a: &Vec3d<f64>, b: &Vec3d<f64>) -> f64 {
+    (a[0] * b[0] + a[1] * b[1] + a[2] * b[2])
+        .clamp(-1.0, 1.0)
+        .acos()
+}
+
+fn normalized(v: [f64; 3]) -> Vec3d<f64> {
+    let r = (v[0] * v[0] + v[1] * v[1] + v[2] * v[2]).sqrt();
+    Vec3d::new(v[0] / r, v[1] / r, v[2] / r)
+}
+
+/// Two unit vectors spanning the tangent plane at `p`.
+fn tangent_basis(p: &Vec3d<f64>) -> (Vec3d<f64>, Vec3d<f64>) {
+    let a = if p[2].abs() < 0.9 {
+        [0.0, 0.0, 1.0]
+    } else {
+        [1.0, 0.0, 0.0]
+    };
+    let e1 = normalized([
+        a[1] * p[2] - a[2] * p[1],
+        a[2] * p[0] - a[0] * p[2],
+        a[0] * p[1] - a[1] * p[0],
+    ]);
+    let e2 = normalized([
+        p[1] * e1[2] - p[2] * e1[1],
+        p[2] * e1[0] - p[0] * e1[2],
+        p[0] * e1[1] - p[1] * e1[0],
+    ]);
+    (e1, e2)
+}
+
+/// Locates the maximum of the array factor above the horizon, first on a
+/// HEALPix grid of resolution `nside` and then by a local pattern search.
+#[allow(clippy::too_many_arguments)]
+pub fn find_beam_peak(
+    x_list: &[f64],
+    y_list: &[f64],
+    z_list: &[f64],
+    w_list: &[f64],
+    phi_list: &[f64],
+    freq_hz: f64,
+    nside: usize,
+) -> DbfResult<Vec3d<f64>> {
+    check_array_lists(&[
+        ("x_list", x_list),
+        ("y_list", y_list),
+        ("z_list", z_list),
+        ("w_list", w_list),
+        ("phi_list", phi_list),
+    ])?;
+    let lambda = LIGHT_SPEED / freq_hz;
+    let f = |p: &Vec3d<f64>| array_factor(p, x_list, y_list, z_list, w_list, phi_list, lambda);
+    let npix = nside2npix(nside);
+    let (mut peak, mut peak_value) = (0..npix / 2)
+        .map(|i| {
+            let p = pix2vec_ring::<f64>(nside, i);
+            let v = f(&p);
+            (p, v)
+        })
+        .fold((Vec3d::new(0.0, 0.0, 1.0), f64::MIN), |a, b| {
+            if b.1 > a.1 {
+                b
+            } else {
+                a
+            }
+        });
+
+    let mut step = (4.0 * PI / npix as f64).sqrt();
+    while step > 1e-7 {
+        let (e1, e2) = tangent_basis(&peak);
+        let mut moved = false;
+        for (da, db) in [
+            (1.0, 0.0),
+            (-1.0, 0.0),
+            (0.0, 1.0),
+            (0.0, -1.0),
+            (1.0, 1.0),
+            (1.0, -1.0),
+            (-1.0, 1.0),
+            (-1.0, -1.0),
+        ] {
+            let p = normalized([
+                peak[0] + step * (da * e1[0] + db * e2[0]),
+                peak[1] + step * (da * e1[1] + db * e2[1]),
+                peak[2] + step * (da * e1[2] + db * e2[2]),
+            ]);
+            let v = f(&p);
+            if v > peak_value {
+                peak = p;
+                peak_value = v;
+                moved = true;
+            }
+        }
+        if !moved {
+            step /= 2.0;
+        }
+    }
+    Ok(peak)
+}
+
+/// Squint of the beam peak away from `target` and the gain loss towards
+/// `target`, relative to a coherent sum of all elements, for each frequency.
+#[allow(clippy::too_many_arguments)]
+pub fn calc_squint(
+    x_list: &[f64],
+    y_list: &[f64],
+    z_list: &[f64],
+    w_list: &[f64],
+    delay: &[f64],
+    steering: Steering,
+    target: &Vec3d<f64>,
+    freqs_hz: &[f64],
+    nside: usize,
+) -> DbfResult<Vec<SquintReport>> {
+    check_len("delay", x_list.len(), delay.len())?;
+    let full_gain = w_list.iter().map(|w| w.abs()).sum::<f64>().powi(2);
+    freqs_hz
+        .iter()
+        .map(|&freq_hz| {
+            let phi_list = steering.phase(delay, freq_hz);
+            let peak = find_beam_peak(x_list, y_list, z_list, w_list, &phi_list, freq_hz, nside)?;
+            let gain = array_factor(
+                target,
+                x_list,
+                y_list,
+                z_list,
+                w_list,
+                &phi_list,
+                LIGHT_SPEED / freq_hz,
+            );
+            Ok(SquintReport {
+                freq_hz,
+                peak: SphCoord::from_xyz(peak[0], peak[1], peak[2]),
+                squint_deg: angle_between(&peak, target).to_degrees(),
+                gain_loss_db: 10.0 * (gain / full_gain).log10(),
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,266 @@
+//! Analytic target beams centred on the zenith.
+//!
+//! ```yaml
+//! shape: {gaussian: {sigma_deg: 10.0, sigma_minor_deg: 5.0, pa_deg: 30.0}}
+//! horizon_deg: 10.0
+//! ref_freq_mhz: 75.0
+//! ```
+
+use std::f64::consts::{FRAC_2_PI, FRAC_PI_4, PI};
+
+use serde::{Deserialize, Serialize};
+
+use scorus::healpix::{pix::pix2ang_ring, utils::nside2npix};
+
+use crate::{
+    error::{DbfError, DbfResult},
+    utils::{check_nside, hemi_npix},
+};
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TargetShape {
+    /// exp(-theta^2 / 2 sigma^2), with sigma varying from `sigma_deg` along
+    /// the major axis to `sigma_minor_deg` (circular if not given)
+    Gaussian {
+        sigma_deg: f64,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        sigma_minor_deg: Option<f64>,
+        /// azimuth of the major axis, from north towards east
+        #[serde(default)]
+        pa_deg: f64,
+    },
+    /// cos(theta)^n
+    CosN { n: f64 },
+    /// (2 J1(x) / x)^2 of a uniformly illuminated circular aperture
+    Airy { fwhm_deg: f64 },
+    /// 1 within `radius_deg`, falling to 0 over `taper_deg` as a raised cosine
+    FlatTop { radius_deg: f64, taper_deg: f64 },
+}
+
+impl TargetShape {
+    pub fn gaussian(sigma_deg: f64) -> Self {
+        TargetShape::Gaussian {
+            sigma_deg,
+            sigma_minor_deg: None,
+            pa_deg: 0.0,
+        }
+    }
+
+    fn check(&self) -> DbfResult<()> {
+        let positive = |what, x: f64| {
+            if x.is_nan() || x <= 0.0 {
+                Err(DbfError::InvalidValue {
+                    what,
+                    reason: format!("must be positive, found {}", x),
+                })
+            } else {
+                Ok(())
+            }
+        };
+        match *self {
+            TargetShape::Gaussian {
+                sigma_deg,
+                sigma_minor_deg,
+                ..
+            } => {
+                positive("sigma", sigma_deg)?;
+                sigma_minor_deg.map_or(Ok(()), |s| positive("sigma_minor", s))
+            }
+            TargetShape::CosN { n } => positive("n", n),
+            TargetShape::Airy { fwhm_deg } => positive("fwhm", fwhm_deg),
+            TargetShape::FlatTop {
+                radius_deg,
+                taper_deg,
+            } => {
+                positive("radius", radius_deg)?;
+                if taper_deg.is_nan() || taper_deg < 0.0 {
+                    Err(DbfError::InvalidValue {
+                        what: "taper",
+                        reason: format!("must not be negative, found {}", taper_deg),
+                    })
+                } else {
+                    Ok(())
+                }
+            }
+        }
+    }
+
+    /// The same shape with all angular widths multiplied by `s`.
+    pub fn scaled(&self, s: f64) -> Self {
+        match *self {
+            TargetShape::Gaussian {
+                sigma_deg,
+                sigma_minor_deg,
+                pa_deg,
+            } => TargetShape::Gaussian {
+                sigma_deg: sigma_deg * s,
+                sigma_minor_deg: sigma_minor_deg.map(|x| x * s),
+                pa_deg,
+            },
+            // cos^n ~ exp(-n theta^2 / 2) near the zenith
+            TargetShape::CosN { n } => TargetShape::CosN { n: n / (s * s) },
+            TargetShape::Airy { fwhm_deg } => TargetShape::Airy {
+                fwhm_deg: fwhm_deg * s,
+            },
+            TargetShape::FlatTop {
+                radius_deg,
+                taper_deg,
+            } => TargetShape::FlatTop {
+                radius_deg: radius_deg * s,
+                taper_deg: taper_deg * s,
+            },
+        }
+    }
+
+    /// Gain at zenith angle `theta` and azimuth `az` (from north towards
+    /// east), both in rad, normalised to 1 at the zenith.
+    pub fn gain(&self, theta: f64, az: f64) -> f64 {
+        match *self {
+            TargetShape::Gaussian {
+                sigma_deg,
+                sigma_minor_deg,
+                pa_deg,
+            } => {
+                let a = sigma_deg.to_radians();
+                let b = sigma_minor_deg.unwrap_or(sigma_deg).to_radians();
+                let (s, c) = (az - pa_deg.to_radians()).sin_cos();
+                (-0.5 * theta * theta * ((c / a).powi(2) + (s / b).powi(2))).exp()
+            }
+            TargetShape::CosN { n } => {
+                if theta < PI / 2.0 {
+                    theta.cos().powf(n)
+                } else {
+                    0.0
+                }
+            }
+            TargetShape::Airy { fwhm_deg } => {
+                // 2 J1(x) / x falls to 1 / sqrt(2) at x = 1.6163
+                let x = 1.616_339_9 * theta.sin() / (fwhm_deg.to_radians() / 2.0).sin();
+                if x.abs() < 1e-8 {
+                    1.0
+                } else {
+                    (2.0 * bessel_j1(x) / x).powi(2)
+                }
+            }
+            TargetShape::FlatTop {
+                radius_deg,
+                taper_deg,
+            } => {
+                let t = theta.to_degrees();
+                if t <= radius_deg {
+                    1.0
+                } else if t < radius_deg + taper_deg {
+                    0.5 * (1.0 + (PI * (t - radius_deg) / taper_deg).cos())
+                } else {
+                    0.0
+                }
+            }
+        }
+    }
+}
+
+/// J1 by the rational and asymptotic approximations of Numerical Recipes,
+/// accurate to about 1e-8.
+fn bessel_j1(x: f64) -> f64 {
+    let ax = x.abs();
+    if ax < 8.0 {
+        let y = x * x;
+        let n = x
+            * (72362614232.0
+                + y * (-7895059235.0
+                    + y * (242396853.1
+                        + y * (-2972611.439 + y * (15704.48260 + y * -30.16036606)))));
+        let d = 144725228442.0
+            + y * (2300535178.0 + y * (18583304.74 + y * (99447.43394 + y * (376.9991397 + y))));
+        n / d
+    } else {
+        let z = 8.0 / ax;
+        let y = z * z;
+        let xx = ax - 3.0 * FRAC_PI_4;
+        let p = 1.0
+            + y * (0.183105e-2
+                + y * (-0.3516396496e-4 + y * (0.2457520174e-5 + y * -0.240337019e-6)));
+        let q = 0.04687499995
+            + y * (-0.2002690873e-3
+                + y * (0.8449199096e-5 + y * (-0.88228987e-6 + y * 0.105787412e-6)));
+        let j = (FRAC_2_PI / ax).sqrt() * (xx.cos() * p - z * xx.sin() * q);
+        if x < 0.0 {
+            -j
+        } else {
+            j
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TargetBeam {
+    pub shape: TargetShape,
+    /// zero below this elevation
+    #[serde(default)]
+    pub horizon_deg: f64,
+    /// freq at which `shape` is given, the widths scaling as 1 / freq at
+    /// other freqs; frequency independent if not given
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ref_freq_mhz: Option<f64>,
+}
+
+impl TargetBeam {
+    pub fn new(shape: TargetShape) -> Self {
+        TargetBeam {
+            shape,
+            horizon_deg: 0.0,
+            ref_freq_mhz: None,
+        }
+    }
+
+    /// Shape at `freq_mhz`.
+    pub fn shape_at(&self, freq_mhz: f64) -> TargetShape {
+        match self.ref_freq_mhz {
+            Some(f0) => self.shape.scaled(f0 / freq_mhz),
+            None => self.shape.clone(),
+        }
+    }
+
+    /// RING map at `nside` and `freq_mhz`; the freq is only used if
+    /// `ref_freq_mhz` is given.
+    pub fn healpix(&self, nside: usize, freq_mhz: f64) -> DbfResult<Vec<f64>> {
+        check_nside(nside)?;
+        self.first_pixels(nside, freq_mhz, nside2npix(nside))
+    }
+
+    /// `healpix` over the upper hemisphere only (see `utils::hemi_npix`),
+    /// any part of the beam below the horizon being cut.
+    pub fn healpix_hemi(&self, nside: usize, freq_mhz: f64) -> DbfResult<Vec<f64>> {
+        check_nside(nside)?;
+        self.first_pixels(nside, freq_mhz, hemi_npix(nside))
+    }
+
+    /// First `npix` pixels of the RING map.
+    fn first_pixels(&self, nside: usize, freq_mhz: f64, npix: usize) -> DbfResult<Vec<f64>> {
+        self.shape.check()?;
+        if self.ref_freq_mhz.is_some() && (freq_mhz.is_nan() || freq_mhz <= 0.0) {
+            return Err(DbfError::InvalidValue {
+                what: "freq",
+                reason: format!("must be positive, found {}", freq_mhz),
+            });
+        }
+        let shape = self.shape_at(freq_mhz);
+        let theta_max = (90.0 - self.horizon_deg).to_radians();
+        Ok((0..npix)
+            .map(|i| {
+                let p = pix2ang_ring::<f64>(nside, i);
+                if p.pol > theta_max {
+                    0.0
+                } else {
+                    shape.gain(p.pol, PI / 2.0 - p.az)
+                }
+            })
+            .collect())
+    }
+
+    /// One RING map per freq.
+    pub fn healpix_cube(&self, nside: usize, freqs_mhz: &[f64]) -> DbfResult<Vec<Vec<f64>>> {
+        freqs_mhz.iter().map(|&f| self.healpix(nside, f)).collect()
+    }
+}
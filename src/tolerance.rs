@@ -0,0 +1,204 @@
+use std::f64::consts::PI;
+
+use num::complex::Complex;
+
+use rand::Rng;
+
+use serde::{Deserialize, Serialize};
+
+use scorus::healpix::{pix::pix2vec_ring, utils::nside2npix};
+
+use crate::{
+    constants::LIGHT_SPEED,
+    error::{check_array_lists, check_len, DbfError, DbfResult},
+    utils::check_nside,
+};
+
+/// Random errors applied to every element of an array in one realisation.
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize)]
+pub struct Tolerance {
+    /// probability that an element is dead
+    pub dropout_prob: f64,
+    /// rms position error along each axis, in metre
+    pub pos_sigma: f64,
+    /// rms fractional amplitude error
+    pub amp_sigma: f64,
+    /// rms phase error, in rad
+    pub phase_sigma: f64,
+    /// rms of the linear gain slope across the sky of each element beam
+    pub beam_sigma: f64,
+}
+
+#[derive(Clone, Debug)]
+pub struct PerturbedArray {
+    pub x_list: Vec<f64>,
+    pub y_list: Vec<f64>,
+    pub z_list: Vec<f64>,
+    pub w_list: Vec<f64>,
+    pub phi_list: Vec<f64>,
+    /// element voltage pattern variation, `1 + a * nx + b * ny`
+    pub beam_tilt: Vec<(f64, f64)>,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Stats {
+    pub mean: f64,
+    pub p05: f64,
+    pub p50: f64,
+    pub p95: f64,
+    pub worst: f64,
+    /// num of NaN values left out, e.g. of realisations with zero gain
+    pub dropped: usize,
+}
+
+/// Standard normal deviate by the Box-Muller transform.
+pub fn gaussian<R: Rng>(rng: &mut R) -> f64 {
+    let u1: f64 = 1.0 - rng.gen::<f64>();
+    let u2: f64 = rng.gen::<f64>();
+    (-2.0 * u1.ln()).sqrt() * (2.0 * PI * u2).cos()
+}
+
+impl Tolerance {
+    pub fn realise<R: Rng>(
+        &self,
+        x_list: &[f64],
+        y_list: &[f64],
+        z_list: &[f64],
+        w_list: &[f64],
+        phi_list: &[f64],
+        rng: &mut R,
+    ) -> DbfResult<PerturbedArray> {
+        let n = check_array_lists(&[
+            ("x_list", x_list),
+            ("y_list", y_list),
+            ("z_list", z_list),
+            ("w_list", w_list),
+            ("phi_list", phi_list),
+        ])?;
+        let mut result = PerturbedArray {
+            x_list: Vec::with_capacity(n),
+            y_list: Vec::with_capacity(n),
+            z_list: Vec::with_capacity(n),
+            w_list: Vec::with_capacity(n),
+            phi_list: Vec::with_capacity(n),
+            beam_tilt: Vec::with_capacity(n),
+        };
+        for i in 0..n {
+            let alive = rng.gen::<f64>() >= self.dropout_prob;
+            result
+                .x_list
+                .push(x_list[i] + self.pos_sigma * gaussian(rng));
+            result
+                .y_list
+                .push(y_list[i] + self.pos_sigma * gaussian(rng));
+            result
+                .z_list
+                .push(z_list[i] + self.pos_sigma * gaussian(rng));
+            result.w_list.push(if alive {
+                w_list[i] * (1.0 + self.amp_sigma * gaussian(rng))
+            } else {
+                0.0
+            });
+            result
+                .phi_list
+                .push(phi_list[i] + self.phase_sigma * gaussian(rng));
+            result.beam_tilt.push((
+                self.beam_sigma * gaussian(rng),
+                self.beam_sigma * gaussian(rng),
+            ));
+        }
+        Ok(result)
+    }
+}
+
+impl PerturbedArray {
+    /// Checks that every element has a value in each list.
+    pub fn check(&self) -> DbfResult<usize> {
+        let n = check_array_lists(&[
+            ("x_list", &self.x_list),
+            ("y_list", &self.y_list),
+            ("z_list", &self.z_list),
+            ("w_list", &self.w_list),
+            ("phi_list", &self.phi_list),
+        ])?;
+        check_len("beam_tilt", n, self.beam_tilt.len())?;
+        Ok(n)
+    }
+}
+
+pub fn calc_perturbed_array_beam(
+    nside: usize,
+    array: &PerturbedArray,
+    freq_hz: f64,
+    ground_cut: bool,
+) -> DbfResult<Vec<f64>> {
+    check_nside(nside)?;
+    let n = array.check()?;
+    let npix = nside2npix(nside);
+    let lambda = LIGHT_SPEED / freq_hz;
+    Ok((0..npix)
+        .map(|i| {
+            if i < npix / 2 || !ground_cut {
+                let pointing = pix2vec_ring::<f64>(nside, i);
+                (0..n)
+                    .map(|k| {
+                        let dl = pointing[0] * array.x_list[k]
+                            + pointing[1] * array.y_list[k]
+                            + pointing[2] * array.z_list[k];
+                        let phase = dl / lambda * 2.0 * PI;
+                        let (a, b) = array.beam_tilt[k];
+                        let g = 1.0 + a * pointing[0] + b * pointing[1];
+                        Complex::from_polar(array.w_list[k] * g, phase - array.phi_list[k])
+                    })
+                    .sum::<Complex<f64>>()
+                    .norm_sqr()
+            } else {
+                0.0
+            }
+        })
+        .collect())
+}
+
+fn percentile(sorted: &[f64], p: f64) -> f64 {
+    let x = p * (sorted.len() - 1) as f64;
+    let i = x.floor() as usize;
+    let j = x.ceil() as usize;
+    sorted[i] + (sorted[j] - sorted[i]) * (x - i as f64)
+}
+
+/// Summary of a metric over all realisations, leaving out NaN values;
+/// `larger_is_worse` selects which end of the distribution is reported as
+/// the worst case. All stats are NaN if every value is.
+pub fn summarize(values: &[f64], larger_is_worse: bool) -> DbfResult<Stats> {
+    if values.is_empty() {
+        return Err(DbfError::InvalidValue {
+            what: "values",
+            reason: "at least one realisation is needed".to_string(),
+        });
+    }
+    let mut sorted: Vec<f64> = values.iter().cloned().filter(|x| !x.is_nan()).collect();
+    let dropped = values.len() - sorted.len();
+    if sorted.is_empty() {
+        return Ok(Stats {
+            mean: f64::NAN,
+            p05: f64::NAN,
+            p50: f64::NAN,
+            p95: f64::NAN,
+            worst: f64::NAN,
+            dropped,
+        });
+    }
+    sorted.sort_by(f64::total_cmp);
+    Ok(Stats {
+        mean: sorted.iter().sum::<f64>() / sorted.len() as f64,
+        p05: percentile(&sorted, 0.05),
+        p50: percentile(&sorted, 0.5),
+        p95: percentile(&sorted, 0.95),
+        worst: if larger_is_worse {
+            sorted[sorted.len() - 1]
+        } else {
+            sorted[0]
+        },
+        dropped,
+    })
+}
@@ -1,12 +1,50 @@
 use scorus::healpix::{
     pix::{pix2ring_ring, ring2z_ring},
-    utils::{npix2nside, nring2nside, nside2npix, nside2nring},
+    utils::nside2npix,
 };
 
-pub fn integrate_az(hmap: &[f64]) -> (Vec<f64>, Vec<usize>, Vec<f64>) {
+use crate::{
+    error::{check_len, DbfError, DbfResult},
+    target::{TargetBeam, TargetShape},
+};
+
+/// nside of a map with `npix` pixels, rejecting lengths that are not 12 * nside^2.
+pub fn checked_npix2nside(npix: usize) -> DbfResult<usize> {
+    let nside = ((npix / 12) as f64).sqrt().round() as usize;
+    if nside > 0 && nside2npix(nside) == npix {
+        Ok(nside)
+    } else {
+        Err(DbfError::InvalidNpix(npix))
+    }
+}
+
+/// nside of a map with `nring` rings, rejecting counts that are not 4 * nside - 1.
+pub fn checked_nring2nside(nring: usize) -> DbfResult<usize> {
+    if nring >= 3 && (nring + 1) % 4 == 0 {
+        Ok((nring + 1) / 4)
+    } else {
+        Err(DbfError::InvalidNring(nring))
+    }
+}
+
+pub(crate) fn check_nside(nside: usize) -> DbfResult<()> {
+    if nside > 0 {
+        Ok(())
+    } else {
+        Err(DbfError::InvalidValue {
+            what: "nside",
+            reason: "must be positive".to_string(),
+        })
+    }
+}
+
+/// Azimuthal mean per ring, the pixel count per ring and the ring
+/// colatitudes of a full-sky RING map, or of its upper hemisphere (see
+/// `hemi_npix`), which only has the rings down to the equator.
+pub fn integrate_az(hmap: &[f64]) -> DbfResult<(Vec<f64>, Vec<usize>, Vec<f64>)> {
     let npix = hmap.len();
-    let nside = npix2nside(npix);
-    let nring = nside2nring(nside);
+    let nside = checked_beam_npix2nside(npix)?;
+    let nring = pix2ring_ring(nside, npix - 1);
     let mut wgt = vec![0_usize; nring];
     let mut mean_values = vec![0.0; nring];
     let ring_idx: Vec<_> = (0..npix).map(|ipix| pix2ring_ring(nside, ipix)).collect();
@@ -22,28 +60,85 @@ pub fn integrate_az(hmap: &[f64]) -> (Vec<f64>, Vec<usize>, Vec<f64>) {
     let theta = (1..=nring)
         .map(|iring| ring2z_ring::<f64>(nside, iring).acos())
         .collect();
-    (mean_values, wgt, theta)
+    Ok((mean_values, wgt, theta))
 }
 
-pub fn averaged_beam_to_healpix(beam: &[f64]) -> Vec<f64> {
+pub fn averaged_beam_to_healpix(beam: &[f64]) -> DbfResult<Vec<f64>> {
     let nring = beam.len();
-    let nside = nring2nside(nring);
+    let nside = checked_nring2nside(nring)?;
     let npix = nside2npix(nside);
-    (0..npix)
+    Ok((0..npix)
         .map(|ipix| beam[pix2ring_ring(nside, ipix) - 1])
-        .collect()
+        .collect())
 }
 
-pub fn calc_averaged_ant_output(beam: &[f64], sky: &[f64]) -> f64 {
-    let beam_hp = averaged_beam_to_healpix(beam);
-    assert_eq!(beam_hp.len(), sky.len());
+pub fn calc_averaged_ant_output(beam: &[f64], sky: &[f64]) -> DbfResult<f64> {
+    let beam_hp = averaged_beam_to_healpix(beam)?;
+    check_len("sky", beam_hp.len(), sky.len())?;
     let norm = beam_hp.iter().sum::<f64>();
-    beam_hp
+    Ok(beam_hp
+        .iter()
+        .zip(sky.iter())
+        .map(|(&b, &s)| b * s)
+        .sum::<f64>()
+        / norm)
+}
+
+/// Beam-weighted mean of the sky, without averaging over LST. The beam may
+/// be given as its upper hemisphere only, the sky being full sky or the same
+/// hemisphere.
+pub fn calc_ant_output(beam: &[f64], sky: &[f64]) -> DbfResult<f64> {
+    let sky = match checked_hemi_npix2nside(beam.len()) {
+        Ok(nside) if sky.len() == nside2npix(nside) => &sky[..beam.len()],
+        _ => sky,
+    };
+    check_len("sky", beam.len(), sky.len())?;
+    let norm = beam.iter().sum::<f64>();
+    Ok(beam
         .iter()
         .zip(sky.iter())
         .map(|(&b, &s)| b * s)
         .sum::<f64>()
-        / norm
+        / norm)
+}
+
+/// Num of pixels of the upper hemisphere of a RING map, i.e. the first half
+/// of the map as kept by the ground cut.
+pub fn hemi_npix(nside: usize) -> usize {
+    nside2npix(nside) / 2
+}
+
+/// nside of an upper hemisphere map with `npix` pixels.
+pub fn checked_hemi_npix2nside(npix: usize) -> DbfResult<usize> {
+    checked_npix2nside(2 * npix).map_err(|_| DbfError::InvalidNpix(npix))
+}
+
+/// nside of a map with `npix` pixels, either full sky or its upper
+/// hemisphere; the two cannot be confused as 6 * n^2 is never 12 * m^2.
+pub fn checked_beam_npix2nside(npix: usize) -> DbfResult<usize> {
+    checked_npix2nside(npix).or_else(|_| checked_hemi_npix2nside(npix))
+}
+
+/// Full-sky RING map from its upper hemisphere, zero below.
+pub fn hemi2full(hemi: &[f64]) -> DbfResult<Vec<f64>> {
+    let nside = checked_hemi_npix2nside(hemi.len())?;
+    let mut full = hemi.to_vec();
+    full.resize(nside2npix(nside), 0.0);
+    Ok(full)
+}
+
+/// Checks that a beam at `nside` is either full sky or its upper hemisphere.
+pub(crate) fn check_beam_npix(what: &'static str, nside: usize, npix: usize) -> DbfResult<()> {
+    if npix == hemi_npix(nside) {
+        Ok(())
+    } else {
+        check_len(what, nside2npix(nside), npix)
+    }
+}
+
+/// Circular gaussian beam centred on the zenith, zero below the horizon.
+pub fn gaussian_beam(nside: usize, sigma_deg: f64) -> DbfResult<Vec<f64>> {
+    TargetBeam::new(TargetShape::gaussian(sigma_deg)).healpix(nside, 0.0)
 }
 
 pub use crate::arbitrary_array::calc_averaged_ant_output2;
@@ -0,0 +1,97 @@
+use ndarray::Array2;
+
+use wasm_bindgen::prelude::*;
+
+use crate::{
+    arbitrary_array::calc_array_beam,
+    array_cfg::ArrayCfg,
+    error::{DbfError, DbfResult},
+    projection::{project, Projection},
+    regular_array::quarter_wgt2pattern,
+};
+
+fn js_error<E: ToString>(e: E) -> JsValue {
+    JsValue::from_str(&e.to_string())
+}
+
+fn normalize(mut raster: Vec<f64>) -> Vec<f64> {
+    let peak = raster
+        .iter()
+        .cloned()
+        .filter(|x| x.is_finite())
+        .fold(0.0, f64::max);
+    if peak > 0.0 {
+        raster.iter_mut().for_each(|x| *x /= peak);
+    }
+    raster
+}
+
+fn parse_wgt(wgt_json: &str) -> Result<Array2<f64>, JsValue> {
+    let rows: Vec<Vec<f64>> = serde_json::from_str(wgt_json).map_err(js_error)?;
+    let h = rows.len();
+    let w = rows.first().map(|r| r.len()).unwrap_or(0);
+    if rows.iter().any(|r| r.len() != w) {
+        return Err(js_error(DbfError::InvalidValue {
+            what: "wgt",
+            reason: "rows have different lengths".to_string(),
+        }));
+    }
+    Array2::from_shape_vec((h, w), rows.into_iter().flatten().collect()).map_err(js_error)
+}
+
+fn to_raster(
+    beam: DbfResult<Vec<f64>>,
+    projection: &str,
+    width: usize,
+    height: usize,
+) -> Result<Vec<f64>, JsValue> {
+    let proj = Projection::from_name(projection).map_err(js_error)?;
+    project(&beam.map_err(js_error)?, proj, width, height)
+        .map(normalize)
+        .map_err(js_error)
+}
+
+/// Beam of an arbitrary array given as an array cfg in JSON
+/// (`{"ants": [{"pos": [x, y, z], "wgt": w, "delay": t}, ...]}`),
+/// as a `width` x `height` raster normalised to its peak.
+#[wasm_bindgen(js_name = arrayBeamRaster)]
+pub fn array_beam_raster(
+    layout_json: &str,
+    freq_mhz: f64,
+    nside: usize,
+    projection: &str,
+    width: usize,
+    height: usize,
+) -> Result<Vec<f64>, JsValue> {
+    let array_cfg: ArrayCfg = serde_json::from_str(layout_json).map_err(js_error)?;
+    let (x_list, y_list, z_list) = array_cfg.xyz_lists();
+    let freq_hz = freq_mhz * 1e6;
+    let beam = calc_array_beam(
+        nside,
+        &x_list,
+        &y_list,
+        &z_list,
+        &array_cfg.wgt_list(),
+        &array_cfg.phase_list(freq_hz),
+        freq_hz,
+        true,
+    );
+    to_raster(beam, projection, width, height)
+}
+
+/// Beam of a regular array from its quarter wgt, given in JSON as a list of
+/// rows starting with the central element.
+#[wasm_bindgen(js_name = quarterWgtRaster)]
+pub fn quarter_wgt_raster(
+    wgt_json: &str,
+    d: f64,
+    freq_mhz: f64,
+    nside: usize,
+    projection: &str,
+    width: usize,
+    height: usize,
+) -> Result<Vec<f64>, JsValue> {
+    let wgt = parse_wgt(wgt_json)?;
+    let beam = quarter_wgt2pattern(wgt.view(), d, freq_mhz, nside);
+    to_raster(beam, projection, width, height)
+}